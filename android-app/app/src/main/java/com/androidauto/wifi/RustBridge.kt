@@ -79,6 +79,21 @@ object RustBridge {
         return data.size
     }
     
+    /**
+     * Coalesce small messages on the chosen channels into batch frames.
+     * @param channelMask Bit n batches channel n; 0 turns batching off and
+     *     sends anything still queued
+     */
+    fun setBatching(channelMask: Int) {}
+    
+    /**
+     * Send batched messages whose 500µs deadline has passed.
+     * sendData and processIncomingData already do this; while batching is on
+     * and neither is being called, call this every 500µs from the send loop.
+     * @return Length of the batch frame sent, or 0 if nothing was due
+     */
+    fun flushBatch(): Int = 0
+    
     /**
      * Get connection statistics as JSON string.
      * @return JSON string with statistics
@@ -87,6 +102,29 @@ object RustBridge {
         return """{"connected":$connected,"bytes_sent":$bytesSent,"bytes_received":$bytesReceived,"session_id":$sessionId}"""
     }
     
+    /**
+     * Poll the next video source event (keyframe request or bitrate hint).
+     * @return JSON string describing the event, or null if none is pending
+     */
+    fun pollEvent(): String? = null
+    
+    /**
+     * Start recording bridge traffic to rotating capture files.
+     * @param directory Directory for capture files
+     * @param maxFileBytes Size at which a new file is started
+     * @param maxFiles Number of files kept (oldest deleted first)
+     * @return true if capture started
+     */
+    fun startCapture(directory: String, maxFileBytes: Long, maxFiles: Int): Boolean {
+        Log.w(TAG, "Capture not available in Kotlin stub")
+        return false
+    }
+    
+    /**
+     * Stop recording bridge traffic.
+     */
+    fun stopCapture() {}
+    
     /**
      * Perform handshake with ESP32.
      * @return true if handshake succeeded
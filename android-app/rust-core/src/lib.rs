@@ -36,17 +36,30 @@
 //! - `Java_com_androidauto_wifi_RustBridge_connect`: Connect to ESP32
 //! - `Java_com_androidauto_wifi_RustBridge_disconnect`: Disconnect
 //! - `Java_com_androidauto_wifi_RustBridge_sendData`: Send data to ESP32
+//! - `Java_com_androidauto_wifi_RustBridge_setBatching`: Coalesce small messages on chosen channels
+//! - `Java_com_androidauto_wifi_RustBridge_flushBatch`: Send batched messages whose deadline passed
 //! - `Java_com_androidauto_wifi_RustBridge_getStats`: Get connection statistics
+//! - `Java_com_androidauto_wifi_RustBridge_pollEvent`: Poll video source events (keyframe, bitrate)
+//! - `Java_com_androidauto_wifi_RustBridge_startCapture`: Record traffic to rotating capture files
+//! - `Java_com_androidauto_wifi_RustBridge_stopCapture`: Stop recording
 
-use jni::objects::{JClass, JObject, JString, JByteArray};
-use jni::sys::{jboolean, jbyteArray, jint, jlong, jstring, JNI_TRUE, JNI_FALSE};
+use jni::objects::{JClass, JString, JByteArray};
+use jni::sys::{jboolean, jint, jlong, jstring, JNI_TRUE, JNI_FALSE};
 use jni::JNIEnv;
 use log::{debug, error, info, warn, LevelFilter};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex, Once};
 use thiserror::Error;
 
-use shared::protocol::{ControlMessage, Message, FrameBuilder};
-use shared::buffer::ZeroCopyBuffer;
+use shared::batch::{parse_frames, BatchError, Batcher};
+use shared::protocol::{
+    ControlMessage, DataPayload, Header, Message, FrameBuilder, FRAME_OVERHEAD, MAX_PAYLOAD_SIZE,
+};
+use shared::capture::{CaptureSink, Direction, Recorder, RecorderConfig, CHANNEL_UNKNOWN};
+use shared::flow::{SendWindow, FEATURE_FLOW_CONTROL, MAX_CHANNELS};
+use shared::time::monotonic_us;
 
 // Initialize logging once
 static INIT_LOGGER: Once = Once::new();
@@ -66,6 +79,40 @@ pub enum BridgeError {
     NetworkError(String),
 }
 
+/// Maximum number of undelivered events kept for the Android side
+const MAX_PENDING_EVENTS: usize = 32;
+
+/// Packed payload capacity of a batch frame
+const BATCH_CAPACITY: usize = 1024;
+
+/// Events the Android side should act on, delivered as JSON by `pollEvent`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeEvent {
+    /// The bridge lost video sync; the encoder should emit an IDR frame
+    KeyframeRequested,
+    /// The encoder should adapt to the measured link capacity
+    BitrateHint {
+        /// Target video bitrate in kbit/s
+        target_kbps: u32,
+        /// Maximum frame rate
+        max_fps: u8,
+    },
+}
+
+impl BridgeEvent {
+    /// Map a control message from the ESP32 to an event, if it is one
+    pub fn from_control(msg: &ControlMessage) -> Option<Self> {
+        match *msg {
+            ControlMessage::RequestKeyframe => Some(Self::KeyframeRequested),
+            ControlMessage::BitrateHint { target_kbps, max_fps } => {
+                Some(Self::BitrateHint { target_kbps, max_fps })
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Connection state shared between JNI calls
 struct ConnectionState {
     /// Whether currently connected
@@ -78,6 +125,17 @@ struct ConnectionState {
     frame_builder: FrameBuilder,
     /// Session ID (from handshake)
     session_id: u32,
+    /// Per-channel send credits advertised by the ESP32 (unlimited unless
+    /// it negotiated flow control)
+    send_window: SendWindow,
+    /// Events waiting to be polled by the Android side
+    events: VecDeque<BridgeEvent>,
+    /// Traffic recorder, if capture is enabled
+    recorder: Option<Recorder>,
+    /// Coalesces small messages on `batch_channels` into batch frames
+    batcher: Batcher<BATCH_CAPACITY>,
+    /// Channels whose messages are batched, one bit per channel (0 = off)
+    batch_channels: u8,
     /// Statistics
     bytes_sent: u64,
     bytes_received: u64,
@@ -91,21 +149,123 @@ impl Default for ConnectionState {
             port: shared::protocol::MAX_PAYLOAD_SIZE as u16, // Use as placeholder
             frame_builder: FrameBuilder::new(),
             session_id: 0,
+            send_window: SendWindow::unlimited(),
+            events: VecDeque::new(),
+            recorder: None,
+            batcher: Batcher::default(),
+            batch_channels: 0,
             bytes_sent: 0,
             bytes_received: 0,
         }
     }
 }
 
+impl ConnectionState {
+    /// Queue or send `data` on `channel`, as `sendData` returns it
+    fn send(&mut self, channel: u8, data: &[u8], now_us: u64) -> jint {
+        self.flush_due(now_us);
+
+        // Hold back data the ESP32 has no buffer space for
+        if self.send_window.consume(channel, data.len()).is_err() {
+            debug!(
+                "Channel {} starved: {} bytes pending, {} credits",
+                channel,
+                data.len(),
+                self.send_window.credits(channel)
+            );
+            return 0;
+        }
+
+        let Some(payload) = DataPayload::new(data) else {
+            error!("Payload of {} bytes too large", data.len());
+            return -1;
+        };
+        let msg = Message::Data(payload);
+        if !(self.batches(channel) && self.push_batched(channel, &msg, now_us)) {
+            let mut frame = [0u8; MAX_PAYLOAD_SIZE + FRAME_OVERHEAD];
+            match self.frame_builder.build_frame(&msg, channel, &mut frame) {
+                Ok(len) => self.transmit(&frame[..len]),
+                Err(e) => {
+                    error!("Failed to build data frame: {:?}", e);
+                    return -1;
+                }
+            }
+        }
+        self.bytes_sent += data.len() as u64;
+
+        data.len() as jint
+    }
+
+    /// Put a built frame on the wire, capturing exactly what is sent
+    ///
+    /// Recorded under [`CHANNEL_UNKNOWN`] like received bytes, so both
+    /// directions of a capture deframe the same way.
+    fn transmit(&mut self, frame: &[u8]) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(Direction::Tx, CHANNEL_UNKNOWN, &[frame]);
+        }
+        // TODO: Send frame over TCP
+    }
+
+    /// Check if messages on `channel` are batched
+    fn batches(&self, channel: u8) -> bool {
+        (channel as usize) < MAX_CHANNELS && self.batch_channels & (1 << channel) != 0
+    }
+
+    /// Queue a message for batching, flushing when the batch is full or due
+    ///
+    /// Returns `false` if the message does not fit in a batch and must be
+    /// sent alone; anything queued before it has been flushed by then.
+    fn push_batched(&mut self, channel: u8, msg: &Message, now_us: u64) -> bool {
+        match self.batcher.push(msg, channel, now_us) {
+            Ok(full) => {
+                if full || self.batcher.is_due(now_us) {
+                    self.flush_batch(now_us);
+                }
+                true
+            }
+            Err(BatchError::Full) if !self.batcher.is_empty() => {
+                self.flush_batch(now_us);
+                self.push_batched(channel, msg, now_us)
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Flush the batch if its oldest message has waited past the deadline
+    ///
+    /// Checked on every `sendData` and `processIncomingData`, so the Android
+    /// side only needs `flushBatch` while the link is otherwise idle.
+    fn flush_due(&mut self, now_us: u64) -> usize {
+        if !self.batcher.is_due(now_us) {
+            return 0;
+        }
+        self.flush_batch(now_us)
+    }
+
+    /// Emit the queued messages as one batch frame; returns its length
+    fn flush_batch(&mut self, now_us: u64) -> usize {
+        let mut frame = [0u8; BATCH_CAPACITY + FRAME_OVERHEAD];
+        match self.batcher.flush(&mut self.frame_builder, &mut frame, now_us) {
+            Ok(len) => {
+                if len > 0 {
+                    self.transmit(&frame[..len]);
+                }
+                len
+            }
+            Err(e) => {
+                error!("Failed to build batch frame: {:?}", e);
+                0
+            }
+        }
+    }
+}
+
 // Global state (wrapped in Arc<Mutex> for thread safety)
 lazy_static::lazy_static! {
     static ref STATE: Arc<Mutex<ConnectionState>> = Arc::new(Mutex::new(ConnectionState::default()));
 }
 
-// Required for lazy_static
-#[macro_use]
-extern crate lazy_static;
-
 /// Initialize the Rust native library
 ///
 /// Called from Kotlin:
@@ -114,7 +274,7 @@ extern crate lazy_static;
 /// ```
 #[no_mangle]
 pub extern "system" fn Java_com_androidauto_wifi_RustBridge_init(
-    mut env: JNIEnv,
+    _env: JNIEnv,
     _class: JClass,
 ) -> jboolean {
     // Initialize Android logger (only once)
@@ -186,6 +346,14 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_disconnect(
     state.connected = false;
     state.esp32_ip = None;
     state.session_id = 0;
+    state.send_window.reset();
+    state.batcher = Batcher::default();
+    state.events.clear();
+    if let Some(recorder) = state.recorder.as_mut() {
+        if let Err(e) = recorder.end_session() {
+            warn!("Failed to record session end: {:?}", e);
+        }
+    }
 
     info!("Disconnected");
 }
@@ -212,10 +380,12 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_isConnected(
 /// external fun sendData(channel: Int, data: ByteArray): Int
 /// ```
 ///
-/// Returns: Number of bytes sent, or -1 on error
+/// Returns: Number of bytes sent, 0 if the channel is out of credits
+/// (retry after the ESP32 sends a window update; only when the ESP32
+/// negotiated flow control), or -1 on error
 #[no_mangle]
 pub extern "system" fn Java_com_androidauto_wifi_RustBridge_sendData(
-    mut env: JNIEnv,
+    env: JNIEnv,
     _class: JClass,
     channel: jint,
     data: JByteArray,
@@ -244,12 +414,48 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_sendData(
 
     debug!("Sending {} bytes on channel {}", data_len, channel);
 
-    // TODO: Actually send data over TCP
-    // For now, just update statistics
+    STATE.lock().unwrap().send(channel as u8, &rust_data, monotonic_us())
+}
+
+/// Choose the channels whose messages are batched
+///
+/// Called from Kotlin:
+/// ```kotlin
+/// external fun setBatching(channelMask: Int)
+/// ```
+///
+/// Bit `n` of `channelMask` batches channel `n` (e.g. input and sensors);
+/// 0 turns batching off and sends anything still queued.
+#[no_mangle]
+pub extern "system" fn Java_com_androidauto_wifi_RustBridge_setBatching(
+    _env: JNIEnv,
+    _class: JClass,
+    channel_mask: jint,
+) {
     let mut state = STATE.lock().unwrap();
-    state.bytes_sent += data_len as u64;
+    state.batch_channels = channel_mask as u8;
+    if channel_mask == 0 {
+        state.flush_batch(monotonic_us());
+    }
+    info!("Batching channel mask: {:#04x}", state.batch_channels);
+}
 
-    data_len as jint
+/// Send batched messages once the oldest has waited past the deadline
+///
+/// Called from Kotlin:
+/// ```kotlin
+/// external fun flushBatch(): Int
+/// ```
+///
+/// `sendData` and `processIncomingData` already flush a due batch; call
+/// this every 500µs while batching is on and neither is being called.
+/// Returns the batch frame length, or 0 if nothing was due.
+#[no_mangle]
+pub extern "system" fn Java_com_androidauto_wifi_RustBridge_flushBatch(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    STATE.lock().unwrap().flush_due(monotonic_us()) as jint
 }
 
 /// Get connection statistics as JSON
@@ -260,17 +466,26 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_sendData(
 /// ```
 #[no_mangle]
 pub extern "system" fn Java_com_androidauto_wifi_RustBridge_getStats(
-    mut env: JNIEnv,
+    env: JNIEnv,
     _class: JClass,
 ) -> jstring {
     let state = STATE.lock().unwrap();
+    let batch = state.batcher.stats();
     
     let stats_json = format!(
-        r#"{{"connected":{},"bytes_sent":{},"bytes_received":{},"session_id":{}}}"#,
+        concat!(
+            r#"{{"connected":{},"bytes_sent":{},"bytes_received":{},"session_id":{},"credit_starvations":{},"#,
+            r#""batches":{},"batched_messages":{},"messages_per_batch":{:.2},"batch_bytes_saved":{}}}"#
+        ),
         state.connected,
         state.bytes_sent,
         state.bytes_received,
-        state.session_id
+        state.session_id,
+        state.send_window.total_starvations(),
+        batch.batches,
+        batch.messages,
+        batch.messages_per_batch_x100() as f64 / 100.0,
+        batch.bytes_saved
     );
 
     match env.new_string(&stats_json) {
@@ -282,6 +497,113 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_getStats(
     }
 }
 
+/// Poll the next pending event as JSON
+///
+/// Called from Kotlin:
+/// ```kotlin
+/// external fun pollEvent(): String?
+/// ```
+///
+/// Returns: e.g. `{"type":"bitrate_hint","target_kbps":4000,"max_fps":30}`,
+/// or null if no event is pending
+#[no_mangle]
+pub extern "system" fn Java_com_androidauto_wifi_RustBridge_pollEvent(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    let event = STATE.lock().unwrap().events.pop_front();
+    let Some(event) = event else {
+        return std::ptr::null_mut();
+    };
+
+    let json = match serde_json::to_string(&event) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize event: {:?}", e);
+            return std::ptr::null_mut();
+        }
+    };
+
+    match env.new_string(&json) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            error!("Failed to create event string: {:?}", e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Start recording traffic to rotating capture files
+///
+/// Called from Kotlin:
+/// ```kotlin
+/// external fun startCapture(directory: String, maxFileBytes: Long, maxFiles: Int): Boolean
+/// ```
+///
+/// At most `maxFiles` files of `maxFileBytes` each are kept; the oldest file
+/// is deleted on rotation.
+#[no_mangle]
+pub extern "system" fn Java_com_androidauto_wifi_RustBridge_startCapture(
+    mut env: JNIEnv,
+    _class: JClass,
+    directory: JString,
+    max_file_bytes: jlong,
+    max_files: jint,
+) -> jboolean {
+    let directory: String = match env.get_string(&directory) {
+        Ok(s) => s.into(),
+        Err(e) => {
+            error!("Failed to get capture directory: {:?}", e);
+            return JNI_FALSE;
+        }
+    };
+
+    let config = RecorderConfig {
+        directory: PathBuf::from(directory),
+        max_file_bytes: max_file_bytes.max(0) as u64,
+        max_files: max_files.max(1) as usize,
+        ..RecorderConfig::default()
+    };
+
+    let mut state = STATE.lock().unwrap();
+    match Recorder::new(config) {
+        Ok(mut recorder) => {
+            if state.session_id != 0 {
+                if let Err(e) = recorder.start_session(state.session_id) {
+                    warn!("Failed to record session start: {:?}", e);
+                }
+            }
+            info!("Capture started");
+            state.recorder = Some(recorder);
+            JNI_TRUE
+        }
+        Err(e) => {
+            error!("Failed to start capture: {:?}", e);
+            JNI_FALSE
+        }
+    }
+}
+
+/// Stop recording traffic
+///
+/// Called from Kotlin:
+/// ```kotlin
+/// external fun stopCapture()
+/// ```
+#[no_mangle]
+pub extern "system" fn Java_com_androidauto_wifi_RustBridge_stopCapture(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    let mut state = STATE.lock().unwrap();
+    if let Some(mut recorder) = state.recorder.take() {
+        if let Err(e) = recorder.flush() {
+            warn!("Failed to flush capture: {:?}", e);
+        }
+        info!("Capture stopped: {:?}", recorder.stats());
+    }
+}
+
 /// Perform handshake with ESP32
 ///
 /// Called from Kotlin:
@@ -302,9 +624,10 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_performHandshake(
     }
 
     // Create handshake request message
+    // All features supported, including FEATURE_FLOW_CONTROL
     let handshake_msg = Message::Control(ControlMessage::HandshakeRequest {
         version: 1,
-        features: 0xFF, // All features supported
+        features: 0xFF,
     });
 
     // Serialize to frame
@@ -312,7 +635,8 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_performHandshake(
     match state.frame_builder.build_frame(&handshake_msg, 0, &mut buffer) {
         Ok(len) => {
             debug!("Handshake frame built: {} bytes", len);
-            // TODO: Send frame over TCP and wait for response
+            state.transmit(&buffer[..len]);
+            // TODO: Wait for response
         }
         Err(e) => {
             error!("Failed to build handshake frame: {:?}", e);
@@ -323,6 +647,12 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_performHandshake(
     // TODO: Receive and parse HandshakeResponse
     // For now, simulate success
     state.session_id = 12345;
+    let session_id = state.session_id;
+    if let Some(recorder) = state.recorder.as_mut() {
+        if let Err(e) = recorder.start_session(session_id) {
+            warn!("Failed to record session start: {:?}", e);
+        }
+    }
 
     info!("Handshake completed (simulated), session_id: {}", state.session_id);
     JNI_TRUE
@@ -340,7 +670,7 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_performHandshake(
 /// Returns: Number of bytes processed, or -1 on error
 #[no_mangle]
 pub extern "system" fn Java_com_androidauto_wifi_RustBridge_processIncomingData(
-    mut env: JNIEnv,
+    env: JNIEnv,
     _class: JClass,
     data: JByteArray,
 ) -> jint {
@@ -359,42 +689,76 @@ pub extern "system" fn Java_com_androidauto_wifi_RustBridge_processIncomingData(
         return -1;
     }
 
-    // Try to parse as protocol frame
-    match FrameBuilder::parse_frame(&rust_data) {
-        Ok((header, message)) => {
-            debug!(
-                "Received message: type={:?}, seq={}, channel={}",
-                message.message_type(),
-                header.sequence,
-                header.channel
-            );
+    // Record raw bytes before parsing so malformed frames are captured too
+    let mut state = STATE.lock().unwrap();
+    state.flush_due(monotonic_us());
+    if let Some(recorder) = state.recorder.as_mut() {
+        recorder.record(Direction::Rx, CHANNEL_UNKNOWN, &[&rust_data]);
+    }
+
+    // Try to parse as protocol frame (a batched frame carries several messages)
+    let frames = match parse_frames(&rust_data) {
+        Ok(frames) => frames,
+        Err(e) => {
+            warn!("Failed to parse frame: {:?}", e);
+            return -1;
+        }
+    };
 
-            // Update statistics
-            let mut state = STATE.lock().unwrap();
-            state.bytes_received += data_len as u64;
+    // Update statistics
+    state.bytes_received += data_len as u64;
 
-            // Handle specific message types
-            match message {
-                Message::Ping { timestamp } => {
-                    debug!("Received ping, timestamp: {}", timestamp);
-                    // TODO: Send pong response
-                }
-                Message::Control(ctrl) => {
-                    debug!("Received control message: {:?}", ctrl);
-                }
-                Message::Data(payload) => {
-                    debug!("Received data: {} bytes", payload.len());
-                    // TODO: Forward to Android Auto
-                }
-                _ => {}
+    for frame in frames {
+        match frame {
+            Ok((header, message)) => handle_message(&mut state, header, message),
+            Err(e) => {
+                warn!("Failed to parse batched message: {:?}", e);
+                return -1;
             }
+        }
+    }
+
+    data_len as jint
+}
+
+/// Handle one decoded message from the ESP32
+fn handle_message(state: &mut ConnectionState, header: Header, message: Message) {
+    debug!(
+        "Received message: type={:?}, seq={}, channel={}",
+        message.message_type(),
+        header.sequence,
+        header.channel
+    );
 
-            data_len as jint
+    // Handle specific message types
+    match message {
+        Message::Ping { timestamp } => {
+            debug!("Received ping, timestamp: {}", timestamp);
+            // TODO: Send pong response
         }
-        Err(e) => {
-            warn!("Failed to parse frame: {:?}", e);
-            -1
+        Message::Control(ctrl) => {
+            debug!("Received control message: {:?}", ctrl);
+            if let ControlMessage::HandshakeResponse { features, .. } = ctrl {
+                state.send_window.negotiate(features);
+                info!(
+                    "Flow control {}",
+                    if features & FEATURE_FLOW_CONTROL != 0 { "enabled" } else { "disabled" }
+                );
+            }
+            state.send_window.apply(&ctrl);
+            if let Some(event) = BridgeEvent::from_control(&ctrl) {
+                if state.events.len() == MAX_PENDING_EVENTS {
+                    warn!("Event queue full, dropping {:?}", state.events.front());
+                    state.events.pop_front();
+                }
+                state.events.push_back(event);
+            }
         }
+        Message::Data(payload) => {
+            debug!("Received data: {} bytes", payload.len());
+            // TODO: Forward to Android Auto
+        }
+        _ => {}
     }
 }
 
@@ -415,5 +779,133 @@ mod tests {
         assert!(!state.connected);
         assert!(state.esp32_ip.is_none());
         assert_eq!(state.bytes_sent, 0);
+        assert_eq!(state.send_window.total_starvations(), 0);
+    }
+
+    #[test]
+    fn test_sends_pass_when_peer_never_grants_credits() {
+        let mut state = ConnectionState::default();
+        let response = |features| {
+            Message::Control(ControlMessage::HandshakeResponse {
+                version: 1,
+                features,
+                session_id: 7,
+            })
+        };
+
+        // Firmware without flow control never sends a WindowUpdate
+        handle_message(&mut state, Header::new(0, 0, 0), response(0));
+        assert!(state.send_window.consume(2, 16 * 1024).is_ok());
+        assert_eq!(state.send_window.total_starvations(), 0);
+
+        handle_message(&mut state, Header::new(1, 0, 0), response(FEATURE_FLOW_CONTROL));
+        assert!(state.send_window.consume(2, 1).is_err());
+        handle_message(
+            &mut state,
+            Header::new(2, 0, 0),
+            Message::Control(ControlMessage::WindowUpdate { channel: 2, credits: 10 }),
+        );
+        assert!(state.send_window.consume(2, 10).is_ok());
+    }
+
+    #[test]
+    fn test_batched_channels() {
+        let mut state = ConnectionState {
+            batch_channels: 1 << 3,
+            ..ConnectionState::default()
+        };
+        assert!(state.batches(3));
+        assert!(!state.batches(2));
+        assert!(!state.batches(9));
+
+        let touch = Message::Data(DataPayload::new(&[1, 2, 3, 4]).unwrap());
+        for _ in 0..3 {
+            assert!(state.push_batched(3, &touch, 0));
+        }
+        assert_eq!(state.batcher.len(), 3);
+
+        // Too big for a batch: queued messages go first, then it goes alone
+        let large = Message::Data(DataPayload::new(&[0; BATCH_CAPACITY]).unwrap());
+        assert!(!state.push_batched(3, &large, 10));
+        assert!(state.batcher.is_empty());
+
+        // The deadline passed: the next push flushes
+        assert!(state.push_batched(3, &touch, 20));
+        assert!(state.push_batched(3, &touch, 1_000));
+        let stats = state.batcher.stats();
+        assert_eq!((stats.batches, stats.messages), (2, 5));
+        assert!(stats.bytes_saved > 0);
+
+        // A lone queued message goes out once due, without another push
+        assert!(state.push_batched(3, &touch, 2_000));
+        assert_eq!(state.flush_due(2_100), 0);
+        assert!(state.flush_due(3_000) > 0);
+        assert!(state.batcher.is_empty());
+    }
+
+    #[test]
+    fn test_captured_sends_deframe() {
+        use bridge_tools::deframe::{Deframed, Deframer};
+        use bridge_tools::input::{self, Record};
+
+        let directory = std::env::temp_dir().join(format!("rust-core-tx-{}", std::process::id()));
+        let config = RecorderConfig {
+            directory: directory.clone(),
+            ..RecorderConfig::default()
+        };
+        let mut state = ConnectionState {
+            recorder: Some(Recorder::new(config).unwrap()),
+            batch_channels: 1 << 4,
+            ..ConnectionState::default()
+        };
+        assert_eq!(state.send(2, b"video", 0), 5);
+        assert_eq!(state.send(4, b"touch", 0), 5);
+        assert!(state.flush_due(1_000) > 0);
+
+        let mut recorder = state.recorder.take().unwrap();
+        recorder.flush().unwrap();
+        let path = recorder.files().next().unwrap().clone();
+        let mut deframer = Deframer::new();
+        for record in input::load(&path).unwrap() {
+            if let Record::Data { direction, bytes, .. } = record {
+                assert_eq!(direction, Direction::Tx);
+                deframer.push(&bytes);
+            }
+        }
+
+        let mut sent = Vec::new();
+        while let Some(item) = deframer.next_frame() {
+            let Deframed::Frame { bytes, .. } = item else {
+                panic!("undecodable capture: {item:?}");
+            };
+            for frame in parse_frames(&bytes).unwrap() {
+                let (header, msg) = frame.unwrap();
+                let Message::Data(payload) = msg else {
+                    panic!("unexpected {msg:?}");
+                };
+                sent.push((header.channel, payload.data.to_vec()));
+            }
+        }
+        assert_eq!(sent, [(2, b"video".to_vec()), (4, b"touch".to_vec())]);
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
+    #[test]
+    fn test_bridge_event_from_control() {
+        assert_eq!(
+            BridgeEvent::from_control(&ControlMessage::RequestKeyframe),
+            Some(BridgeEvent::KeyframeRequested)
+        );
+        assert_eq!(BridgeEvent::from_control(&ControlMessage::StopStream), None);
+
+        let event = BridgeEvent::from_control(&ControlMessage::BitrateHint {
+            target_kbps: 4000,
+            max_fps: 30,
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"type":"bitrate_hint","target_kbps":4000,"max_fps":30}"#
+        );
     }
 }
@@ -0,0 +1,146 @@
+//! # Android Auto Protocol (AAP) Frame Headers
+//!
+//! `Data` payloads carry the Android Auto Protocol stream between the phone
+//! and the head unit. This module decodes just enough of the AAP framing to
+//! label traffic in tooling; it never looks inside encrypted payloads.
+//!
+//! ## Frame Layout
+//!
+//! ```text
+//! ┌─────────┬───────┬────────────┬───────────────────┬──────────────┬─────────
+//! │ channel │ flags │ frame_len  │ total_len         │ message_id   │ body...
+//! │  1 byte │ 1 byte│ u16 BE     │ u32 BE (only on a │ u16 BE (only │
+//! │         │       │            │ first, non-last   │ if not       │
+//! │         │       │            │ fragment)         │ encrypted)   │
+//! └─────────┴───────┴────────────┴───────────────────┴──────────────┴─────────
+//! ```
+//!
+//! `frame_len` counts the bytes after the header (including `message_id`).
+
+/// Flag: first fragment of a message
+pub const FLAG_FIRST: u8 = 0x01;
+/// Flag: last fragment of a message
+pub const FLAG_LAST: u8 = 0x02;
+/// Flag: control-type message (vs. channel-specific)
+pub const FLAG_CONTROL: u8 = 0x04;
+/// Flag: payload is TLS-encrypted
+pub const FLAG_ENCRYPTED: u8 = 0x08;
+
+/// Decoded AAP frame header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AapHeader {
+    /// AAP service channel
+    pub channel: u8,
+    /// Raw flags byte
+    pub flags: u8,
+    /// Bytes following the header in this frame
+    pub frame_len: u16,
+    /// Total message length (first fragment of a multi-frame message only)
+    pub total_len: Option<u32>,
+    /// Message ID (unencrypted frames that start a message only)
+    pub message_id: Option<u16>,
+    /// Header length in bytes (excluding `message_id`)
+    pub header_len: usize,
+}
+
+impl AapHeader {
+    /// Decode the header at the start of `data`
+    ///
+    /// Returns `None` if there are not enough bytes for the header.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let [channel, flags, hi, lo, rest @ ..] = data else {
+            return None;
+        };
+        let frame_len = u16::from_be_bytes([*hi, *lo]);
+
+        let (total_len, header_len, body) = if flags & (FLAG_FIRST | FLAG_LAST) == FLAG_FIRST {
+            let total = rest.get(..4)?;
+            (
+                Some(u32::from_be_bytes([total[0], total[1], total[2], total[3]])),
+                8,
+                &rest[4..],
+            )
+        } else {
+            (None, 4, rest)
+        };
+
+        let starts_message = flags & FLAG_FIRST != 0;
+        let message_id = match body {
+            [hi, lo, ..] if starts_message && flags & FLAG_ENCRYPTED == 0 => {
+                Some(u16::from_be_bytes([*hi, *lo]))
+            }
+            _ => None,
+        };
+
+        Some(Self {
+            channel: *channel,
+            flags: *flags,
+            frame_len,
+            total_len,
+            message_id,
+            header_len,
+        })
+    }
+
+    /// Check if this is the first fragment of a message
+    pub fn is_first(&self) -> bool {
+        self.flags & FLAG_FIRST != 0
+    }
+
+    /// Check if this is the last fragment of a message
+    pub fn is_last(&self) -> bool {
+        self.flags & FLAG_LAST != 0
+    }
+
+    /// Check if the payload is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Check if this is a control-type message
+    pub fn is_control(&self) -> bool {
+        self.flags & FLAG_CONTROL != 0
+    }
+
+    /// Total frame length on the wire (header + body)
+    pub fn wire_len(&self) -> usize {
+        self.header_len + self.frame_len as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_frame_message() {
+        // Channel 0, first|last, 6 bytes, VERSION_REQUEST (0x0001)
+        let data = [0x00, 0x03, 0x00, 0x06, 0x00, 0x01, 0x00, 0x01, 0x00, 0x07];
+        let header = AapHeader::parse(&data).unwrap();
+        assert_eq!(header.channel, 0);
+        assert!(header.is_first() && header.is_last());
+        assert_eq!(header.total_len, None);
+        assert_eq!(header.message_id, Some(0x0001));
+        assert_eq!(header.wire_len(), 10);
+    }
+
+    #[test]
+    fn test_first_fragment_has_total_len() {
+        let data = [0x03, 0x09, 0x40, 0x00, 0x00, 0x01, 0x00, 0x00, 0xAA, 0xBB];
+        let header = AapHeader::parse(&data).unwrap();
+        assert_eq!(header.channel, 3);
+        assert!(header.is_encrypted());
+        assert_eq!(header.total_len, Some(0x0001_0000));
+        assert_eq!(header.header_len, 8);
+        // Encrypted: no message ID
+        assert_eq!(header.message_id, None);
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert!(AapHeader::parse(&[0x00, 0x01, 0x00]).is_none());
+        // First, non-last fragment without total length
+        assert!(AapHeader::parse(&[0x00, 0x01, 0x00, 0x10, 0x00]).is_none());
+    }
+}
@@ -0,0 +1,783 @@
+//! # Android Open Accessory 2.0 Handshake
+//!
+//! A sans-IO state machine that switches a USB device into accessory mode.
+//! It decides which control transfer comes next and interprets the result;
+//! the USB host stack performs the transfers and reports attach/detach
+//! events. [`drive`] runs the transfers over any [`ControlTransfer`]
+//! implementation, which is also how the sequence is tested on a host.
+//!
+//! ## Sequence
+//!
+//! ```text
+//!   attach (any VID/PID)
+//!        │
+//!        ▼
+//!   GET_PROTOCOL (51) ──── version 0 ──► Failed(NotSupported)
+//!        │ version ≥ 1
+//!        ▼
+//!   SEND_STRING (52) × 6   manufacturer, model, description,
+//!        │                 version, URI, serial
+//!        ▼
+//!   START (53)
+//!        │
+//!        ▼
+//!   detach … attach 0x18D1/0x2D00–0x2D05 ──► Accessory(mode)
+//!        │
+//!        └── timeout or another VID/PID ──► Failed(...)
+//! ```
+//!
+//! A device that is already in accessory mode when attached skips straight
+//! to `Accessory`.
+//!
+//! ## Identity and Quirks
+//!
+//! The strings come from an [`AoaIdentity`] (defaults to the Android Auto
+//! identity) that can be changed per slot from configuration. Head units
+//! that need slower pacing or a longer re-enumeration window get
+//! [`Quirks`] via [`AoaHandshake::set_quirks`]; their delays surface as
+//! [`Step::Sleep`].
+
+use core::future::Future;
+
+use heapless::{String, Vec};
+
+use crate::quirks::Quirks;
+
+/// Google's USB vendor ID, used by devices in accessory mode
+pub const GOOGLE_VID: u16 = 0x18D1;
+
+/// First accessory-mode product ID (accessory only)
+pub const ACCESSORY_PID_FIRST: u16 = 0x2D00;
+
+/// Last accessory-mode product ID (accessory + audio + ADB)
+pub const ACCESSORY_PID_LAST: u16 = 0x2D05;
+
+/// Vendor request: read the supported AOA protocol version
+pub const AOA_GET_PROTOCOL: u8 = 51;
+
+/// Vendor request: send one identification string
+pub const AOA_SEND_STRING: u8 = 52;
+
+/// Vendor request: switch to accessory mode
+pub const AOA_START: u8 = 53;
+
+/// `bmRequestType` for device-to-host vendor requests
+pub const REQUEST_TYPE_VENDOR_IN: u8 = 0xC0;
+
+/// `bmRequestType` for host-to-device vendor requests
+pub const REQUEST_TYPE_VENDOR_OUT: u8 = 0x40;
+
+/// Longest identification string, excluding the NUL terminator
+pub const MAX_STRING_LEN: usize = 255;
+
+/// How long to wait for the device to come back in accessory mode
+pub const REENUMERATION_TIMEOUT_MS: u64 = 5_000;
+
+/// USB setup packet for a control transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetupPacket {
+    /// `bmRequestType`
+    pub request_type: u8,
+    /// `bRequest`
+    pub request: u8,
+    /// `wValue`
+    pub value: u16,
+    /// `wIndex`
+    pub index: u16,
+    /// `wLength`
+    pub length: u16,
+}
+
+/// USB host access needed by the handshake
+pub trait ControlTransfer {
+    /// Transfer error type
+    type Error;
+
+    /// Perform a device-to-host control transfer; returns the bytes received
+    fn control_in(
+        &mut self,
+        setup: &SetupPacket,
+        data: &mut [u8],
+    ) -> impl Future<Output = Result<usize, Self::Error>>;
+
+    /// Perform a host-to-device control transfer
+    fn control_out(
+        &mut self,
+        setup: &SetupPacket,
+        data: &[u8],
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// Identification string slots, in `wIndex` order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum StringIndex {
+    /// Manufacturer name
+    Manufacturer = 0,
+    /// Model name
+    Model = 1,
+    /// Description
+    Description = 2,
+    /// Version
+    Version = 3,
+    /// URI for the accessory's app
+    Uri = 4,
+    /// Serial number
+    Serial = 5,
+}
+
+impl StringIndex {
+    /// All slots in the order they are sent
+    pub const ALL: [StringIndex; 6] = [
+        StringIndex::Manufacturer,
+        StringIndex::Model,
+        StringIndex::Description,
+        StringIndex::Version,
+        StringIndex::Uri,
+        StringIndex::Serial,
+    ];
+
+    /// Look up a slot by its config name (`manufacturer`, `model`, ...)
+    pub fn from_name(name: &str) -> Option<StringIndex> {
+        Some(match name {
+            "manufacturer" => StringIndex::Manufacturer,
+            "model" => StringIndex::Model,
+            "description" => StringIndex::Description,
+            "version" => StringIndex::Version,
+            "uri" => StringIndex::Uri,
+            "serial" => StringIndex::Serial,
+            _ => return None,
+        })
+    }
+
+    fn next(self) -> Option<StringIndex> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+}
+
+/// The six strings the accessory identifies itself with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessoryStrings<'a> {
+    /// Manufacturer name
+    pub manufacturer: &'a str,
+    /// Model name
+    pub model: &'a str,
+    /// Description
+    pub description: &'a str,
+    /// Version
+    pub version: &'a str,
+    /// URI for the accessory's app
+    pub uri: &'a str,
+    /// Serial number
+    pub serial: &'a str,
+}
+
+impl<'a> AccessoryStrings<'a> {
+    /// The identity Android Auto head units present
+    pub const ANDROID_AUTO: AccessoryStrings<'static> = AccessoryStrings {
+        manufacturer: "Android",
+        model: "Android Auto",
+        description: "Android Auto",
+        version: "2.0.1",
+        uri: "https://www.android.com/auto/",
+        serial: "HU-AAAAAA001",
+    };
+
+    /// Get the string for a slot
+    pub fn get(&self, index: StringIndex) -> &'a str {
+        match index {
+            StringIndex::Manufacturer => self.manufacturer,
+            StringIndex::Model => self.model,
+            StringIndex::Description => self.description,
+            StringIndex::Version => self.version,
+            StringIndex::Uri => self.uri,
+            StringIndex::Serial => self.serial,
+        }
+    }
+}
+
+/// Owned, configurable identification strings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AoaIdentity {
+    slots: [String<MAX_STRING_LEN>; 6],
+}
+
+impl AoaIdentity {
+    /// Copy an identity from borrowed strings
+    pub fn from_strings(strings: &AccessoryStrings<'_>) -> Result<Self, AoaError> {
+        let mut identity = Self {
+            slots: Default::default(),
+        };
+        for index in StringIndex::ALL {
+            identity.set(index, strings.get(index))?;
+        }
+        Ok(identity)
+    }
+
+    /// Replace the string for a slot
+    pub fn set(&mut self, index: StringIndex, value: &str) -> Result<(), AoaError> {
+        self.slots[index as usize] = value.try_into().map_err(|_| AoaError::StringTooLong)?;
+        Ok(())
+    }
+
+    /// Get the string for a slot
+    pub fn get(&self, index: StringIndex) -> &str {
+        &self.slots[index as usize]
+    }
+
+    /// Borrow as [`AccessoryStrings`] for [`AoaHandshake::new`]
+    pub fn strings(&self) -> AccessoryStrings<'_> {
+        AccessoryStrings {
+            manufacturer: self.get(StringIndex::Manufacturer),
+            model: self.get(StringIndex::Model),
+            description: self.get(StringIndex::Description),
+            version: self.get(StringIndex::Version),
+            uri: self.get(StringIndex::Uri),
+            serial: self.get(StringIndex::Serial),
+        }
+    }
+}
+
+impl Default for AoaIdentity {
+    /// The Android Auto identity
+    fn default() -> Self {
+        // Every built-in string fits a slot
+        Self::from_strings(&AccessoryStrings::ANDROID_AUTO).unwrap()
+    }
+}
+
+/// Vendor and product ID of an attached device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UsbDeviceInfo {
+    /// `idVendor`
+    pub vendor_id: u16,
+    /// `idProduct`
+    pub product_id: u16,
+}
+
+impl UsbDeviceInfo {
+    /// Interfaces offered by a device in accessory mode, if it is in it
+    pub fn accessory_mode(&self) -> Option<AccessoryMode> {
+        if self.vendor_id != GOOGLE_VID
+            || !(ACCESSORY_PID_FIRST..=ACCESSORY_PID_LAST).contains(&self.product_id)
+        {
+            return None;
+        }
+        let (accessory, audio) = match self.product_id - ACCESSORY_PID_FIRST {
+            0 | 1 => (true, false),
+            2 | 3 => (false, true),
+            _ => (true, true),
+        };
+        Some(AccessoryMode {
+            accessory,
+            audio,
+            adb: self.product_id & 1 == 1,
+        })
+    }
+}
+
+/// Interfaces a device exposes in accessory mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AccessoryMode {
+    /// Accessory bulk interface
+    pub accessory: bool,
+    /// Audio interface
+    pub audio: bool,
+    /// ADB interface
+    pub adb: bool,
+}
+
+/// Reasons the handshake gives up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AoaError {
+    /// The device reported AOA protocol version 0
+    NotSupported,
+    /// GET_PROTOCOL returned fewer than 2 bytes
+    ShortResponse,
+    /// A control transfer failed
+    TransferFailed,
+    /// An identification string exceeds [`MAX_STRING_LEN`]
+    StringTooLong,
+    /// The device did not come back within the re-enumeration timeout
+    ReenumerationTimeout,
+    /// The device came back, but not in accessory mode
+    NotSwitched,
+}
+
+/// Where the handshake is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AoaState {
+    /// Waiting for a device to attach
+    Idle,
+    /// Next: GET_PROTOCOL
+    GetProtocol,
+    /// Next: SEND_STRING for this slot
+    SendString(StringIndex),
+    /// Next: START
+    Start,
+    /// START sent; waiting for the device to re-enumerate
+    Reenumerating {
+        /// When the wait began (set by the first `poll` after START)
+        since_ms: Option<u64>,
+    },
+    /// Device is in accessory mode
+    Accessory(AccessoryMode),
+    /// Handshake gave up
+    Failed(AoaError),
+}
+
+/// A control transfer the host stack should perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlRequest<'a> {
+    /// Device-to-host; report the received bytes with [`AoaHandshake::complete_in`]
+    In(SetupPacket),
+    /// Host-to-device; report success with [`AoaHandshake::complete_out`]
+    Out(SetupPacket, &'a [u8]),
+}
+
+/// What the handshake needs next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step<'a> {
+    /// Perform this control transfer
+    Transfer(ControlRequest<'a>),
+    /// Wait for an attach (or detach) event
+    WaitForDevice,
+    /// A quirk asks for a pause; poll again at this time (ms)
+    Sleep(u64),
+    /// Done: the device is in accessory mode
+    Done(AccessoryMode),
+    /// The handshake failed; call [`AoaHandshake::reset`] to start over
+    Failed(AoaError),
+}
+
+/// Accessory-mode handshake for one device
+pub struct AoaHandshake<'a> {
+    strings: AccessoryStrings<'a>,
+    state: AoaState,
+    protocol: u16,
+    scratch: Vec<u8, { MAX_STRING_LEN + 1 }>,
+    quirks: Quirks,
+    /// Pause requested by the last completed transfer, not yet scheduled
+    pending_delay_ms: u32,
+    resume_at_ms: Option<u64>,
+}
+
+impl<'a> AoaHandshake<'a> {
+    /// Create a handshake that identifies with `strings`
+    pub fn new(strings: AccessoryStrings<'a>) -> Self {
+        Self {
+            strings,
+            state: AoaState::Idle,
+            protocol: 0,
+            scratch: Vec::new(),
+            quirks: Quirks::default(),
+            pending_delay_ms: 0,
+            resume_at_ms: None,
+        }
+    }
+
+    /// Apply a head unit's quirks; call after `device_attached`
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// When a pending [`Step::Sleep`] ends, if one is scheduled
+    pub fn wake_at(&self) -> Option<u64> {
+        self.resume_at_ms
+    }
+
+    /// Current state
+    pub fn state(&self) -> AoaState {
+        self.state
+    }
+
+    /// AOA protocol version reported by the device (0 until known)
+    pub fn protocol(&self) -> u16 {
+        self.protocol
+    }
+
+    /// Forget the device and wait for the next attach
+    pub fn reset(&mut self) {
+        self.state = AoaState::Idle;
+        self.protocol = 0;
+        self.pending_delay_ms = 0;
+        self.resume_at_ms = None;
+    }
+
+    /// Report a newly attached device
+    pub fn device_attached(&mut self, device: UsbDeviceInfo) {
+        self.state = match (self.state, device.accessory_mode()) {
+            (AoaState::Idle | AoaState::Reenumerating { .. }, Some(mode)) => {
+                AoaState::Accessory(mode)
+            }
+            (AoaState::Idle, None) => AoaState::GetProtocol,
+            (AoaState::Reenumerating { .. }, None) => AoaState::Failed(AoaError::NotSwitched),
+            // Already busy with a device; ignore others
+            (state, _) => state,
+        };
+    }
+
+    /// Report that the device went away
+    pub fn device_detached(&mut self) {
+        match self.state {
+            // Expected after START
+            AoaState::Reenumerating { .. } => {}
+            _ => self.reset(),
+        }
+    }
+
+    /// Decide what happens next; `now_ms` is any monotonic millisecond clock
+    pub fn poll(&mut self, now_ms: u64) -> Step<'_> {
+        if matches!(self.state, AoaState::SendString(_) | AoaState::Start) {
+            if self.pending_delay_ms > 0 {
+                self.resume_at_ms = Some(now_ms + u64::from(self.pending_delay_ms));
+                self.pending_delay_ms = 0;
+            }
+            match self.resume_at_ms {
+                Some(at) if now_ms < at => return Step::Sleep(at),
+                _ => self.resume_at_ms = None,
+            }
+        }
+        match self.state {
+            AoaState::Idle => Step::WaitForDevice,
+            AoaState::GetProtocol => Step::Transfer(ControlRequest::In(SetupPacket {
+                request_type: REQUEST_TYPE_VENDOR_IN,
+                request: AOA_GET_PROTOCOL,
+                value: 0,
+                index: 0,
+                length: 2,
+            })),
+            AoaState::SendString(index) => {
+                let string = self.strings.get(index).as_bytes();
+                self.scratch.clear();
+                if self.scratch.extend_from_slice(string).is_err() || self.scratch.push(0).is_err()
+                {
+                    self.state = AoaState::Failed(AoaError::StringTooLong);
+                    return Step::Failed(AoaError::StringTooLong);
+                }
+                Step::Transfer(ControlRequest::Out(
+                    SetupPacket {
+                        request_type: REQUEST_TYPE_VENDOR_OUT,
+                        request: AOA_SEND_STRING,
+                        value: 0,
+                        index: index as u16,
+                        length: self.scratch.len() as u16,
+                    },
+                    &self.scratch,
+                ))
+            }
+            AoaState::Start => Step::Transfer(ControlRequest::Out(
+                SetupPacket {
+                    request_type: REQUEST_TYPE_VENDOR_OUT,
+                    request: AOA_START,
+                    value: 0,
+                    index: 0,
+                    length: 0,
+                },
+                &[],
+            )),
+            AoaState::Reenumerating { since_ms: None } => {
+                self.state = AoaState::Reenumerating {
+                    since_ms: Some(now_ms),
+                };
+                Step::WaitForDevice
+            }
+            AoaState::Reenumerating {
+                since_ms: Some(since),
+            } => {
+                let timeout = self
+                    .quirks
+                    .reenumeration_timeout_ms
+                    .map_or(REENUMERATION_TIMEOUT_MS, u64::from);
+                if now_ms.saturating_sub(since) >= timeout {
+                    self.state = AoaState::Failed(AoaError::ReenumerationTimeout);
+                    Step::Failed(AoaError::ReenumerationTimeout)
+                } else {
+                    Step::WaitForDevice
+                }
+            }
+            AoaState::Accessory(mode) => Step::Done(mode),
+            AoaState::Failed(error) => Step::Failed(error),
+        }
+    }
+
+    /// Report the data received for an `In` transfer
+    pub fn complete_in(&mut self, data: &[u8]) {
+        if self.state != AoaState::GetProtocol {
+            return;
+        }
+        self.state = match data {
+            [lo, hi, ..] => {
+                self.protocol = u16::from_le_bytes([*lo, *hi]);
+                if self.protocol == 0 {
+                    AoaState::Failed(AoaError::NotSupported)
+                } else {
+                    AoaState::SendString(StringIndex::Manufacturer)
+                }
+            }
+            _ => AoaState::Failed(AoaError::ShortResponse),
+        };
+        self.schedule_delay();
+    }
+
+    /// Report that an `Out` transfer succeeded
+    pub fn complete_out(&mut self) {
+        self.state = match self.state {
+            AoaState::SendString(index) => {
+                index.next().map_or(AoaState::Start, AoaState::SendString)
+            }
+            AoaState::Start => AoaState::Reenumerating { since_ms: None },
+            state => state,
+        };
+        self.schedule_delay();
+    }
+
+    /// Queue the quirk delays before the next transfer
+    fn schedule_delay(&mut self) {
+        self.pending_delay_ms = match self.state {
+            AoaState::SendString(_) => self.quirks.request_delay_ms,
+            AoaState::Start => self.quirks.request_delay_ms + self.quirks.start_delay_ms,
+            _ => 0,
+        };
+    }
+
+    /// Report that the last transfer failed
+    pub fn transfer_failed(&mut self) {
+        self.state = AoaState::Failed(AoaError::TransferFailed);
+    }
+}
+
+/// Perform transfers until the handshake needs a device event or finishes
+///
+/// Returns `Ok(Some(mode))` once in accessory mode, `Ok(None)` while
+/// waiting for an attach, re-enumeration or a quirk delay (see
+/// [`AoaHandshake::wake_at`]).
+pub async fn drive<C: ControlTransfer>(
+    handshake: &mut AoaHandshake<'_>,
+    usb: &mut C,
+    now_ms: u64,
+) -> Result<Option<AccessoryMode>, AoaError> {
+    loop {
+        match handshake.poll(now_ms) {
+            Step::Transfer(ControlRequest::In(setup)) => {
+                let mut data = [0u8; 2];
+                match usb.control_in(&setup, &mut data).await {
+                    Ok(n) => handshake.complete_in(&data[..n.min(data.len())]),
+                    Err(_) => handshake.transfer_failed(),
+                }
+            }
+            Step::Transfer(ControlRequest::Out(setup, data)) => {
+                match usb.control_out(&setup, data).await {
+                    Ok(()) => handshake.complete_out(),
+                    Err(_) => handshake.transfer_failed(),
+                }
+            }
+            Step::WaitForDevice | Step::Sleep(_) => return Ok(None),
+            Step::Done(mode) => return Ok(Some(mode)),
+            Step::Failed(error) => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    const HEAD_UNIT: UsbDeviceInfo = UsbDeviceInfo {
+        vendor_id: 0x1234,
+        product_id: 0x5678,
+    };
+
+    /// Records every transfer and answers GET_PROTOCOL with `protocol`
+    #[cfg(feature = "std")]
+    struct MockDevice {
+        protocol: u16,
+        fail_request: Option<u8>,
+        log: std::vec::Vec<(SetupPacket, std::vec::Vec<u8>)>,
+    }
+
+    #[cfg(feature = "std")]
+    impl MockDevice {
+        fn new(protocol: u16) -> Self {
+            Self {
+                protocol,
+                fail_request: None,
+                log: std::vec::Vec::new(),
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl ControlTransfer for MockDevice {
+        type Error = ();
+
+        async fn control_in(&mut self, setup: &SetupPacket, data: &mut [u8]) -> Result<usize, ()> {
+            self.log.push((*setup, std::vec::Vec::new()));
+            data[..2].copy_from_slice(&self.protocol.to_le_bytes());
+            Ok(2)
+        }
+
+        async fn control_out(&mut self, setup: &SetupPacket, data: &[u8]) -> Result<(), ()> {
+            if self.fail_request == Some(setup.request) {
+                return Err(());
+            }
+            self.log.push((*setup, data.to_vec()));
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_full_handshake() {
+        let mut handshake = AoaHandshake::new(AccessoryStrings::ANDROID_AUTO);
+        let mut device = MockDevice::new(2);
+
+        handshake.device_attached(HEAD_UNIT);
+        assert_eq!(drive(&mut handshake, &mut device, 0).await, Ok(None));
+        assert_eq!(handshake.protocol(), 2);
+
+        let requests: std::vec::Vec<u8> = device.log.iter().map(|(s, _)| s.request).collect();
+        assert_eq!(requests, [51, 52, 52, 52, 52, 52, 52, 53]);
+        let (setup, data) = &device.log[2];
+        assert_eq!(setup.request_type, REQUEST_TYPE_VENDOR_OUT);
+        assert_eq!(setup.index, StringIndex::Model as u16);
+        assert_eq!(data.as_slice(), b"Android Auto\0");
+        assert_eq!(setup.length as usize, data.len());
+
+        // Device drops off the bus and returns as accessory + ADB
+        handshake.device_detached();
+        handshake.device_attached(UsbDeviceInfo {
+            vendor_id: GOOGLE_VID,
+            product_id: 0x2D01,
+        });
+        let mode = AccessoryMode {
+            accessory: true,
+            audio: false,
+            adb: true,
+        };
+        assert_eq!(
+            drive(&mut handshake, &mut device, 100).await,
+            Ok(Some(mode))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_handshake_failures() {
+        let mut handshake = AoaHandshake::new(AccessoryStrings::ANDROID_AUTO);
+        handshake.device_attached(HEAD_UNIT);
+        let result = drive(&mut handshake, &mut MockDevice::new(0), 0).await;
+        assert_eq!(result, Err(AoaError::NotSupported));
+
+        handshake.reset();
+        handshake.device_attached(HEAD_UNIT);
+        let mut device = MockDevice::new(2);
+        device.fail_request = Some(AOA_START);
+        let result = drive(&mut handshake, &mut device, 0).await;
+        assert_eq!(result, Err(AoaError::TransferFailed));
+
+        let mut strings = AccessoryStrings::ANDROID_AUTO;
+        let long = [b'x'; MAX_STRING_LEN + 1];
+        strings.serial = core::str::from_utf8(&long).unwrap();
+        let mut handshake = AoaHandshake::new(strings);
+        handshake.device_attached(HEAD_UNIT);
+        let result = drive(&mut handshake, &mut MockDevice::new(2), 0).await;
+        assert_eq!(result, Err(AoaError::StringTooLong));
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_reenumeration_outcomes() {
+        let mut handshake = AoaHandshake::new(AccessoryStrings::ANDROID_AUTO);
+        handshake.device_attached(HEAD_UNIT);
+        drive(&mut handshake, &mut MockDevice::new(1), 1_000)
+            .await
+            .unwrap();
+
+        // Back with the original IDs: the switch was refused
+        handshake.device_detached();
+        handshake.device_attached(HEAD_UNIT);
+        assert_eq!(handshake.poll(1_200), Step::Failed(AoaError::NotSwitched));
+
+        handshake.reset();
+        handshake.device_attached(HEAD_UNIT);
+        drive(&mut handshake, &mut MockDevice::new(1), 1_000)
+            .await
+            .unwrap();
+        assert_eq!(
+            handshake.poll(1_000 + REENUMERATION_TIMEOUT_MS - 1),
+            Step::WaitForDevice
+        );
+        assert_eq!(
+            handshake.poll(1_000 + REENUMERATION_TIMEOUT_MS),
+            Step::Failed(AoaError::ReenumerationTimeout)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_identity_and_quirk_delays() {
+        let mut identity = AoaIdentity::default();
+        assert_eq!(identity.strings(), AccessoryStrings::ANDROID_AUTO);
+        let serial = StringIndex::from_name("serial").unwrap();
+        identity.set(serial, "HU-BRIDGE-7").unwrap();
+        let long = [b'x'; MAX_STRING_LEN + 1];
+        assert_eq!(
+            identity.set(serial, core::str::from_utf8(&long).unwrap()),
+            Err(AoaError::StringTooLong)
+        );
+
+        let mut handshake = AoaHandshake::new(identity.strings());
+        handshake.device_attached(HEAD_UNIT);
+        handshake.set_quirks(Quirks {
+            request_delay_ms: 10,
+            start_delay_ms: 100,
+            reenumeration_timeout_ms: Some(20_000),
+            ..Quirks::default()
+        });
+        let mut device = MockDevice::new(2);
+
+        // One transfer per call, each followed by a pause
+        let mut now = 0;
+        while drive(&mut handshake, &mut device, now).await == Ok(None) {
+            match handshake.wake_at() {
+                Some(at) => now = at,
+                None => break,
+            }
+        }
+        assert_eq!(device.log.len(), 8);
+        assert_eq!(device.log[6].1.as_slice(), b"HU-BRIDGE-7\0");
+        // 7 request delays plus the extra pause before START
+        assert_eq!(now, 7 * 10 + 100);
+
+        handshake.poll(now);
+        assert_eq!(
+            handshake.poll(now + REENUMERATION_TIMEOUT_MS),
+            Step::WaitForDevice
+        );
+    }
+
+    #[test]
+    fn test_already_in_accessory_mode() {
+        let mut handshake = AoaHandshake::new(AccessoryStrings::ANDROID_AUTO);
+        handshake.device_attached(UsbDeviceInfo {
+            vendor_id: GOOGLE_VID,
+            product_id: 0x2D04,
+        });
+        assert_eq!(
+            handshake.poll(0),
+            Step::Done(AccessoryMode {
+                accessory: true,
+                audio: true,
+                adb: false
+            })
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! # Small-Message Coalescing
+//!
+//! Touch and sensor events arrive in bursts of tiny messages. Framed one by one,
+//! each costs [`FRAME_OVERHEAD`] bytes plus a TCP segment. The [`Batcher`]
+//! packs them into a single frame flagged with [`FLAG_BATCH`].
+//!
+//! ## Batch Payload Format
+//!
+//! ```text
+//! ┌─────────┬──────────┬────────────────┬─────────┬──────────┬─────────────
+//! │ channel │   len    │ postcard bytes │ channel │   len    │  ...
+//! │ 1 byte  │ 2 bytes  │   len bytes    │ 1 byte  │ 2 bytes  │
+//! └─────────┴──────────┴────────────────┴─────────┴──────────┴─────────────
+//! ```
+//!
+//! The batch frame uses `MessageType::Batch`. A batch is flushed when it
+//! reaches `max_bytes` or when its oldest message is `deadline_us` old,
+//! whichever comes first. Receivers use [`parse_frames`], which yields the
+//! same `(Header, Message)` pairs for plain and batched frames.
+
+use heapless::Vec;
+
+use crate::protocol::{
+    FrameBuilder, FrameError, Header, Message, MessageType, FLAG_BATCH, FRAME_OVERHEAD,
+};
+
+/// Per-message overhead inside a batch: channel(1) + length(2)
+pub const ENTRY_OVERHEAD: usize = 3;
+
+/// Flush policy for the batcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Flush once the packed payload reaches this many bytes
+    pub max_bytes: usize,
+    /// Flush once the oldest message has waited this long (microseconds)
+    pub deadline_us: u32,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 512,
+            deadline_us: 500,
+        }
+    }
+}
+
+/// Errors from the batcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BatchError {
+    /// Not enough room left; flush and push again
+    Full,
+    /// The message could not be encoded
+    SerializationError,
+}
+
+/// Batching efficiency counters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatchStats {
+    /// Batch frames emitted
+    pub batches: u32,
+    /// Messages packed into batches
+    pub messages: u32,
+    /// Flushes triggered by the size threshold
+    pub size_flushes: u32,
+    /// Flushes triggered by the deadline
+    pub deadline_flushes: u32,
+    /// Wire bytes saved compared to one frame per message
+    pub bytes_saved: u64,
+}
+
+impl BatchStats {
+    /// Average messages per batch, in hundredths
+    pub fn messages_per_batch_x100(&self) -> u32 {
+        if self.batches == 0 {
+            return 0;
+        }
+        (self.messages as u64 * 100 / self.batches as u64) as u32
+    }
+}
+
+/// Coalesces small messages into batch frames
+///
+/// `N` is the capacity of the packed payload in bytes.
+pub struct Batcher<const N: usize> {
+    config: BatchConfig,
+    payload: Vec<u8, N>,
+    count: u32,
+    oldest_us: u64,
+    stats: BatchStats,
+}
+
+impl<const N: usize> Batcher<N> {
+    /// Create an empty batcher
+    pub fn new(config: BatchConfig) -> Self {
+        Self {
+            config,
+            payload: Vec::new(),
+            count: 0,
+            oldest_us: 0,
+            stats: BatchStats::default(),
+        }
+    }
+
+    /// Number of messages waiting
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Check if nothing is waiting
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Get batching statistics
+    pub fn stats(&self) -> &BatchStats {
+        &self.stats
+    }
+
+    /// Queue a message for `channel`
+    ///
+    /// Returns `true` if the size threshold has been reached and the batch
+    /// should be flushed now.
+    pub fn push(&mut self, msg: &Message, channel: u8, now_us: u64) -> Result<bool, BatchError> {
+        let start = self.payload.len();
+        let room = N - start;
+        if room <= ENTRY_OVERHEAD {
+            return Err(BatchError::Full);
+        }
+
+        // Encode in place after a reserved entry header
+        let body = start + ENTRY_OVERHEAD;
+        // Cannot fail: resizing up to capacity
+        let _ = self.payload.resize(N, 0);
+        let encoded = msg.serialize(&mut self.payload[body..]).map(|e| e.len());
+        let len = match encoded {
+            Ok(len) => len,
+            Err(e) => {
+                self.payload.truncate(start);
+                return Err(match e {
+                    postcard::Error::SerializeBufferFull => BatchError::Full,
+                    _ => BatchError::SerializationError,
+                });
+            }
+        };
+        self.payload.truncate(body + len);
+        self.payload[start] = channel;
+        self.payload[start + 1..body].copy_from_slice(&(len as u16).to_le_bytes());
+
+        if self.count == 0 {
+            self.oldest_us = now_us;
+        }
+        self.count += 1;
+
+        Ok(self.payload.len() >= self.config.max_bytes)
+    }
+
+    /// Check if the deadline of the oldest queued message has passed
+    pub fn is_due(&self, now_us: u64) -> bool {
+        self.count > 0
+            && now_us.saturating_sub(self.oldest_us) >= self.config.deadline_us as u64
+    }
+
+    /// Emit the queued messages as one batch frame into `buffer`
+    ///
+    /// Returns the frame length, or 0 if nothing was queued.
+    pub fn flush(
+        &mut self,
+        builder: &mut FrameBuilder,
+        buffer: &mut [u8],
+        now_us: u64,
+    ) -> Result<usize, FrameError> {
+        if self.count == 0 {
+            return Ok(0);
+        }
+
+        let len = builder.build_raw_frame(MessageType::Batch, 0, FLAG_BATCH, &self.payload, buffer)?;
+
+        if self.payload.len() >= self.config.max_bytes {
+            self.stats.size_flushes += 1;
+        } else if self.is_due(now_us) {
+            self.stats.deadline_flushes += 1;
+        }
+        let unbatched = self.count as u64 * FRAME_OVERHEAD as u64;
+        let batched = (FRAME_OVERHEAD + ENTRY_OVERHEAD * self.count as usize) as u64;
+        self.stats.bytes_saved += unbatched.saturating_sub(batched);
+        self.stats.batches += 1;
+        self.stats.messages += self.count;
+
+        self.payload.clear();
+        self.count = 0;
+        Ok(len)
+    }
+}
+
+impl<const N: usize> Default for Batcher<N> {
+    fn default() -> Self {
+        Self::new(BatchConfig::default())
+    }
+}
+
+/// Iterator over the `(Header, Message)` pairs of one frame
+///
+/// Plain frames yield a single item; batched frames yield one item per packed
+/// message, with the entry's channel and length and the batch's sequence.
+pub struct Frames<'a> {
+    header: Header,
+    single: Option<&'a [u8]>,
+    batch: &'a [u8],
+}
+
+/// Parse a plain or batched frame
+pub fn parse_frames(data: &[u8]) -> Result<Frames<'_>, FrameError> {
+    let raw = FrameBuilder::parse_raw_frame(data)?;
+    let batched = raw.header.flags & FLAG_BATCH != 0;
+    Ok(Frames {
+        header: raw.header,
+        single: (!batched).then_some(raw.payload),
+        batch: if batched { raw.payload } else { &[] },
+    })
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<(Header, Message), FrameError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(payload) = self.single.take() {
+            let result = Message::deserialize(payload)
+                .map(|msg| (self.header, msg))
+                .map_err(|_| FrameError::DeserializationError);
+            return Some(result);
+        }
+
+        let [channel, lo, hi, rest @ ..] = self.batch else {
+            if self.batch.is_empty() {
+                return None;
+            }
+            self.batch = &[];
+            return Some(Err(FrameError::TooShort));
+        };
+        let len = u16::from_le_bytes([*lo, *hi]) as usize;
+        if rest.len() < len {
+            self.batch = &[];
+            return Some(Err(FrameError::TooShort));
+        }
+
+        let (entry, remaining) = rest.split_at(len);
+        self.batch = remaining;
+        let header = Header {
+            sequence: self.header.sequence,
+            payload_len: len as u16,
+            channel: *channel,
+            flags: self.header.flags & !FLAG_BATCH,
+        };
+        let result = Message::deserialize(entry)
+            .map(|msg| (header, msg))
+            .map_err(|_| FrameError::DeserializationError);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_roundtrip() {
+        let mut batcher = Batcher::<256>::default();
+        let mut builder = FrameBuilder::new();
+
+        batcher.push(&Message::Ping { timestamp: 1 }, 3, 0).unwrap();
+        batcher.push(&Message::Ack { sequence: 7 }, 4, 10).unwrap();
+        assert_eq!(batcher.len(), 2);
+
+        let mut buffer = [0u8; 256];
+        let len = batcher.flush(&mut builder, &mut buffer, 20).unwrap();
+        assert!(batcher.is_empty());
+        assert_eq!(
+            FrameBuilder::parse_frame(&buffer[..len]),
+            Err(FrameError::UnexpectedBatch)
+        );
+
+        let mut frames = parse_frames(&buffer[..len]).unwrap();
+        let (header, msg) = frames.next().unwrap().unwrap();
+        assert_eq!((header.channel, msg), (3, Message::Ping { timestamp: 1 }));
+        let (header, msg) = frames.next().unwrap().unwrap();
+        assert_eq!((header.channel, msg), (4, Message::Ack { sequence: 7 }));
+        assert_eq!(header.flags & FLAG_BATCH, 0);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn test_plain_frame_passthrough() {
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+        let msg = Message::Pong { timestamp: 99 };
+        let len = builder.build_frame(&msg, 2, &mut buffer).unwrap();
+
+        let items: Vec<_, 2> = parse_frames(&buffer[..len]).unwrap().collect();
+        assert_eq!(items.len(), 1);
+        let (header, parsed) = items[0].clone().unwrap();
+        assert_eq!((header.channel, parsed), (2, msg));
+    }
+
+    #[test]
+    fn test_flush_triggers() {
+        let mut batcher = Batcher::<64>::new(BatchConfig {
+            max_bytes: 10,
+            deadline_us: 500,
+        });
+
+        assert!(!batcher.is_due(0));
+        assert_eq!(batcher.push(&Message::Ping { timestamp: 1 }, 0, 100), Ok(false));
+        assert!(!batcher.is_due(599));
+        assert!(batcher.is_due(600));
+
+        // Second message crosses the size threshold
+        assert_eq!(batcher.push(&Message::Ping { timestamp: 2 }, 0, 200), Ok(true));
+    }
+
+    #[test]
+    fn test_full_and_stats() {
+        let mut batcher = Batcher::<16>::default();
+        let mut builder = FrameBuilder::new();
+        let msg = Message::Ping { timestamp: 0x0FFF_FFFF };
+
+        batcher.push(&msg, 0, 0).unwrap();
+        batcher.push(&msg, 0, 0).unwrap();
+        assert_eq!(batcher.push(&msg, 0, 0), Err(BatchError::Full));
+
+        let mut buffer = [0u8; 64];
+        batcher.flush(&mut builder, &mut buffer, 1000).unwrap();
+        let stats = batcher.stats();
+        assert_eq!(stats.batches, 1);
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.deadline_flushes, 1);
+        assert_eq!(stats.messages_per_batch_x100(), 200);
+        // 2 * 13 - (13 + 2 * 3)
+        assert_eq!(stats.bytes_saved, 7);
+    }
+}
@@ -35,7 +35,51 @@
 //!            ▲                       ▲
 //!         read_idx               write_idx
 //! ```
-
+//!
+//! ## Producer/Consumer Handles
+//!
+//! [`RingBuffer::split`] hands out a [`Producer`] and a [`Consumer`]
+//! that can live in different tasks, threads or interrupt handlers. Both
+//! borrow the buffer exclusively, so there is exactly one of each while
+//! they exist. For `'static` handles, split a buffer placed in static
+//! memory:
+//!
+//! ```ignore
+//! static USB_TO_WIFI: StaticCell<ZeroCopyBuffer> = StaticCell::new();
+//!
+//! let (producer, consumer) = USB_TO_WIFI.init(ZeroCopyBuffer::new()).split();
+//! spawner.spawn(usb_rx_task(producer))?;
+//! spawner.spawn(wifi_tx_task(consumer))?;
+//! ```
+//!
+//! The producer publishes data by storing `write_idx` with `Release` after
+//! writing; the consumer loads it with `Acquire` before reading, and the
+//! reverse holds for `read_idx` when space is freed.
+//!
+//! ## Contiguous Grants
+//!
+//! `writable_slice_mut` may hand out two chunks when free space wraps.
+//! A DMA transfer or an in-place frame builder needs one, so
+//! [`Producer::grant_exact`] reserves exactly `len` contiguous bytes. If
+//! they don't fit before the end of the ring, the grant starts at offset 0
+//! and the tail is skipped:
+//!
+//! ```text
+//! ┌──────────────┬────────────┬──────────────┬─────────┐
+//! │    grant     │    free    │   readable   │ padding │
+//! └──────────────┴────────────┴──────────────┴─────────┘
+//! 0              ▲            ▲              ▲         N
+//!            write_idx     read_idx        N - pad
+//! ```
+//!
+//! The padding is only published together with the grant's data and is
+//! skipped by the consumer without ever being read, so readers see the
+//! same byte stream as before. [`Grant::commit`] publishes the bytes
+//! actually used; dropping the grant (or [`Grant::abort`]) publishes
+//! nothing.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use crate::traits::ForwarderError;
@@ -46,11 +90,11 @@ use crate::traits::ForwarderError;
 /// - Aligned to power of 2 for efficient modulo operations
 pub const BUFFER_SIZE: usize = 32 * 1024; // 32KB
 
-/// Mask for efficient modulo operation (BUFFER_SIZE - 1)
-const BUFFER_MASK: usize = BUFFER_SIZE - 1;
+/// The default 32KB buffer used for USB↔WiFi forwarding
+pub type ZeroCopyBuffer = RingBuffer<BUFFER_SIZE>;
 
 /// A slice view into the buffer for zero-copy access
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct BufferSlice<'a> {
     /// First contiguous chunk (before wrap-around)
     pub first: &'a [u8],
@@ -68,6 +112,32 @@ impl<'a> BufferSlice<'a> {
     pub fn is_empty(&self) -> bool {
         self.first.is_empty() && self.second.is_empty()
     }
+
+    /// Copy both chunks into the start of `buf`; returns the bytes copied
+    ///
+    /// `buf` must hold at least `len()` bytes.
+    pub fn copy_to(&self, buf: &mut [u8]) -> usize {
+        let (head, tail) = buf.split_at_mut(self.first.len());
+        head.copy_from_slice(self.first);
+        tail[..self.second.len()].copy_from_slice(self.second);
+        self.len()
+    }
+
+    /// The first `len` bytes across both chunks
+    pub fn prefix(&self, len: usize) -> BufferSlice<'a> {
+        if len <= self.first.len() {
+            BufferSlice {
+                first: &self.first[..len],
+                second: &[],
+            }
+        } else {
+            let second_len = (len - self.first.len()).min(self.second.len());
+            BufferSlice {
+                first: self.first,
+                second: &self.second[..second_len],
+            }
+        }
+    }
 }
 
 /// A mutable slice view into the buffer for zero-copy writes
@@ -89,6 +159,15 @@ impl<'a> BufferSliceMut<'a> {
     pub fn is_empty(&self) -> bool {
         self.first.is_empty() && self.second.is_empty()
     }
+
+    /// Fill both chunks from the start of `data`
+    ///
+    /// `data` must hold at least `len()` bytes.
+    pub fn copy_from(self, data: &[u8]) {
+        let (head, tail) = data.split_at(self.first.len());
+        self.first.copy_from_slice(head);
+        self.second.copy_from_slice(&tail[..self.second.len()]);
+    }
 }
 
 /// Errors specific to buffer operations
@@ -115,10 +194,16 @@ impl From<BufferError> for ForwarderError {
 
 /// Zero-copy ring buffer for high-performance data forwarding
 ///
+/// `N` is the capacity in bytes and must be a power of two (checked at
+/// compile time); one byte is kept free to tell full from empty. Size
+/// each queue to its traffic: 1KB for control and input, the default
+/// [`ZeroCopyBuffer`] for projection data, more for video on a host.
+///
 /// This buffer is designed for the producer-consumer pattern where:
 /// - USB peripheral writes data (producer)
 /// - WiFi peripheral reads data (consumer)
-/// (or vice versa)
+///
+/// or vice versa.
 ///
 /// # Thread Safety
 ///
@@ -128,7 +213,7 @@ impl From<BufferError> for ForwarderError {
 /// # Example
 ///
 /// ```rust
-/// use shared::buffer::ZeroCopyBuffer;
+/// use shared::buffer::{RingBuffer, ZeroCopyBuffer};
 ///
 /// let mut buffer = ZeroCopyBuffer::new();
 ///
@@ -141,11 +226,16 @@ impl From<BufferError> for ForwarderError {
 /// let data = buffer.readable_slice(5).unwrap();
 /// assert_eq!(data, b"hello");
 /// buffer.consume(5).unwrap();
+///
+/// // A smaller queue for control messages
+/// let control = RingBuffer::<1024>::new();
+/// assert_eq!(control.writable_len(), 1023);
 /// ```
-pub struct ZeroCopyBuffer {
+pub struct RingBuffer<const N: usize> {
     /// The actual buffer storage
-    /// Using a fixed-size array for static allocation
-    data: [u8; BUFFER_SIZE],
+    /// Using a fixed-size array for static allocation; the cell lets the
+    /// producer write free space while the consumer reads published data
+    data: UnsafeCell<[u8; N]>,
     
     /// Write index (where producer writes next)
     /// Uses atomic for lock-free access in SPSC scenario
@@ -154,37 +244,64 @@ pub struct ZeroCopyBuffer {
     /// Read index (where consumer reads next)
     /// Uses atomic for lock-free access in SPSC scenario
     read_idx: AtomicUsize,
+
+    /// Length of the tail skipped by a wrapped grant, 0 if none
+    /// Set by the producer before publishing, cleared by the consumer
+    pad: AtomicUsize,
 }
 
-impl ZeroCopyBuffer {
+// SAFETY: bytes are only written through `&mut self` or a `Producer`, and
+// both exclude any other access to the same bytes; shared access only
+// reads data and updates the atomic indices.
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Mask for efficient modulo operation (N - 1)
+    const MASK: usize = N - 1;
+
     /// Create a new zero-initialized buffer
     pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two() && N >= 2, "capacity must be a power of two") };
         Self {
-            data: [0u8; BUFFER_SIZE],
+            data: UnsafeCell::new([0u8; N]),
             write_idx: AtomicUsize::new(0),
             read_idx: AtomicUsize::new(0),
+            pad: AtomicUsize::new(0),
         }
     }
 
+    /// Split into a producer and a consumer that can be used concurrently
+    ///
+    /// The handles borrow the buffer mutably, so the buffer itself cannot
+    /// be touched, or split again, until both are dropped.
+    pub fn split(&mut self) -> (Producer<'_, N>, Consumer<'_, N>) {
+        let buffer = &*self;
+        (
+            Producer { buffer },
+            Consumer { buffer },
+        )
+    }
+
     /// Get the total capacity of the buffer
     #[inline]
     pub const fn capacity(&self) -> usize {
-        BUFFER_SIZE
+        N
     }
 
     /// Get the number of bytes available to read
     #[inline]
     pub fn readable_len(&self) -> usize {
-        let write = self.write_idx.load(Ordering::Acquire);
-        let read = self.read_idx.load(Ordering::Acquire);
-        write.wrapping_sub(read) & BUFFER_MASK
+        self.readable_at(self.read_idx.load(Ordering::Acquire)).0
     }
 
     /// Get the number of bytes available to write
     #[inline]
     pub fn writable_len(&self) -> usize {
-        // Leave one byte to distinguish full from empty
-        BUFFER_SIZE - 1 - self.readable_len()
+        let write = self.write_idx.load(Ordering::Acquire);
+        let read = self.read_idx.load(Ordering::Acquire);
+        // Leave one byte to distinguish full from empty; padding counts
+        // as used until the consumer skips it
+        N - 1 - (write.wrapping_sub(read) & Self::MASK)
     }
 
     /// Check if the buffer is empty
@@ -199,6 +316,96 @@ impl ZeroCopyBuffer {
         self.writable_len() == 0
     }
 
+    /// Readable bytes from `read`, and the offset where the current lap's
+    /// data ends (`N` unless a grant's padding lies ahead)
+    fn readable_at(&self, read: usize) -> (usize, usize) {
+        // `pad` is stored before `write_idx` is published, so loading it
+        // after `write_idx` sees the padding of every visible grant
+        let write = self.write_idx.load(Ordering::Acquire);
+        let used = write.wrapping_sub(read) & Self::MASK;
+        let pad = self.pad.load(Ordering::Relaxed);
+        if pad > 0 && (read & Self::MASK) + used > N - pad {
+            (used - pad, N - pad)
+        } else {
+            (used, N)
+        }
+    }
+
+    /// Advance `read` past `len` bytes, skipping padding reached on the way
+    fn consume_at(&self, read: usize, len: usize) -> Result<(), BufferError> {
+        let (available, end) = self.readable_at(read);
+        if len > available {
+            return Err(BufferError::Underflow);
+        }
+
+        let mut new_read = read.wrapping_add(len);
+        if end < N && (read & Self::MASK) + len >= end {
+            new_read = new_read.wrapping_add(N - end);
+            self.pad.store(0, Ordering::Relaxed);
+        }
+        self.read_idx.store(new_read, Ordering::Release);
+        Ok(())
+    }
+
+    /// Reserve `len` contiguous free bytes, wrapping early if needed
+    ///
+    /// # Safety
+    ///
+    /// The caller must be the only writer while the grant is alive.
+    unsafe fn grant(&self, len: usize) -> Result<Grant<'_, N>, BufferError> {
+        if len >= N {
+            return Err(BufferError::SizeExceedsCapacity);
+        }
+
+        let write = self.write_idx.load(Ordering::Relaxed);
+        let read = self.read_idx.load(Ordering::Acquire);
+        let free = N - 1 - (write.wrapping_sub(read) & Self::MASK);
+        let offset = write & Self::MASK;
+        let tail = N - offset;
+        let (offset, pad) = if len <= tail { (offset, 0) } else { (0, tail) };
+        if pad + len > free {
+            return Err(BufferError::Overflow);
+        }
+
+        Ok(Grant {
+            buffer: self,
+            offset,
+            len,
+            pad,
+        })
+    }
+
+    /// View `len` bytes starting at ring offset `start`, split at `end`
+    ///
+    /// # Safety
+    ///
+    /// Nothing may write these bytes while the view is alive.
+    unsafe fn view(&self, start: usize, len: usize, end: usize) -> BufferSlice<'_> {
+        let ptr = self.data.get() as *const u8;
+        // Sitting on padding: the data continues at offset 0
+        let start = if start >= end { 0 } else { start };
+        let first_len = len.min(end - start);
+        BufferSlice {
+            first: core::slice::from_raw_parts(ptr.add(start), first_len),
+            second: core::slice::from_raw_parts(ptr, len - first_len),
+        }
+    }
+
+    /// Mutable view of `len` bytes starting at ring offset `start`
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may access these bytes while the view is alive.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn view_mut(&self, start: usize, len: usize) -> BufferSliceMut<'_> {
+        let ptr = self.data.get() as *mut u8;
+        let first_len = len.min(N - start);
+        BufferSliceMut {
+            first: core::slice::from_raw_parts_mut(ptr.add(start), first_len),
+            second: core::slice::from_raw_parts_mut(ptr, len - first_len),
+        }
+    }
+
     /// Get a readable slice of up to `max_len` bytes
     ///
     /// This returns a direct view into the buffer memory for zero-copy reads.
@@ -208,24 +415,14 @@ impl ZeroCopyBuffer {
     /// - `Some(&[u8])` if data is available
     /// - `None` if buffer is empty
     pub fn readable_slice(&self, max_len: usize) -> Option<&[u8]> {
-        let available = self.readable_len();
-        if available == 0 {
+        let slice = self.readable_split(max_len);
+        if slice.is_empty() {
             return None;
         }
 
-        let len = max_len.min(available);
-        let read_idx = self.read_idx.load(Ordering::Acquire) & BUFFER_MASK;
-        
-        // Check for wrap-around
-        let end_idx = read_idx + len;
-        if end_idx <= BUFFER_SIZE {
-            // No wrap-around, return single slice
-            Some(&self.data[read_idx..read_idx + len])
-        } else {
-            // With wrap-around, only return first chunk
-            // Caller should call again for second chunk
-            Some(&self.data[read_idx..BUFFER_SIZE])
-        }
+        // With wrap-around, only return first chunk
+        // Caller should call again for second chunk
+        Some(slice.first)
     }
 
     /// Get a split readable view (handles wrap-around)
@@ -233,39 +430,15 @@ impl ZeroCopyBuffer {
     /// Returns two slices that together contain all readable data,
     /// handling the case where data wraps around the buffer end.
     pub fn readable_split(&self, max_len: usize) -> BufferSlice<'_> {
-        let available = self.readable_len();
-        let len = max_len.min(available);
-        
-        if len == 0 {
-            return BufferSlice {
-                first: &[],
-                second: &[],
-            };
-        }
-
-        let read_idx = self.read_idx.load(Ordering::Acquire) & BUFFER_MASK;
-        let end_idx = read_idx + len;
-
-        if end_idx <= BUFFER_SIZE {
-            BufferSlice {
-                first: &self.data[read_idx..end_idx],
-                second: &[],
-            }
-        } else {
-            let first_len = BUFFER_SIZE - read_idx;
-            let second_len = len - first_len;
-            BufferSlice {
-                first: &self.data[read_idx..BUFFER_SIZE],
-                second: &self.data[0..second_len],
-            }
-        }
+        let read = self.read_idx.load(Ordering::Acquire);
+        let (available, end) = self.readable_at(read);
+        // SAFETY: bytes are only written through `&mut self` or a
+        // `Producer`, neither of which can exist while `&self` is borrowed
+        unsafe { self.view(read & Self::MASK, max_len.min(available), end) }
     }
 
     /// Get a mutable writable slice for zero-copy writes
     ///
-    /// # Safety
-    ///
-    /// The caller must ensure only one writer accesses this at a time.
     /// After writing, call `commit()` to make data available to readers.
     pub fn writable_slice_mut(&mut self, max_len: usize) -> Result<BufferSliceMut<'_>, BufferError> {
         let available = self.writable_len();
@@ -274,32 +447,17 @@ impl ZeroCopyBuffer {
         }
 
         let len = max_len.min(available);
-        let write_idx = self.write_idx.load(Ordering::Acquire) & BUFFER_MASK;
-        let end_idx = write_idx + len;
-
-        if end_idx <= BUFFER_SIZE {
-            Ok(BufferSliceMut {
-                first: &mut self.data[write_idx..end_idx],
-                second: &mut [],
-            })
-        } else {
-            let first_len = BUFFER_SIZE - write_idx;
-            let (first_part, rest) = self.data.split_at_mut(BUFFER_SIZE);
-            let _ = rest; // Silence unused warning
-            
-            // Need to handle wrap-around carefully
-            let first = &mut self.data[write_idx..BUFFER_SIZE];
-            let second_len = len - first_len;
-            
-            // This is safe because we're in the same buffer, just different regions
-            // We need unsafe here due to borrow checker limitations with split borrows
-            unsafe {
-                let ptr = self.data.as_mut_ptr();
-                let first = core::slice::from_raw_parts_mut(ptr.add(write_idx), first_len);
-                let second = core::slice::from_raw_parts_mut(ptr, second_len);
-                Ok(BufferSliceMut { first, second })
-            }
-        }
+        let write_idx = self.write_idx.load(Ordering::Acquire) & Self::MASK;
+        // SAFETY: `&mut self` rules out any other access
+        Ok(unsafe { self.view_mut(write_idx, len) })
+    }
+
+    /// Reserve exactly `len` contiguous bytes to fill in place
+    ///
+    /// See [`Producer::grant_exact`].
+    pub fn grant_exact(&mut self, len: usize) -> Result<Grant<'_, N>, BufferError> {
+        // SAFETY: `&mut self` rules out any other writer
+        unsafe { self.grant(len) }
     }
 
     /// Get a direct mutable reference to the underlying buffer
@@ -311,13 +469,13 @@ impl ZeroCopyBuffer {
     /// Caller must ensure proper synchronization and call `commit()` after writing.
     #[inline]
     pub fn as_mut_ptr(&mut self) -> *mut u8 {
-        self.data.as_mut_ptr()
+        self.data.get_mut().as_mut_ptr()
     }
 
     /// Get the write index for DMA setup
     #[inline]
     pub fn write_offset(&self) -> usize {
-        self.write_idx.load(Ordering::Acquire) & BUFFER_MASK
+        self.write_idx.load(Ordering::Acquire) & Self::MASK
     }
 
     /// Commit written bytes, making them available to readers
@@ -339,15 +497,7 @@ impl ZeroCopyBuffer {
     ///
     /// Call this after successfully processing data from `readable_slice()`.
     pub fn consume(&self, len: usize) -> Result<(), BufferError> {
-        if len > self.readable_len() {
-            return Err(BufferError::Underflow);
-        }
-
-        let old_read = self.read_idx.load(Ordering::Acquire);
-        let new_read = old_read.wrapping_add(len);
-        self.read_idx.store(new_read, Ordering::Release);
-        
-        Ok(())
+        self.consume_at(self.read_idx.load(Ordering::Acquire), len)
     }
 
     /// Write data from a slice into the buffer
@@ -357,60 +507,219 @@ impl ZeroCopyBuffer {
         if data.len() > self.writable_len() {
             return Err(BufferError::Overflow);
         }
-
-        let write_idx = self.write_idx.load(Ordering::Acquire) & BUFFER_MASK;
-        let len = data.len();
-
-        // Check for wrap-around
-        if write_idx + len <= BUFFER_SIZE {
-            // No wrap-around
-            self.data[write_idx..write_idx + len].copy_from_slice(data);
-        } else {
-            // Handle wrap-around
-            let first_len = BUFFER_SIZE - write_idx;
-            self.data[write_idx..BUFFER_SIZE].copy_from_slice(&data[..first_len]);
-            self.data[0..len - first_len].copy_from_slice(&data[first_len..]);
+        if data.is_empty() {
+            return Ok(0);
         }
 
-        self.commit(len)?;
-        Ok(len)
+        // Handles wrap-around
+        let region = self.writable_slice_mut(data.len())?;
+        region.copy_from(data);
+
+        self.commit(data.len())?;
+        Ok(data.len())
     }
 
     /// Read data from the buffer into a slice
     ///
     /// This performs a copy but is convenient for non-DMA scenarios.
     pub fn read(&self, buf: &mut [u8]) -> Result<usize, BufferError> {
-        let available = self.readable_len();
+        // Handles wrap-around
+        let len = self.readable_split(buf.len()).copy_to(buf);
+        self.consume(len)?;
+        Ok(len)
+    }
+
+    /// Reset the buffer to empty state
+    pub fn reset(&mut self) {
+        self.read_idx.store(0, Ordering::Release);
+        self.write_idx.store(0, Ordering::Release);
+        self.pad.store(0, Ordering::Release);
+    }
+}
+
+/// Writing half of a split [`RingBuffer`]
+///
+/// Only fills free space and publishes it with `commit`; it never touches
+/// bytes the consumer can see. There is one per split and it is `Send`.
+pub struct Producer<'a, const N: usize = BUFFER_SIZE> {
+    buffer: &'a RingBuffer<N>,
+}
+
+impl<const N: usize> Producer<'_, N> {
+    /// Get the number of bytes that can be written
+    pub fn writable_len(&self) -> usize {
+        let read = self.buffer.read_idx.load(Ordering::Acquire);
+        let write = self.buffer.write_idx.load(Ordering::Relaxed);
+        N - 1 - (write.wrapping_sub(read) & RingBuffer::<N>::MASK)
+    }
+
+    /// Check if there is no free space
+    pub fn is_full(&self) -> bool {
+        self.writable_len() == 0
+    }
+
+    /// Get free space to fill in place; publish it with `commit()`
+    pub fn writable_slice_mut(&mut self, max_len: usize) -> Result<BufferSliceMut<'_>, BufferError> {
+        let available = self.writable_len();
         if available == 0 {
-            return Ok(0);
+            return Err(BufferError::Overflow);
         }
+        let len = max_len.min(available);
+        let write_idx = self.buffer.write_idx.load(Ordering::Relaxed) & RingBuffer::<N>::MASK;
+        // SAFETY: free space is invisible to the consumer until committed,
+        // and `&mut self` allows one view at a time
+        Ok(unsafe { self.buffer.view_mut(write_idx, len) })
+    }
 
-        let len = buf.len().min(available);
-        let read_idx = self.read_idx.load(Ordering::Acquire) & BUFFER_MASK;
+    /// Publish `len` written bytes to the consumer
+    pub fn commit(&mut self, len: usize) -> Result<(), BufferError> {
+        if len > self.writable_len() {
+            return Err(BufferError::Overflow);
+        }
+        let write = self.buffer.write_idx.load(Ordering::Relaxed);
+        self.buffer
+            .write_idx
+            .store(write.wrapping_add(len), Ordering::Release);
+        Ok(())
+    }
 
-        // Check for wrap-around
-        if read_idx + len <= BUFFER_SIZE {
-            // No wrap-around
-            buf[..len].copy_from_slice(&self.data[read_idx..read_idx + len]);
-        } else {
-            // Handle wrap-around
-            let first_len = BUFFER_SIZE - read_idx;
-            buf[..first_len].copy_from_slice(&self.data[read_idx..BUFFER_SIZE]);
-            buf[first_len..len].copy_from_slice(&self.data[0..len - first_len]);
+    /// Copy `data` in and publish it; all or nothing
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, BufferError> {
+        if data.len() > self.writable_len() {
+            return Err(BufferError::Overflow);
+        }
+        if data.is_empty() {
+            return Ok(0);
         }
+        self.writable_slice_mut(data.len())?.copy_from(data);
+        self.commit(data.len())?;
+        Ok(data.len())
+    }
+
+    /// Reserve exactly `len` contiguous bytes to fill in place
+    ///
+    /// If they don't fit before the end of the ring, the grant starts at
+    /// offset 0 and the tail is skipped, which costs that much free space
+    /// until the consumer passes it. Fails with `Overflow` if there is not
+    /// enough room now, or `SizeExceedsCapacity` if there never will be.
+    pub fn grant_exact(&mut self, len: usize) -> Result<Grant<'_, N>, BufferError> {
+        // SAFETY: the grant borrows the only producer mutably
+        unsafe { self.buffer.grant(len) }
+    }
+}
+
+/// Reading half of a split [`RingBuffer`]
+///
+/// Only reads published data and frees it with `consume`. There is one per
+/// split and it is `Send`.
+pub struct Consumer<'a, const N: usize = BUFFER_SIZE> {
+    buffer: &'a RingBuffer<N>,
+}
 
+impl<const N: usize> Consumer<'_, N> {
+    /// Get the number of bytes ready to read
+    pub fn readable_len(&self) -> usize {
+        self.buffer
+            .readable_at(self.buffer.read_idx.load(Ordering::Relaxed))
+            .0
+    }
+
+    /// Check if there is nothing to read
+    pub fn is_empty(&self) -> bool {
+        self.readable_len() == 0
+    }
+
+    /// View up to `max_len` published bytes; free them with `consume()`
+    pub fn readable_split(&self, max_len: usize) -> BufferSlice<'_> {
+        let read = self.buffer.read_idx.load(Ordering::Relaxed);
+        let (available, end) = self.buffer.readable_at(read);
+        let len = max_len.min(available);
+        // SAFETY: the producer does not write published bytes, and they
+        // stay published until `consume`, which needs `&mut self`
+        unsafe { self.buffer.view(read & RingBuffer::<N>::MASK, len, end) }
+    }
+
+    /// View the next contiguous run of up to `max_len` bytes, `None` if empty
+    pub fn readable_slice(&self, max_len: usize) -> Option<&[u8]> {
+        let slice = self.readable_split(max_len);
+        (!slice.is_empty()).then_some(slice.first)
+    }
+
+    /// Free `len` bytes for the producer
+    pub fn consume(&mut self, len: usize) -> Result<(), BufferError> {
+        let read = self.buffer.read_idx.load(Ordering::Relaxed);
+        self.buffer.consume_at(read, len)
+    }
+
+    /// Copy out up to `buf.len()` bytes and free them
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, BufferError> {
+        let len = self.readable_split(buf.len()).copy_to(buf);
         self.consume(len)?;
         Ok(len)
     }
+}
 
-    /// Reset the buffer to empty state
-    pub fn reset(&mut self) {
-        self.read_idx.store(0, Ordering::Release);
-        self.write_idx.store(0, Ordering::Release);
+/// Contiguous free space reserved by `grant_exact`
+///
+/// Derefs to the reserved bytes. Nothing is visible to the consumer until
+/// [`commit`](Grant::commit); dropping the grant releases the reservation.
+pub struct Grant<'a, const N: usize = BUFFER_SIZE> {
+    buffer: &'a RingBuffer<N>,
+    offset: usize,
+    len: usize,
+    pad: usize,
+}
+
+impl<const N: usize> Grant<'_, N> {
+    /// Publish the first `used` bytes (capped at the grant's length)
+    ///
+    /// Committing 0 bytes publishes nothing, not even the skipped tail.
+    pub fn commit(self, used: usize) {
+        let used = used.min(self.len);
+        if used == 0 {
+            return;
+        }
+        if self.pad > 0 {
+            self.buffer.pad.store(self.pad, Ordering::Relaxed);
+        }
+        let write = self.buffer.write_idx.load(Ordering::Relaxed);
+        self.buffer
+            .write_idx
+            .store(write.wrapping_add(self.pad + used), Ordering::Release);
     }
+
+    /// Release the reservation without publishing anything
+    pub fn abort(self) {}
 }
 
-impl Default for ZeroCopyBuffer {
+impl<const N: usize> Deref for Grant<'_, N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: granted bytes are free space, which only this grant's
+        // producer may touch until they are committed
+        unsafe {
+            core::slice::from_raw_parts(
+                (self.buffer.data.get() as *const u8).add(self.offset),
+                self.len,
+            )
+        }
+    }
+}
+
+impl<const N: usize> DerefMut for Grant<'_, N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as for `deref`, and `&mut self` allows one view at a time
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                (self.buffer.data.get() as *mut u8).add(self.offset),
+                self.len,
+            )
+        }
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
     fn default() -> Self {
         Self::new()
     }
@@ -471,6 +780,163 @@ mod tests {
         assert!(read_len > 0);
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_split_handles_across_threads() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut buffer = std::boxed::Box::new(ZeroCopyBuffer::new());
+        let (mut producer, mut consumer) = buffer.split();
+        assert_send(&producer);
+        assert_send(&consumer);
+
+        // Several laps around the ring, in odd-sized pieces
+        const TOTAL: usize = 3 * BUFFER_SIZE + 123;
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut next = 0usize;
+                while next < TOTAL {
+                    let chunk: std::vec::Vec<u8> =
+                        (next..TOTAL.min(next + 777)).map(|i| i as u8).collect();
+                    if producer.write(&chunk).is_ok() {
+                        next += chunk.len();
+                    }
+                }
+            });
+            scope.spawn(move || {
+                let mut expected = 0usize;
+                let mut buf = [0u8; 1000];
+                while expected < TOTAL {
+                    let n = consumer.read(&mut buf).unwrap();
+                    for &byte in &buf[..n] {
+                        assert_eq!(byte, expected as u8);
+                        expected += 1;
+                    }
+                }
+            });
+        });
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_producer_consumer_in_place() {
+        let mut buffer = ZeroCopyBuffer::new();
+        let (mut producer, mut consumer) = buffer.split();
+
+        let region = producer.writable_slice_mut(5).unwrap();
+        region.first.copy_from_slice(b"hello");
+        // Not visible until committed
+        assert!(consumer.is_empty());
+        producer.commit(5).unwrap();
+
+        assert_eq!(consumer.readable_split(10).first, b"hello");
+        assert_eq!(consumer.consume(6), Err(BufferError::Underflow));
+        consumer.consume(5).unwrap();
+        assert_eq!(producer.writable_len(), BUFFER_SIZE - 1);
+    }
+
+    #[test]
+    fn test_small_capacity_wraps() {
+        let mut buffer = RingBuffer::<8>::new();
+        assert_eq!(buffer.capacity(), 8);
+        assert_eq!(buffer.write(b"abcdefgh"), Err(BufferError::Overflow));
+
+        buffer.write(b"abcde").unwrap();
+        buffer.consume(4).unwrap();
+        buffer.write(b"fghij").unwrap();
+        let slice = buffer.readable_split(8);
+        assert_eq!((slice.first, slice.second), (&b"efgh"[..], &b"ij"[..]));
+
+        let mut out = [0u8; 8];
+        assert_eq!(buffer.read(&mut out), Ok(6));
+        assert_eq!(&out[..6], b"efghij");
+    }
+
+    #[test]
+    fn test_grant_wraps_early_and_consumer_skips_padding() {
+        let mut buffer = RingBuffer::<16>::new();
+        buffer.write(b"0123456789").unwrap();
+        buffer.consume(8).unwrap();
+
+        // 6 bytes before the end, so a 5-byte grant fits in place
+        let mut grant = buffer.grant_exact(5).unwrap();
+        grant.copy_from_slice(b"abcde");
+        grant.commit(5);
+
+        // Only one byte left at the end: wrap and skip it
+        let mut grant = buffer.grant_exact(4).unwrap();
+        grant.copy_from_slice(b"WXYZ");
+        grant.commit(4);
+        assert_eq!(buffer.readable_len(), 11);
+        // The padding stays reserved until it is skipped
+        assert_eq!(buffer.writable_len(), 3);
+
+        let (_, mut consumer) = buffer.split();
+        assert_eq!(consumer.readable_slice(16), Some(&b"89abcde"[..]));
+        let slice = consumer.readable_split(16);
+        assert_eq!((slice.first, slice.second), (&b"89abcde"[..], &b"WXYZ"[..]));
+        consumer.consume(7).unwrap();
+        assert_eq!(consumer.readable_slice(16), Some(&b"WXYZ"[..]));
+
+        let mut out = [0u8; 8];
+        assert_eq!(consumer.read(&mut out), Ok(4));
+        assert_eq!(&out[..4], b"WXYZ");
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.writable_len(), 15);
+    }
+
+    #[test]
+    fn test_grant_abort_and_partial_commit() {
+        let mut buffer = RingBuffer::<16>::new();
+        let (mut producer, mut consumer) = buffer.split();
+
+        producer.grant_exact(8).unwrap().abort();
+        assert!(consumer.is_empty());
+
+        let mut grant = producer.grant_exact(8).unwrap();
+        grant[..3].copy_from_slice(b"abc");
+        grant.commit(3);
+        assert_eq!(consumer.readable_slice(16), Some(&b"abc"[..]));
+        consumer.consume(3).unwrap();
+
+        // A wrapped grant committed empty leaves no padding behind
+        producer.write(&[0; 10]).unwrap();
+        consumer.consume(10).unwrap();
+        producer.grant_exact(5).unwrap().commit(0);
+        assert_eq!(producer.writable_len(), 15);
+        producer.write(b"xyz").unwrap();
+        assert_eq!(consumer.readable_slice(16), Some(&b"xyz"[..]));
+    }
+
+    #[test]
+    fn test_grant_errors() {
+        let mut buffer = RingBuffer::<16>::new();
+        assert_eq!(
+            buffer.grant_exact(16).err(),
+            Some(BufferError::SizeExceedsCapacity)
+        );
+
+        buffer.write(&[0; 12]).unwrap();
+        buffer.consume(6).unwrap();
+        // 9 free bytes, but only 4 at the end and 5 at the start
+        assert_eq!(buffer.writable_len(), 9);
+        assert_eq!(buffer.grant_exact(6).err(), Some(BufferError::Overflow));
+        assert_eq!(buffer.grant_exact(4).map(|grant| grant.len()), Ok(4));
+    }
+
+    #[test]
+    fn test_slice_prefix() {
+        let slice = BufferSlice {
+            first: b"abc",
+            second: b"de",
+        };
+        let head = slice.prefix(2);
+        assert_eq!((head.first, head.second), (&b"ab"[..], &b""[..]));
+        let head = slice.prefix(4);
+        assert_eq!((head.first, head.second), (&b"abc"[..], &b"d"[..]));
+        assert_eq!(slice.prefix(10).len(), 5);
+    }
+
     #[test]
     fn test_overflow_error() {
         let mut buffer = ZeroCopyBuffer::new();
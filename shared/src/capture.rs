@@ -0,0 +1,733 @@
+//! # Traffic Capture Format & Recorder
+//!
+//! This module defines a small, documented capture format for recording what
+//! passes through the bridge, plus taps that record any
+//! `EndpointReader`/`EndpointWriter` pair.
+//!
+//! ## File Layout
+//!
+//! All integers are little-endian.
+//!
+//! ```text
+//! File header (16 bytes)
+//! ┌──────────┬─────────┬────────────┬──────────┬──────────┐
+//! │  "AACP"  │ version │ header_len │ snap_len │ reserved │
+//! │ 4 bytes  │   u16   │    u16     │   u32    │   u32    │
+//! └──────────┴─────────┴────────────┴──────────┴──────────┘
+//!
+//! Record (24-byte header + cap_len bytes)
+//! ┌──────────────┬────────────┬──────┬─────┬─────────┬──────────┬──────────┬─────────┬──────┐
+//! │ timestamp_us │ session_id │ kind │ dir │ channel │ reserved │ orig_len │ cap_len │ data │
+//! │     u64      │    u32     │  u8  │ u8  │   u8    │    u8    │   u32    │   u32   │      │
+//! └──────────────┴────────────┴──────┴─────┴─────────┴──────────┴──────────┴─────────┴──────┘
+//! ```
+//!
+//! - `timestamp_us` is microseconds since the UNIX epoch when the recorder has
+//!   a wall clock, otherwise since an arbitrary monotonic origin.
+//! - `data` holds the first `cap_len` bytes of what was transferred; `orig_len`
+//!   is the full length. Chunks recorded by endpoint taps are raw stream bytes
+//!   and may split or join protocol frames.
+//! - `channel` is [`CHANNEL_UNKNOWN`] for raw stream chunks.
+//!
+//! The std [`Recorder`] writes rotating files so a capture never exceeds
+//! `max_files × max_file_bytes` on disk.
+
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::traits::{EndpointReader, EndpointWriter, ForwarderResult};
+
+/// Magic bytes at the start of every capture file
+pub const CAPTURE_MAGIC: [u8; 4] = *b"AACP";
+
+/// Current capture format version
+pub const CAPTURE_VERSION: u16 = 1;
+
+/// Size of the file header
+pub const FILE_HEADER_LEN: usize = 16;
+
+/// Size of a record header
+pub const RECORD_HEADER_LEN: usize = 24;
+
+/// Channel value used when the channel of a chunk is not known
+pub const CHANNEL_UNKNOWN: u8 = 0xFF;
+
+/// Errors when decoding a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CaptureError {
+    /// Not enough bytes for a header or record
+    Truncated,
+    /// File does not start with [`CAPTURE_MAGIC`]
+    InvalidMagic,
+    /// Unsupported format version
+    UnsupportedVersion,
+    /// Unknown record kind or direction
+    InvalidRecord,
+}
+
+/// Direction of a captured transfer, relative to the recording endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Direction {
+    /// Received (read from an endpoint)
+    Rx = 0,
+    /// Transmitted (written to an endpoint)
+    Tx = 1,
+}
+
+/// What a record describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum RecordKind {
+    /// Transferred bytes
+    Data = 0,
+    /// A session started (`session_id` is the new session)
+    SessionStart = 1,
+    /// A session ended
+    SessionEnd = 2,
+}
+
+/// Capture file header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    /// Format version
+    pub version: u16,
+    /// Maximum bytes stored per record
+    pub snap_len: u32,
+}
+
+impl FileHeader {
+    /// Encode the header
+    pub fn encode(&self) -> [u8; FILE_HEADER_LEN] {
+        let mut out = [0u8; FILE_HEADER_LEN];
+        out[0..4].copy_from_slice(&CAPTURE_MAGIC);
+        out[4..6].copy_from_slice(&self.version.to_le_bytes());
+        out[6..8].copy_from_slice(&(FILE_HEADER_LEN as u16).to_le_bytes());
+        out[8..12].copy_from_slice(&self.snap_len.to_le_bytes());
+        out
+    }
+
+    /// Decode a header, returning it and its encoded length
+    pub fn decode(data: &[u8]) -> Result<(Self, usize), CaptureError> {
+        if data.len() < FILE_HEADER_LEN {
+            return Err(CaptureError::Truncated);
+        }
+        if data[0..4] != CAPTURE_MAGIC {
+            return Err(CaptureError::InvalidMagic);
+        }
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != CAPTURE_VERSION {
+            return Err(CaptureError::UnsupportedVersion);
+        }
+        let header_len = u16::from_le_bytes([data[6], data[7]]) as usize;
+        if header_len < FILE_HEADER_LEN || data.len() < header_len {
+            return Err(CaptureError::Truncated);
+        }
+        let snap_len = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+        Ok((Self { version, snap_len }, header_len))
+    }
+}
+
+/// Record header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordHeader {
+    /// Capture timestamp in microseconds
+    pub timestamp_us: u64,
+    /// Session the record belongs to (0 if none)
+    pub session_id: u32,
+    /// Record kind
+    pub kind: RecordKind,
+    /// Transfer direction
+    pub direction: Direction,
+    /// Protocol channel, or [`CHANNEL_UNKNOWN`]
+    pub channel: u8,
+    /// Original transfer length
+    pub orig_len: u32,
+    /// Number of bytes stored
+    pub cap_len: u32,
+}
+
+impl RecordHeader {
+    /// Encode the header
+    pub fn encode(&self) -> [u8; RECORD_HEADER_LEN] {
+        let mut out = [0u8; RECORD_HEADER_LEN];
+        out[0..8].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        out[8..12].copy_from_slice(&self.session_id.to_le_bytes());
+        out[12] = self.kind as u8;
+        out[13] = self.direction as u8;
+        out[14] = self.channel;
+        out[16..20].copy_from_slice(&self.orig_len.to_le_bytes());
+        out[20..24].copy_from_slice(&self.cap_len.to_le_bytes());
+        out
+    }
+
+    /// Decode a header
+    pub fn decode(data: &[u8]) -> Result<Self, CaptureError> {
+        let data: &[u8; RECORD_HEADER_LEN] = data
+            .get(..RECORD_HEADER_LEN)
+            .and_then(|d| d.try_into().ok())
+            .ok_or(CaptureError::Truncated)?;
+
+        let kind = match data[12] {
+            0 => RecordKind::Data,
+            1 => RecordKind::SessionStart,
+            2 => RecordKind::SessionEnd,
+            _ => return Err(CaptureError::InvalidRecord),
+        };
+        let direction = match data[13] {
+            0 => Direction::Rx,
+            1 => Direction::Tx,
+            _ => return Err(CaptureError::InvalidRecord),
+        };
+        let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+
+        Ok(Self {
+            timestamp_us: u64::from_le_bytes([
+                data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7],
+            ]),
+            session_id: u32_at(8),
+            kind,
+            direction,
+            channel: data[14],
+            orig_len: u32_at(16),
+            cap_len: u32_at(20),
+        })
+    }
+}
+
+/// A record borrowed from capture bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// Record header
+    pub header: RecordHeader,
+    /// Captured bytes (`header.cap_len` long)
+    pub data: &'a [u8],
+}
+
+/// Iterator over the records of an in-memory capture file
+pub struct Records<'a> {
+    data: &'a [u8],
+}
+
+/// Parse a capture file held in memory
+pub fn parse_capture(data: &[u8]) -> Result<(FileHeader, Records<'_>), CaptureError> {
+    let (header, len) = FileHeader::decode(data)?;
+    Ok((header, Records { data: &data[len..] }))
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let header = match RecordHeader::decode(self.data) {
+            Ok(header) => header,
+            Err(e) => {
+                self.data = &[];
+                return Some(Err(e));
+            }
+        };
+        let end = RECORD_HEADER_LEN + header.cap_len as usize;
+        let Some(data) = self.data.get(RECORD_HEADER_LEN..end) else {
+            self.data = &[];
+            return Some(Err(CaptureError::Truncated));
+        };
+        self.data = &self.data[end..];
+        Some(Ok(Record { header, data }))
+    }
+}
+
+/// Destination for captured transfers
+///
+/// The sink is responsible for timestamps and session metadata. Data is
+/// passed as chunks so wrapped ring-buffer regions can be recorded without
+/// copying; together they form one record.
+pub trait CaptureSink {
+    /// Record one transfer
+    fn record(&mut self, direction: Direction, channel: u8, chunks: &[&[u8]]);
+}
+
+/// `EndpointReader` wrapper that records everything it reads
+pub struct TapReader<R, S> {
+    inner: R,
+    sink: S,
+    channel: u8,
+}
+
+impl<R: EndpointReader, S: CaptureSink> TapReader<R, S> {
+    /// Wrap a reader; chunks are recorded as `Rx` on [`CHANNEL_UNKNOWN`]
+    pub fn new(inner: R, sink: S) -> Self {
+        Self {
+            inner,
+            sink,
+            channel: CHANNEL_UNKNOWN,
+        }
+    }
+
+    /// Record chunks under a specific channel instead
+    pub fn with_channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Unwrap the reader and sink
+    pub fn into_inner(self) -> (R, S) {
+        (self.inner, self.sink)
+    }
+}
+
+impl<R: EndpointReader, S: CaptureSink> EndpointReader for TapReader<R, S> {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        let before = buffer.readable_len();
+        let n = self.inner.read_into_buffer(buffer).await?;
+        if n > 0 {
+            // The new bytes are the tail of the readable region
+            let all = buffer.readable_split(before + n);
+            let (first, second) = if all.second.len() >= n {
+                (&all.second[all.second.len() - n..], &[][..])
+            } else {
+                let from_first = n - all.second.len();
+                (&all.first[all.first.len() - from_first..], all.second)
+            };
+            self.sink.record(Direction::Rx, self.channel, &[first, second]);
+        }
+        Ok(n)
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        let n = self.inner.read_into_slice(buf).await?;
+        if n > 0 {
+            self.sink.record(Direction::Rx, self.channel, &[&buf[..n]]);
+        }
+        Ok(n)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.inner.max_packet_size()
+    }
+}
+
+/// `EndpointWriter` wrapper that records everything it writes
+pub struct TapWriter<W, S> {
+    inner: W,
+    sink: S,
+    channel: u8,
+}
+
+impl<W: EndpointWriter, S: CaptureSink> TapWriter<W, S> {
+    /// Wrap a writer; chunks are recorded as `Tx` on [`CHANNEL_UNKNOWN`]
+    pub fn new(inner: W, sink: S) -> Self {
+        Self {
+            inner,
+            sink,
+            channel: CHANNEL_UNKNOWN,
+        }
+    }
+
+    /// Record chunks under a specific channel instead
+    pub fn with_channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Unwrap the writer and sink
+    pub fn into_inner(self) -> (W, S) {
+        (self.inner, self.sink)
+    }
+}
+
+impl<W: EndpointWriter, S: CaptureSink> EndpointWriter for TapWriter<W, S> {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        let n = self.inner.write_from_buffer(buffer, len).await?;
+        if n > 0 {
+            let written = buffer.readable_split(n);
+            self.sink
+                .record(Direction::Tx, self.channel, &[written.first, written.second]);
+        }
+        Ok(n)
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        let n = self.inner.write_from_slice(data).await?;
+        if n > 0 {
+            self.sink.record(Direction::Tx, self.channel, &[&data[..n]]);
+        }
+        Ok(n)
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        let n = self.inner.write_vectored(slice).await?;
+        if n > 0 {
+            let written = slice.prefix(n);
+            self.sink
+                .record(Direction::Tx, self.channel, &[written.first, written.second]);
+        }
+        Ok(n)
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        self.inner.flush().await
+    }
+
+    async fn write_zlp(&mut self) -> ForwarderResult<()> {
+        self.inner.write_zlp().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+}
+
+#[cfg(feature = "std")]
+pub use recorder::{Recorder, RecorderConfig, RecorderStats};
+
+#[cfg(feature = "std")]
+mod recorder {
+    use std::collections::VecDeque;
+    use std::fs::{self, File};
+    use std::io::{self, BufWriter, Write};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use super::*;
+
+    /// Where and how much to record
+    #[derive(Debug, Clone)]
+    pub struct RecorderConfig {
+        /// Directory for capture files
+        pub directory: PathBuf,
+        /// File name prefix; files are named `<prefix>.<n>.aacap`
+        pub prefix: String,
+        /// Rotate once a file reaches this size
+        pub max_file_bytes: u64,
+        /// Number of files kept; the oldest is deleted on rotation
+        pub max_files: usize,
+        /// Maximum bytes stored per record
+        pub snap_len: u32,
+    }
+
+    impl Default for RecorderConfig {
+        fn default() -> Self {
+            Self {
+                directory: std::env::temp_dir(),
+                prefix: "bridge".into(),
+                max_file_bytes: 8 * 1024 * 1024,
+                max_files: 4,
+                snap_len: u16::MAX as u32,
+            }
+        }
+    }
+
+    /// Recorder counters
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct RecorderStats {
+        /// Records written
+        pub records: u64,
+        /// Bytes written across all files
+        pub bytes_written: u64,
+        /// Rotations performed
+        pub rotations: u32,
+        /// Records lost to I/O errors
+        pub write_errors: u32,
+    }
+
+    /// Writes captures to size-bounded rotating files
+    pub struct Recorder {
+        config: RecorderConfig,
+        file: Option<BufWriter<File>>,
+        file_bytes: u64,
+        next_index: u32,
+        files: VecDeque<PathBuf>,
+        session_id: u32,
+        stats: RecorderStats,
+    }
+
+    impl Recorder {
+        /// Create a recorder and open its first file
+        pub fn new(config: RecorderConfig) -> io::Result<Self> {
+            fs::create_dir_all(&config.directory)?;
+            let mut recorder = Self {
+                config,
+                file: None,
+                file_bytes: 0,
+                next_index: 0,
+                files: VecDeque::new(),
+                session_id: 0,
+                stats: RecorderStats::default(),
+            };
+            recorder.rotate()?;
+            Ok(recorder)
+        }
+
+        /// Files currently on disk, oldest first
+        pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+            self.files.iter()
+        }
+
+        /// Get recorder statistics
+        pub fn stats(&self) -> RecorderStats {
+            self.stats
+        }
+
+        /// Mark the start of a session; later records carry its ID
+        pub fn start_session(&mut self, session_id: u32) -> io::Result<()> {
+            self.session_id = session_id;
+            self.write_record(RecordKind::SessionStart, Direction::Rx, CHANNEL_UNKNOWN, &[])
+        }
+
+        /// Mark the end of the current session
+        pub fn end_session(&mut self) -> io::Result<()> {
+            self.write_record(RecordKind::SessionEnd, Direction::Rx, CHANNEL_UNKNOWN, &[])?;
+            self.session_id = 0;
+            Ok(())
+        }
+
+        /// Record a transfer
+        pub fn record_data(
+            &mut self,
+            direction: Direction,
+            channel: u8,
+            chunks: &[&[u8]],
+        ) -> io::Result<()> {
+            self.write_record(RecordKind::Data, direction, channel, chunks)
+        }
+
+        /// Flush buffered records to disk
+        pub fn flush(&mut self) -> io::Result<()> {
+            match self.file.as_mut() {
+                Some(file) => file.flush(),
+                None => Ok(()),
+            }
+        }
+
+        fn write_record(
+            &mut self,
+            kind: RecordKind,
+            direction: Direction,
+            channel: u8,
+            chunks: &[&[u8]],
+        ) -> io::Result<()> {
+            let orig_len: usize = chunks.iter().map(|c| c.len()).sum();
+            let cap_len = orig_len.min(self.config.snap_len as usize);
+            let record_len = (RECORD_HEADER_LEN + cap_len) as u64;
+
+            if self.file_bytes + record_len > self.config.max_file_bytes
+                && self.file_bytes > FILE_HEADER_LEN as u64
+            {
+                self.rotate()?;
+            }
+
+            let header = RecordHeader {
+                timestamp_us: now_us(),
+                session_id: self.session_id,
+                kind,
+                direction,
+                channel,
+                orig_len: orig_len as u32,
+                cap_len: cap_len as u32,
+            };
+            let file = self.file.as_mut().ok_or(io::ErrorKind::NotConnected)?;
+            file.write_all(&header.encode())?;
+            let mut remaining = cap_len;
+            for chunk in chunks {
+                let take = chunk.len().min(remaining);
+                file.write_all(&chunk[..take])?;
+                remaining -= take;
+            }
+
+            self.file_bytes += record_len;
+            self.stats.bytes_written += record_len;
+            self.stats.records += 1;
+            Ok(())
+        }
+
+        /// Close the current file and start the next one
+        fn rotate(&mut self) -> io::Result<()> {
+            if let Some(mut file) = self.file.take() {
+                file.flush()?;
+                self.stats.rotations += 1;
+            }
+
+            while self.files.len() >= self.config.max_files.max(1) {
+                if let Some(oldest) = self.files.pop_front() {
+                    fs::remove_file(oldest)?;
+                }
+            }
+
+            let path = self.config.directory.join(format!(
+                "{}.{}.aacap",
+                self.config.prefix, self.next_index
+            ));
+            self.next_index += 1;
+
+            let mut file = BufWriter::new(File::create(&path)?);
+            let header = FileHeader {
+                version: CAPTURE_VERSION,
+                snap_len: self.config.snap_len,
+            };
+            file.write_all(&header.encode())?;
+
+            self.file = Some(file);
+            self.file_bytes = FILE_HEADER_LEN as u64;
+            self.stats.bytes_written += FILE_HEADER_LEN as u64;
+            self.files.push_back(path);
+            Ok(())
+        }
+    }
+
+    impl Drop for Recorder {
+        fn drop(&mut self) {
+            let _ = self.flush();
+        }
+    }
+
+    impl CaptureSink for Recorder {
+        fn record(&mut self, direction: Direction, channel: u8, chunks: &[&[u8]]) {
+            if self.record_data(direction, channel, chunks).is_err() {
+                self.stats.write_errors += 1;
+            }
+        }
+    }
+
+    /// Shared sink so a reader tap and a writer tap can feed one recorder
+    impl<T: CaptureSink> CaptureSink for Arc<Mutex<T>> {
+        fn record(&mut self, direction: Direction, channel: u8, chunks: &[&[u8]]) {
+            if let Ok(mut sink) = self.lock() {
+                sink.record(direction, channel, chunks);
+            }
+        }
+    }
+
+    fn now_us() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_header_roundtrip() {
+        let header = RecordHeader {
+            timestamp_us: 1_700_000_000_000_000,
+            session_id: 42,
+            kind: RecordKind::Data,
+            direction: Direction::Tx,
+            channel: 3,
+            orig_len: 1500,
+            cap_len: 64,
+        };
+        assert_eq!(RecordHeader::decode(&header.encode()), Ok(header));
+
+        let mut bad = header.encode();
+        bad[13] = 7;
+        assert_eq!(RecordHeader::decode(&bad), Err(CaptureError::InvalidRecord));
+    }
+
+    #[test]
+    fn test_file_header_validation() {
+        let header = FileHeader { version: CAPTURE_VERSION, snap_len: 256 };
+        let encoded = header.encode();
+        assert_eq!(FileHeader::decode(&encoded), Ok((header, FILE_HEADER_LEN)));
+        assert_eq!(FileHeader::decode(&encoded[..8]), Err(CaptureError::Truncated));
+        assert_eq!(FileHeader::decode(&[0u8; 16]), Err(CaptureError::InvalidMagic));
+    }
+
+    #[cfg(feature = "std")]
+    struct VecSink(std::vec::Vec<(Direction, std::vec::Vec<u8>)>);
+
+    #[cfg(feature = "std")]
+    impl CaptureSink for VecSink {
+        fn record(&mut self, direction: Direction, _channel: u8, chunks: &[&[u8]]) {
+            self.0.push((direction, chunks.concat()));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    struct FixedReader(&'static [u8]);
+
+    #[cfg(feature = "std")]
+    impl EndpointReader for FixedReader {
+        async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+            Ok(buffer.write(self.0)?)
+        }
+
+        async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+            buf[..self.0.len()].copy_from_slice(self.0);
+            Ok(self.0.len())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn max_packet_size(&self) -> usize {
+            64
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_tap_reader_records_new_bytes_only() {
+        let mut tap = TapReader::new(FixedReader(b"abc"), VecSink(std::vec::Vec::new()));
+        let mut buffer = ZeroCopyBuffer::new();
+        buffer.write(b"old").unwrap();
+
+        assert_eq!(tap.read_into_buffer(&mut buffer).await, Ok(3));
+        let (_, sink) = tap.into_inner();
+        assert_eq!(sink.0, [(Direction::Rx, b"abc".to_vec())]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_recorder_rotates_and_bounds_files() {
+        let directory = std::env::temp_dir().join(format!("aacap-test-{}", std::process::id()));
+        let config = RecorderConfig {
+            directory: directory.clone(),
+            prefix: "rot".into(),
+            max_file_bytes: 128,
+            max_files: 2,
+            snap_len: 32,
+        };
+        let mut recorder = Recorder::new(config).unwrap();
+        recorder.start_session(7).unwrap();
+        for _ in 0..10 {
+            recorder.record_data(Direction::Tx, 1, &[&[0xAB; 40]]).unwrap();
+        }
+        recorder.flush().unwrap();
+
+        let files: std::vec::Vec<_> = recorder.files().cloned().collect();
+        assert_eq!(files.len(), 2);
+        assert!(recorder.stats().rotations > 0);
+
+        // Every kept file parses and respects snap_len and size bound
+        let bytes = std::fs::read(files.last().unwrap()).unwrap();
+        assert!(bytes.len() as u64 <= 128);
+        let (_, records) = parse_capture(&bytes).unwrap();
+        for record in records {
+            let record = record.unwrap();
+            assert_eq!(record.header.session_id, 7);
+            assert_eq!(record.header.orig_len, 40);
+            assert_eq!(record.data.len(), 32);
+        }
+
+        drop(recorder);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
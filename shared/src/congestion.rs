@@ -0,0 +1,615 @@
+//! # Congestion-Aware Video Frame Dropping
+//!
+//! When the WiFi link can't keep up, the `ZeroCopyBuffer` fills. Rather than
+//! erroring out or stalling the phone, the bridge sheds video in an order that
+//! keeps the stream decodable:
+//!
+//! ```text
+//!  buffer fill   0% ─────────── moderate ─────────── severe ─────── 100%
+//!  video            forward all │ drop non-reference │ drop until next IDR
+//!                               │ frames             │ + request keyframe
+//!  audio / input    never dropped
+//! ```
+//!
+//! Once the policy starts dropping reference frames the decoder's reference
+//! chain is broken, so it keeps dropping video until an IDR arrives, even if
+//! congestion clears in the meantime. Parameter sets (SPS/PPS) are always
+//! forwarded because the upcoming IDR depends on them.
+//!
+//! A picture may span several payloads. Continuation payloads carry no
+//! NAL start code (`FrameKind::Unknown`), so they share the verdict of the
+//! payload that started their frame, and are dropped while waiting for an
+//! IDR; the decoder never sees a fragment of a dropped frame.
+//!
+//! ## Bitrate Adaptation
+//!
+//! Dropping frames only treats the symptom. [`BitrateController`] measures
+//! delivered throughput and loss per interval and derives `BitrateHint`
+//! messages so the video source can encode at a rate the link sustains:
+//!
+//! - **Loss above threshold**: back off multiplicatively below measured throughput
+//! - **Clean interval near target**: probe upward additively
+//! - **Low target**: also cap the frame rate to keep per-frame quality
+
+use crate::buffer::{BufferError, ZeroCopyBuffer};
+use crate::flow::MAX_CHANNELS;
+use crate::h264::{self, FrameKind};
+use crate::protocol::ControlMessage;
+
+/// Current congestion level derived from buffer occupancy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CongestionLevel {
+    /// Buffer comfortably drained
+    Normal,
+    /// Buffer filling up; shed droppable frames
+    Moderate,
+    /// Buffer nearly full; resynchronize on the next IDR
+    Severe,
+}
+
+/// Thresholds for the drop policy, as buffer fill percentages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DropConfig {
+    /// Fill level at which non-reference frames are dropped
+    pub moderate_percent: u8,
+    /// Fill level at which all video is dropped until the next IDR
+    pub severe_percent: u8,
+}
+
+impl DropConfig {
+    /// Map a buffer's occupancy to a congestion level
+    pub fn level(&self, used: usize, capacity: usize) -> CongestionLevel {
+        let percent = used * 100 / capacity.max(1);
+        if percent >= self.severe_percent as usize {
+            CongestionLevel::Severe
+        } else if percent >= self.moderate_percent as usize {
+            CongestionLevel::Moderate
+        } else {
+            CongestionLevel::Normal
+        }
+    }
+}
+
+impl Default for DropConfig {
+    fn default() -> Self {
+        Self {
+            moderate_percent: 50,
+            severe_percent: 85,
+        }
+    }
+}
+
+/// Channel roles announced by `ControlMessage::StartStream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelMap {
+    /// Video channel ID (the only channel frames may be dropped from)
+    pub video: u8,
+    /// Audio channel ID
+    pub audio: u8,
+    /// Input channel ID
+    pub input: u8,
+}
+
+impl ChannelMap {
+    /// Extract the channel map from a `StartStream` message
+    pub fn from_control(msg: &ControlMessage) -> Option<Self> {
+        match *msg {
+            ControlMessage::StartStream {
+                video_channel,
+                audio_channel,
+                input_channel,
+            } => Some(Self {
+                video: video_channel,
+                audio: audio_channel,
+                input: input_channel,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of offering a frame to the policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Verdict {
+    /// Forward the frame
+    Forward,
+    /// Drop the frame
+    Drop,
+    /// Drop the frame and ask the video source for a keyframe
+    DropAndRequestKeyframe,
+}
+
+impl Verdict {
+    /// Check if the frame should be forwarded
+    pub fn is_forward(&self) -> bool {
+        matches!(self, Self::Forward)
+    }
+
+    /// Control message to send back to the video source, if any
+    pub fn control_message(&self) -> Option<ControlMessage> {
+        match self {
+            Self::DropAndRequestKeyframe => Some(ControlMessage::RequestKeyframe),
+            _ => None,
+        }
+    }
+}
+
+/// Per-channel drop accounting
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DropStats {
+    /// Frames dropped per channel
+    pub frames: [u32; MAX_CHANNELS],
+    /// Bytes dropped per channel
+    pub bytes: [u64; MAX_CHANNELS],
+    /// Keyframe requests issued
+    pub keyframe_requests: u32,
+}
+
+impl DropStats {
+    /// Frames dropped across all channels
+    pub fn total_frames(&self) -> u32 {
+        self.frames.iter().fold(0u32, |acc, n| acc.saturating_add(*n))
+    }
+
+    fn record(&mut self, channel: u8, len: usize) {
+        let idx = channel as usize;
+        if idx < MAX_CHANNELS {
+            self.frames[idx] = self.frames[idx].saturating_add(1);
+            self.bytes[idx] = self.bytes[idx].saturating_add(len as u64);
+        }
+    }
+}
+
+/// Video queue policy that drops whole frames while preserving decodability
+#[derive(Debug, Clone)]
+pub struct VideoDropPolicy {
+    config: DropConfig,
+    channels: Option<ChannelMap>,
+    /// Dropping all video until the next IDR
+    awaiting_idr: bool,
+    /// The current frame's first payload was dropped, so its
+    /// continuation payloads are too
+    dropping_frame: bool,
+    stats: DropStats,
+}
+
+impl VideoDropPolicy {
+    /// Create a policy; nothing is dropped until channels are known
+    pub const fn new(config: DropConfig) -> Self {
+        Self {
+            config,
+            channels: None,
+            awaiting_idr: false,
+            dropping_frame: false,
+            stats: DropStats {
+                frames: [0; MAX_CHANNELS],
+                bytes: [0; MAX_CHANNELS],
+                keyframe_requests: 0,
+            },
+        }
+    }
+
+    /// Set the channel roles
+    pub fn set_channels(&mut self, channels: ChannelMap) {
+        self.channels = Some(channels);
+        self.awaiting_idr = false;
+        self.dropping_frame = false;
+    }
+
+    /// Learn channel roles from a control message, if it is `StartStream`
+    pub fn observe_control(&mut self, msg: &ControlMessage) {
+        if let Some(map) = ChannelMap::from_control(msg) {
+            self.set_channels(map);
+        } else if matches!(msg, ControlMessage::StopStream) {
+            self.channels = None;
+            self.awaiting_idr = false;
+            self.dropping_frame = false;
+        }
+    }
+
+    /// Check if the policy is discarding video until the next IDR
+    pub fn is_awaiting_idr(&self) -> bool {
+        self.awaiting_idr
+    }
+
+    /// Get drop statistics
+    pub fn stats(&self) -> &DropStats {
+        &self.stats
+    }
+
+    /// Decide what to do with a payload on `channel` at the given congestion
+    ///
+    /// `payload` is the video data (Annex-B) used for classification; `len`
+    /// is the number of bytes that would be queued and is used for stats.
+    pub fn decide(
+        &mut self,
+        channel: u8,
+        payload: &[u8],
+        len: usize,
+        level: CongestionLevel,
+    ) -> Verdict {
+        let is_video = self.channels.is_some_and(|map| map.video == channel);
+        if !is_video {
+            return Verdict::Forward;
+        }
+
+        let kind = h264::classify(payload);
+        if kind == FrameKind::Unknown {
+            // Continuation of the current frame: same fate as its start
+            if !(self.dropping_frame || self.awaiting_idr) {
+                return Verdict::Forward;
+            }
+            if let Some(bytes) = self.stats.bytes.get_mut(channel as usize) {
+                *bytes = bytes.saturating_add(len as u64);
+            }
+            return Verdict::Drop;
+        }
+
+        let verdict = match kind {
+            FrameKind::Idr => {
+                self.awaiting_idr = false;
+                Verdict::Forward
+            }
+            FrameKind::Config => Verdict::Forward,
+            _ if self.awaiting_idr => Verdict::Drop,
+            _ if level == CongestionLevel::Severe => {
+                self.awaiting_idr = true;
+                self.stats.keyframe_requests = self.stats.keyframe_requests.saturating_add(1);
+                Verdict::DropAndRequestKeyframe
+            }
+            FrameKind::NonReference if level >= CongestionLevel::Moderate => Verdict::Drop,
+            _ => Verdict::Forward,
+        };
+
+        self.dropping_frame = !verdict.is_forward();
+        if self.dropping_frame {
+            self.stats.record(channel, len);
+        }
+        verdict
+    }
+
+    /// Offer a frame for queueing into `buffer`
+    ///
+    /// Congestion is measured from the buffer's occupancy. Forwarded frames are
+    /// written whole. If a video frame doesn't fit it is dropped as under
+    /// severe congestion; any other channel gets `BufferError::Overflow` so the
+    /// caller can apply backpressure instead of losing audio or input.
+    pub fn offer(
+        &mut self,
+        buffer: &mut ZeroCopyBuffer,
+        channel: u8,
+        frame: &[u8],
+        payload: &[u8],
+    ) -> Result<Verdict, BufferError> {
+        let mut level = self.config.level(buffer.readable_len(), buffer.capacity());
+        let is_video = self.channels.is_some_and(|map| map.video == channel);
+        if is_video && frame.len() > buffer.writable_len() {
+            level = CongestionLevel::Severe;
+        }
+
+        let verdict = self.decide(channel, payload, frame.len(), level);
+        if !verdict.is_forward() {
+            return Ok(verdict);
+        }
+
+        if is_video && frame.len() > buffer.writable_len() {
+            // Not even an IDR fits: drop it and resync on the next one
+            self.awaiting_idr = true;
+            self.dropping_frame = true;
+            self.stats.keyframe_requests = self.stats.keyframe_requests.saturating_add(1);
+            self.stats.record(channel, frame.len());
+            return Ok(Verdict::DropAndRequestKeyframe);
+        }
+
+        buffer.write(frame)?;
+        Ok(verdict)
+    }
+}
+
+impl Default for VideoDropPolicy {
+    fn default() -> Self {
+        Self::new(DropConfig::default())
+    }
+}
+
+/// Configuration for [`BitrateController`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitrateConfig {
+    /// Lowest target the controller will suggest (kbit/s)
+    pub min_kbps: u32,
+    /// Highest target the controller will suggest (kbit/s)
+    pub max_kbps: u32,
+    /// Additive increase per clean interval (kbit/s)
+    pub step_kbps: u32,
+    /// Loss rate, in per-mille of frames, that triggers a back-off
+    pub loss_threshold_permille: u16,
+    /// Frame rate suggested at normal bitrates
+    pub full_fps: u8,
+    /// Frame rate suggested below `reduced_fps_below_kbps`
+    pub reduced_fps: u8,
+    /// Target below which the frame rate is reduced (kbit/s)
+    pub reduced_fps_below_kbps: u32,
+}
+
+impl Default for BitrateConfig {
+    fn default() -> Self {
+        Self {
+            min_kbps: 500,
+            max_kbps: 8000,
+            step_kbps: 250,
+            loss_threshold_permille: 20,
+            full_fps: 60,
+            reduced_fps: 30,
+            reduced_fps_below_kbps: 2000,
+        }
+    }
+}
+
+/// Measurements for one adaptation interval
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkSample {
+    /// Video bytes delivered to the peer during the interval
+    pub bytes_delivered: u64,
+    /// Interval length in milliseconds
+    pub elapsed_ms: u32,
+    /// Video frames offered during the interval
+    pub frames_offered: u32,
+    /// Video frames dropped during the interval
+    pub frames_dropped: u32,
+}
+
+impl LinkSample {
+    /// Delivered throughput in kbit/s
+    pub fn throughput_kbps(&self) -> u32 {
+        if self.elapsed_ms == 0 {
+            return 0;
+        }
+        (self.bytes_delivered * 8 / self.elapsed_ms as u64).min(u32::MAX as u64) as u32
+    }
+
+    /// Frame loss in per-mille
+    pub fn loss_permille(&self) -> u16 {
+        if self.frames_offered == 0 {
+            return 0;
+        }
+        (self.frames_dropped as u64 * 1000 / self.frames_offered as u64).min(1000) as u16
+    }
+}
+
+/// Derives `BitrateHint` messages from measured throughput and loss
+#[derive(Debug, Clone)]
+pub struct BitrateController {
+    config: BitrateConfig,
+    target_kbps: u32,
+    max_fps: u8,
+}
+
+impl BitrateController {
+    /// Create a controller starting at the configured maximum
+    pub fn new(config: BitrateConfig) -> Self {
+        Self {
+            target_kbps: config.max_kbps,
+            max_fps: config.full_fps,
+            config,
+        }
+    }
+
+    /// Current target bitrate (kbit/s)
+    pub fn target_kbps(&self) -> u32 {
+        self.target_kbps
+    }
+
+    /// Current frame rate cap
+    pub fn max_fps(&self) -> u8 {
+        self.max_fps
+    }
+
+    /// Feed one interval of measurements
+    ///
+    /// Returns a `BitrateHint` when the target or frame rate changed enough to
+    /// be worth telling the video source about.
+    pub fn on_sample(&mut self, sample: &LinkSample) -> Option<ControlMessage> {
+        let throughput = sample.throughput_kbps();
+        let lossy = sample.loss_permille() > self.config.loss_threshold_permille;
+
+        let mut target = if lossy {
+            // Back off to 85% of what actually got through
+            self.target_kbps.min(throughput) / 20 * 17
+        } else if sample.frames_dropped == 0 && throughput >= self.target_kbps / 10 * 9 {
+            self.target_kbps.saturating_add(self.config.step_kbps)
+        } else {
+            self.target_kbps
+        };
+        target = target.clamp(self.config.min_kbps, self.config.max_kbps);
+
+        let max_fps = if target < self.config.reduced_fps_below_kbps {
+            self.config.reduced_fps
+        } else {
+            self.config.full_fps
+        };
+
+        // Ignore changes under 5% to avoid encoder churn
+        let delta = self.target_kbps.abs_diff(target);
+        if max_fps == self.max_fps && delta * 20 < self.target_kbps {
+            return None;
+        }
+
+        self.target_kbps = target;
+        self.max_fps = max_fps;
+        Some(ControlMessage::BitrateHint {
+            target_kbps: target,
+            max_fps,
+        })
+    }
+}
+
+impl Default for BitrateController {
+    fn default() -> Self {
+        Self::new(BitrateConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BUFFER_SIZE;
+
+    const IDR: [u8; 6] = [0, 0, 0, 1, 0x65, 0x88];
+    const P: [u8; 6] = [0, 0, 0, 1, 0x41, 0x9A];
+    const B: [u8; 6] = [0, 0, 0, 1, 0x01, 0x9E];
+
+    fn policy() -> VideoDropPolicy {
+        let mut policy = VideoDropPolicy::default();
+        policy.observe_control(&ControlMessage::StartStream {
+            video_channel: 1,
+            audio_channel: 2,
+            input_channel: 3,
+        });
+        policy
+    }
+
+    #[test]
+    fn test_levels() {
+        let config = DropConfig::default();
+        assert_eq!(config.level(0, 100), CongestionLevel::Normal);
+        assert_eq!(config.level(50, 100), CongestionLevel::Moderate);
+        assert_eq!(config.level(90, 100), CongestionLevel::Severe);
+    }
+
+    #[test]
+    fn test_moderate_drops_only_non_reference() {
+        let mut policy = policy();
+        let level = CongestionLevel::Moderate;
+        assert_eq!(policy.decide(1, &B, 6, level), Verdict::Drop);
+        assert_eq!(policy.decide(1, &P, 6, level), Verdict::Forward);
+        assert_eq!(policy.decide(1, &IDR, 6, level), Verdict::Forward);
+        assert_eq!(policy.stats().frames[1], 1);
+        assert_eq!(policy.stats().bytes[1], 6);
+    }
+
+    #[test]
+    fn test_severe_drops_until_idr() {
+        let mut policy = policy();
+        assert_eq!(
+            policy.decide(1, &P, 6, CongestionLevel::Severe),
+            Verdict::DropAndRequestKeyframe
+        );
+        assert!(policy.is_awaiting_idr());
+
+        // Congestion cleared, but the reference chain is broken
+        assert_eq!(policy.decide(1, &P, 6, CongestionLevel::Normal), Verdict::Drop);
+        assert_eq!(policy.decide(1, &IDR, 6, CongestionLevel::Normal), Verdict::Forward);
+        assert!(!policy.is_awaiting_idr());
+        assert_eq!(policy.decide(1, &P, 6, CongestionLevel::Normal), Verdict::Forward);
+
+        assert_eq!(policy.stats().frames[1], 2);
+        assert_eq!(policy.stats().keyframe_requests, 1);
+    }
+
+    #[test]
+    fn test_continuation_payloads_follow_their_frame() {
+        // Slice data without a start code: the rest of a split frame
+        const REST: [u8; 4] = [0x9A, 0x12, 0x34, 0x56];
+        let mut policy = policy();
+        let moderate = CongestionLevel::Moderate;
+
+        assert_eq!(policy.decide(1, &B, 6, moderate), Verdict::Drop);
+        assert_eq!(policy.decide(1, &REST, 4, moderate), Verdict::Drop);
+        assert_eq!(policy.decide(1, &REST, 4, CongestionLevel::Normal), Verdict::Drop);
+        assert_eq!(policy.decide(1, &P, 6, moderate), Verdict::Forward);
+        assert_eq!(policy.decide(1, &REST, 4, moderate), Verdict::Forward);
+        // One dropped frame, all of its bytes
+        assert_eq!((policy.stats().frames[1], policy.stats().bytes[1]), (1, 14));
+
+        // Everything up to the next IDR goes, continuations included
+        policy.decide(1, &P, 6, CongestionLevel::Severe);
+        assert_eq!(policy.decide(1, &REST, 4, CongestionLevel::Normal), Verdict::Drop);
+        assert_eq!(policy.decide(1, &IDR, 6, CongestionLevel::Normal), Verdict::Forward);
+        assert_eq!(policy.decide(1, &REST, 4, CongestionLevel::Normal), Verdict::Forward);
+    }
+
+    #[test]
+    fn test_audio_and_input_never_dropped() {
+        let mut policy = policy();
+        for channel in [2, 3] {
+            assert_eq!(policy.decide(channel, &B, 6, CongestionLevel::Severe), Verdict::Forward);
+        }
+        assert_eq!(policy.stats().total_frames(), 0);
+    }
+
+    #[test]
+    fn test_offer_full_buffer() {
+        let mut policy = policy();
+        let mut buffer = ZeroCopyBuffer::new();
+        buffer.write(&[0u8; BUFFER_SIZE - 4]).unwrap();
+
+        // Video that doesn't fit is dropped and triggers resync
+        let verdict = policy.offer(&mut buffer, 1, &P, &P).unwrap();
+        assert_eq!(verdict, Verdict::DropAndRequestKeyframe);
+
+        // An IDR that doesn't fit is dropped too
+        let verdict = policy.offer(&mut buffer, 1, &IDR, &IDR).unwrap();
+        assert_eq!(verdict, Verdict::DropAndRequestKeyframe);
+        assert_eq!(policy.stats().frames[1], 2);
+
+        // Audio is never dropped: caller sees the overflow
+        assert_eq!(policy.offer(&mut buffer, 2, &B, &B), Err(BufferError::Overflow));
+    }
+
+    #[test]
+    fn test_verdict_requests_keyframe() {
+        assert_eq!(
+            Verdict::DropAndRequestKeyframe.control_message(),
+            Some(ControlMessage::RequestKeyframe)
+        );
+        assert_eq!(Verdict::Drop.control_message(), None);
+    }
+
+    #[test]
+    fn test_bitrate_backs_off_on_loss() {
+        let mut controller = BitrateController::default();
+        let sample = LinkSample {
+            bytes_delivered: 500_000, // 4000 kbit/s over one second
+            elapsed_ms: 1000,
+            frames_offered: 60,
+            frames_dropped: 6,
+        };
+        assert_eq!(sample.throughput_kbps(), 4000);
+        assert_eq!(sample.loss_permille(), 100);
+
+        let hint = controller.on_sample(&sample).unwrap();
+        assert_eq!(hint, ControlMessage::BitrateHint { target_kbps: 3400, max_fps: 60 });
+
+        // Severe degradation also caps the frame rate
+        let sample = LinkSample { bytes_delivered: 125_000, ..sample };
+        let hint = controller.on_sample(&sample).unwrap();
+        assert_eq!(hint, ControlMessage::BitrateHint { target_kbps: 850, max_fps: 30 });
+    }
+
+    #[test]
+    fn test_bitrate_probes_up_when_clean() {
+        let mut controller = BitrateController::new(BitrateConfig {
+            step_kbps: 1000,
+            ..BitrateConfig::default()
+        });
+        let lossy = LinkSample {
+            bytes_delivered: 250_000,
+            elapsed_ms: 1000,
+            frames_offered: 60,
+            frames_dropped: 30,
+        };
+        controller.on_sample(&lossy).unwrap();
+        assert_eq!(controller.target_kbps(), 1700);
+
+        let clean = LinkSample { frames_dropped: 0, ..lossy };
+        let hint = controller.on_sample(&clean).unwrap();
+        assert_eq!(hint, ControlMessage::BitrateHint { target_kbps: 2700, max_fps: 60 });
+
+        // Small changes are suppressed
+        assert!(BitrateController::default().on_sample(&LinkSample::default()).is_none());
+    }
+}
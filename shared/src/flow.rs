@@ -0,0 +1,399 @@
+//! # Credit-Based Flow Control
+//!
+//! This module implements receiver-advertised, per-channel credit windows so a
+//! fast sender can never overrun the dongle's `ZeroCopyBuffer`.
+//!
+//! ## How It Works
+//!
+//! ```text
+//!      Sender (SendWindow)                      Receiver (CreditGrantor)
+//!  ┌──────────────────────────┐              ┌──────────────────────────┐
+//!  │ credits[ch] = 0          │◄─────────────│ WindowUpdate(ch, 16KiB)  │
+//!  │                          │              │                          │
+//!  │ consume(ch, len) ────────┼── Data ─────►│ on_received(ch, len)     │
+//!  │ credits[ch] -= len       │              │                          │
+//!  │                          │              │ on_drained(ch, len)      │
+//!  │ credits[ch] += credits   │◄─────────────│ WindowUpdate(ch, drained)│
+//!  └──────────────────────────┘              └──────────────────────────┘
+//! ```
+//!
+//! - Credits are counted in **payload bytes of `Data` messages**. Control,
+//!   ping and ack traffic is never flow-controlled, so a `WindowUpdate` can
+//!   always get through.
+//! - Window updates are **incremental**: each one adds to the sender's window.
+//! - When a channel's credits reach zero the sender stops sending on that
+//!   channel and records a starvation event instead of overflowing the buffer.
+//!
+//! ## Negotiation
+//!
+//! Flow control is only used when the receiver sets [`FEATURE_FLOW_CONTROL`]
+//! in its `HandshakeResponse`. Until then, and with peers that never grant
+//! credits (such as older firmware), [`SendWindow::unlimited`] lets every
+//! send through; [`SendWindow::negotiate`] switches modes after the
+//! handshake.
+
+use crate::buffer::BUFFER_SIZE;
+use crate::protocol::{ControlMessage, FRAME_OVERHEAD, MAX_PAYLOAD_SIZE};
+
+/// Number of channels tracked by the flow controller
+///
+/// Channel IDs at or above this value are rejected with
+/// [`FlowError::UnknownChannel`].
+pub const MAX_CHANNELS: usize = 8;
+
+/// Handshake feature bit: the receiver grants credits with `WindowUpdate`
+pub const FEATURE_FLOW_CONTROL: u32 = 1 << 0;
+
+/// Errors reported by the flow controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlowError {
+    /// Channel ID is outside the tracked range
+    UnknownChannel,
+    /// Not enough credits to send on this channel
+    Starved,
+    /// Peer sent more data than it had been granted
+    WindowExceeded,
+}
+
+/// Flow control configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowConfig {
+    /// Credits granted to each channel when the session starts
+    pub initial_window: u32,
+    /// Drained bytes to accumulate before a `WindowUpdate` is emitted
+    pub update_threshold: u32,
+}
+
+impl FlowConfig {
+    /// Smallest usable window: one maximum-size frame
+    ///
+    /// Sends are never split, so a smaller window would refuse the largest
+    /// payloads forever.
+    pub const MIN_WINDOW: u32 = (MAX_PAYLOAD_SIZE + FRAME_OVERHEAD) as u32;
+
+    /// Derive a configuration that keeps `channels` windows within `capacity`
+    ///
+    /// One eighth of each channel's share is held back as headroom for frame
+    /// overhead and control traffic, which is not flow-controlled. Windows
+    /// never go below [`MIN_WINDOW`](Self::MIN_WINDOW), so with a small
+    /// capacity or many channels they can add up to more than `capacity`.
+    pub const fn for_buffer(capacity: usize, channels: usize) -> Self {
+        let share = (capacity / channels) as u32;
+        let mut initial_window = share - share / 8;
+        if initial_window < Self::MIN_WINDOW {
+            initial_window = Self::MIN_WINDOW;
+        }
+        Self {
+            initial_window,
+            update_threshold: initial_window / 4,
+        }
+    }
+}
+
+impl Default for FlowConfig {
+    /// Windows for every tracked channel, each at least one maximum-size frame
+    fn default() -> Self {
+        Self::for_buffer(BUFFER_SIZE, MAX_CHANNELS)
+    }
+}
+
+/// Sender-side view of the credits advertised by the peer
+#[derive(Debug, Clone)]
+pub struct SendWindow {
+    /// Remaining credits per channel
+    credits: [u32; MAX_CHANNELS],
+    /// Number of sends refused for lack of credits, per channel
+    starvations: [u32; MAX_CHANNELS],
+    /// Whether sends are limited by credits at all
+    limited: bool,
+}
+
+impl SendWindow {
+    /// Create a window with no credits; nothing may be sent until the peer
+    /// advertises a window
+    pub const fn new() -> Self {
+        Self {
+            credits: [0; MAX_CHANNELS],
+            starvations: [0; MAX_CHANNELS],
+            limited: true,
+        }
+    }
+
+    /// Create a window that allows every send, for peers without flow control
+    pub const fn unlimited() -> Self {
+        Self {
+            limited: false,
+            ..Self::new()
+        }
+    }
+
+    /// Pick the mode from the peer's handshake feature bits
+    ///
+    /// Starts from no credits if the peer set [`FEATURE_FLOW_CONTROL`], and
+    /// unlimited otherwise. Counters are kept.
+    pub fn negotiate(&mut self, peer_features: u32) {
+        self.limited = peer_features & FEATURE_FLOW_CONTROL != 0;
+        self.credits = [0; MAX_CHANNELS];
+    }
+
+    /// Check whether sends are limited by credits
+    pub fn is_limited(&self) -> bool {
+        self.limited
+    }
+
+    /// Remaining credits on a channel (0 for unknown channels)
+    pub fn credits(&self, channel: u8) -> u32 {
+        self.credits.get(channel as usize).copied().unwrap_or(0)
+    }
+
+    /// Check whether `len` payload bytes may be sent on `channel`
+    pub fn can_send(&self, channel: u8, len: usize) -> bool {
+        !self.limited || len as u64 <= self.credits(channel) as u64
+    }
+
+    /// Take `len` credits before sending on `channel`
+    ///
+    /// On [`FlowError::Starved`] nothing is consumed and a starvation event is
+    /// recorded; the caller should hold the data until a window update arrives.
+    pub fn consume(&mut self, channel: u8, len: usize) -> Result<(), FlowError> {
+        let idx = channel_index(channel)?;
+        if !self.can_send(channel, len) {
+            self.starvations[idx] = self.starvations[idx].saturating_add(1);
+            return Err(FlowError::Starved);
+        }
+        if self.limited {
+            self.credits[idx] -= len as u32;
+        }
+        Ok(())
+    }
+
+    /// Add credits to a channel
+    pub fn grant(&mut self, channel: u8, credits: u32) -> Result<(), FlowError> {
+        let idx = channel_index(channel)?;
+        self.credits[idx] = self.credits[idx].saturating_add(credits);
+        Ok(())
+    }
+
+    /// Apply a received control message
+    ///
+    /// Returns `true` if the message was a `WindowUpdate` for a known channel.
+    pub fn apply(&mut self, msg: &ControlMessage) -> bool {
+        match *msg {
+            ControlMessage::WindowUpdate { channel, credits } => {
+                self.grant(channel, credits).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Starvation events recorded on a channel
+    pub fn starvations(&self, channel: u8) -> u32 {
+        self.starvations.get(channel as usize).copied().unwrap_or(0)
+    }
+
+    /// Starvation events recorded across all channels
+    pub fn total_starvations(&self) -> u32 {
+        self.starvations
+            .iter()
+            .fold(0u32, |acc, n| acc.saturating_add(*n))
+    }
+
+    /// Drop all credits and counters and go back to unlimited until the
+    /// next handshake (e.g. on reconnect)
+    pub fn reset(&mut self) {
+        *self = Self::unlimited();
+    }
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+/// Receiver-side credit accounting
+///
+/// Tracks how much of each channel's window is in flight and hands credits
+/// back to the sender as the local buffer drains.
+#[derive(Debug, Clone)]
+pub struct CreditGrantor {
+    config: FlowConfig,
+    /// Credits granted to the peer but not yet used
+    outstanding: [u32; MAX_CHANNELS],
+    /// Bytes drained locally but not yet re-advertised
+    pending: [u32; MAX_CHANNELS],
+}
+
+impl CreditGrantor {
+    /// Create a grantor with the given configuration
+    pub const fn new(config: FlowConfig) -> Self {
+        Self {
+            config,
+            outstanding: [0; MAX_CHANNELS],
+            pending: [0; MAX_CHANNELS],
+        }
+    }
+
+    /// Get the active configuration
+    pub fn config(&self) -> &FlowConfig {
+        &self.config
+    }
+
+    /// Build the initial window advertisement for a channel
+    pub fn open(&mut self, channel: u8) -> Result<ControlMessage, FlowError> {
+        let idx = channel_index(channel)?;
+        self.outstanding[idx] = self.config.initial_window;
+        self.pending[idx] = 0;
+        Ok(ControlMessage::WindowUpdate {
+            channel,
+            credits: self.config.initial_window,
+        })
+    }
+
+    /// Account for `len` payload bytes received on `channel`
+    ///
+    /// Returns [`FlowError::WindowExceeded`] if the peer ignored its window.
+    pub fn on_received(&mut self, channel: u8, len: usize) -> Result<(), FlowError> {
+        let idx = channel_index(channel)?;
+        if len as u64 > self.outstanding[idx] as u64 {
+            return Err(FlowError::WindowExceeded);
+        }
+        self.outstanding[idx] -= len as u32;
+        Ok(())
+    }
+
+    /// Account for `len` bytes drained from the local buffer on `channel`
+    ///
+    /// Returns a `WindowUpdate` to send once enough space has been freed.
+    pub fn on_drained(&mut self, channel: u8, len: usize) -> Option<ControlMessage> {
+        let idx = channel_index(channel).ok()?;
+        self.pending[idx] = self.pending[idx].saturating_add(len as u32);
+        if self.pending[idx] < self.config.update_threshold {
+            return None;
+        }
+
+        let credits = self.pending[idx];
+        self.pending[idx] = 0;
+        self.outstanding[idx] = self.outstanding[idx].saturating_add(credits);
+        Some(ControlMessage::WindowUpdate { channel, credits })
+    }
+
+    /// Credits currently held by the peer on a channel
+    pub fn outstanding(&self, channel: u8) -> u32 {
+        self.outstanding.get(channel as usize).copied().unwrap_or(0)
+    }
+}
+
+impl Default for CreditGrantor {
+    fn default() -> Self {
+        Self::new(FlowConfig::default())
+    }
+}
+
+#[inline]
+fn channel_index(channel: u8) -> Result<usize, FlowError> {
+    let idx = channel as usize;
+    if idx < MAX_CHANNELS {
+        Ok(idx)
+    } else {
+        Err(FlowError::UnknownChannel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_window_sizes() {
+        let config = FlowConfig::default();
+        assert!(config.initial_window >= FlowConfig::MIN_WINDOW);
+        assert!(config.update_threshold > 0);
+
+        // A buffer big enough for every window is shared out without the floor
+        let config = FlowConfig::for_buffer(256 * 1024, MAX_CHANNELS);
+        assert!(config.initial_window as usize * MAX_CHANNELS < 256 * 1024);
+        assert!(config.initial_window > FlowConfig::MIN_WINDOW);
+    }
+
+    #[test]
+    fn test_max_payload_fits_default_window() {
+        let mut grantor = CreditGrantor::default();
+        let mut window = SendWindow::unlimited();
+        window.negotiate(FEATURE_FLOW_CONTROL);
+
+        assert!(window.apply(&grantor.open(1).unwrap()));
+        window.consume(1, MAX_PAYLOAD_SIZE).unwrap();
+        grantor.on_received(1, MAX_PAYLOAD_SIZE).unwrap();
+        assert_eq!(window.total_starvations(), 0);
+    }
+
+    #[test]
+    fn test_sender_starts_starved() {
+        let mut window = SendWindow::new();
+        assert_eq!(window.consume(1, 10), Err(FlowError::Starved));
+        assert_eq!(window.starvations(1), 1);
+        assert_eq!(window.total_starvations(), 1);
+    }
+
+    #[test]
+    fn test_peer_without_flow_control_never_starves() {
+        let mut window = SendWindow::unlimited();
+        // The peer never sends a WindowUpdate
+        window.negotiate(0);
+        assert!(!window.is_limited());
+        window.consume(1, 64 * 1024).unwrap();
+        window.consume(1, 64 * 1024).unwrap();
+        assert_eq!(window.total_starvations(), 0);
+        assert_eq!(window.consume(0xFF, 1), Err(FlowError::UnknownChannel));
+
+        window.negotiate(FEATURE_FLOW_CONTROL);
+        assert_eq!(window.consume(1, 1), Err(FlowError::Starved));
+        window.reset();
+        assert!(!window.is_limited());
+    }
+
+    #[test]
+    fn test_credits_consumed_and_refilled() {
+        let mut grantor = CreditGrantor::new(FlowConfig {
+            initial_window: 100,
+            update_threshold: 50,
+        });
+        let mut window = SendWindow::new();
+
+        assert!(window.apply(&grantor.open(2).unwrap()));
+        assert_eq!(window.credits(2), 100);
+
+        window.consume(2, 100).unwrap();
+        grantor.on_received(2, 100).unwrap();
+        assert_eq!(window.consume(2, 1), Err(FlowError::Starved));
+
+        // Below threshold: no update yet
+        assert!(grantor.on_drained(2, 40).is_none());
+        let update = grantor.on_drained(2, 20).unwrap();
+        assert_eq!(update, ControlMessage::WindowUpdate { channel: 2, credits: 60 });
+
+        assert!(window.apply(&update));
+        assert!(window.can_send(2, 60));
+        assert!(!window.can_send(2, 61));
+    }
+
+    #[test]
+    fn test_window_exceeded() {
+        let mut grantor = CreditGrantor::new(FlowConfig {
+            initial_window: 10,
+            update_threshold: 5,
+        });
+        grantor.open(0).unwrap();
+        assert_eq!(grantor.on_received(0, 11), Err(FlowError::WindowExceeded));
+        assert_eq!(grantor.outstanding(0), 10);
+    }
+
+    #[test]
+    fn test_unknown_channel() {
+        let mut window = SendWindow::new();
+        assert_eq!(window.grant(MAX_CHANNELS as u8, 10), Err(FlowError::UnknownChannel));
+        assert_eq!(window.consume(0xFF, 1), Err(FlowError::UnknownChannel));
+    }
+}
@@ -0,0 +1,388 @@
+//! # H.264 Annex-B NAL Unit Scanner
+//!
+//! This module finds NAL unit boundaries in video-channel payloads and
+//! classifies them, without allocating or copying any video data.
+//!
+//! ## Annex-B Byte Stream
+//!
+//! ```text
+//! ┌─────────────┬────────┬─────────────┬─────────────┬────────┬─────────
+//! │ 00 00 00 01 │ NAL hdr│  NAL body   │  00 00 01   │ NAL hdr│  ...
+//! │ start code  │ 1 byte │             │ start code  │        │
+//! └─────────────┴────────┴─────────────┴─────────────┴────────┴─────────
+//!
+//! NAL header:  ┌───┬───────────┬───────────────┐
+//!              │ F │ ref_idc   │   nal_type    │
+//!              │ 1 │  2 bits   │    5 bits     │
+//!              └───┴───────────┴───────────────┘
+//! ```
+//!
+//! Both the 4-byte start code ([`crate::AA_MAGIC`]) and the 3-byte form are
+//! accepted. The scanner is used by the bridge to make drop decisions under
+//! congestion and by host tooling to report frame rates and GOP structure.
+
+use crate::AA_MAGIC;
+
+/// 3-byte Annex-B start code
+pub const SHORT_START_CODE: [u8; 3] = [0x00, 0x00, 0x01];
+
+/// H.264 NAL unit types (ITU-T H.264 Table 7-1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NalUnitType {
+    /// Coded slice of a non-IDR picture
+    NonIdrSlice,
+    /// Coded slice data partition A/B/C
+    DataPartition,
+    /// Coded slice of an IDR picture
+    Idr,
+    /// Supplemental enhancement information
+    Sei,
+    /// Sequence parameter set
+    Sps,
+    /// Picture parameter set
+    Pps,
+    /// Access unit delimiter
+    AccessUnitDelimiter,
+    /// End of sequence / end of stream / filler data
+    EndOfStream,
+    /// Any other (reserved or unspecified) type
+    Other(u8),
+}
+
+impl NalUnitType {
+    /// Decode the 5-bit `nal_unit_type` field
+    pub fn from_bits(value: u8) -> Self {
+        match value & 0x1F {
+            1 => Self::NonIdrSlice,
+            2..=4 => Self::DataPartition,
+            5 => Self::Idr,
+            6 => Self::Sei,
+            7 => Self::Sps,
+            8 => Self::Pps,
+            9 => Self::AccessUnitDelimiter,
+            10..=12 => Self::EndOfStream,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Check if this NAL carries coded picture data
+    pub fn is_slice(&self) -> bool {
+        matches!(self, Self::NonIdrSlice | Self::DataPartition | Self::Idr)
+    }
+}
+
+/// A single NAL unit borrowed from a payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NalUnit<'a> {
+    /// Decoded NAL unit type
+    pub nal_type: NalUnitType,
+    /// `nal_ref_idc` (0 means no other picture references this one)
+    pub ref_idc: u8,
+    /// NAL bytes including the header byte, excluding the start code
+    pub data: &'a [u8],
+}
+
+impl<'a> NalUnit<'a> {
+    /// Parse a NAL unit from its bytes (header byte first)
+    ///
+    /// Returns `None` for an empty slice or if the forbidden bit is set.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        let header = *data.first()?;
+        if header & 0x80 != 0 {
+            return None;
+        }
+        Some(Self {
+            nal_type: NalUnitType::from_bits(header),
+            ref_idc: (header >> 5) & 0x03,
+            data,
+        })
+    }
+
+    /// Check if other pictures may reference this NAL
+    pub fn is_reference(&self) -> bool {
+        self.ref_idc != 0
+    }
+}
+
+/// Iterator over the NAL units of an Annex-B payload
+///
+/// Bytes before the first start code are skipped. Malformed NAL units
+/// (forbidden bit set) are skipped rather than ending the iteration.
+#[derive(Debug, Clone)]
+pub struct NalIter<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NalIter<'a> {
+    /// Start scanning a payload
+    pub fn new(data: &'a [u8]) -> Self {
+        let pos = find_start_code(data, 0).map_or(data.len(), |(_, next)| next);
+        Self { data, pos }
+    }
+}
+
+impl<'a> Iterator for NalIter<'a> {
+    type Item = NalUnit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.data.len() {
+            let start = self.pos;
+            let (end, next) = match find_start_code(self.data, start) {
+                Some((code_start, next)) => (code_start, next),
+                None => (self.data.len(), self.data.len()),
+            };
+            self.pos = next;
+
+            // Trailing zeros belong to the next 4-byte start code
+            let mut nal = &self.data[start..end];
+            while let [rest @ .., 0] = nal {
+                nal = rest;
+            }
+
+            if let Some(unit) = NalUnit::parse(nal) {
+                return Some(unit);
+            }
+        }
+        None
+    }
+}
+
+/// Find the next start code at or after `from`
+///
+/// Returns `(start_code_offset, first_nal_byte_offset)`.
+fn find_start_code(data: &[u8], from: usize) -> Option<(usize, usize)> {
+    let mut i = from;
+    while i + 3 <= data.len() {
+        if data[i + 2] > 1 {
+            // Neither of the next two positions can end a start code here
+            i += 3;
+        } else if data[i..i + 3] == SHORT_START_CODE {
+            return Some((i, i + 3));
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Check if a payload begins with an Annex-B start code
+pub fn is_annex_b(data: &[u8]) -> bool {
+    data.starts_with(&AA_MAGIC) || data.starts_with(&SHORT_START_CODE)
+}
+
+/// Classification of a video payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameKind {
+    /// Contains an IDR slice (decoding can restart here)
+    Idr,
+    /// Non-IDR slice that later pictures may reference
+    Reference,
+    /// Non-IDR slice nothing references (safe to drop)
+    NonReference,
+    /// Parameter sets / SEI only, no picture data
+    Config,
+    /// No recognizable NAL units
+    Unknown,
+}
+
+impl FrameKind {
+    /// Check if dropping this payload leaves the stream decodable
+    pub fn is_droppable(&self) -> bool {
+        matches!(self, Self::NonReference)
+    }
+}
+
+/// Summary of the NAL units found in a payload
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PayloadInfo {
+    /// Number of NAL units found
+    pub nal_count: u16,
+    /// Payload contains a sequence parameter set
+    pub has_sps: bool,
+    /// Payload contains a picture parameter set
+    pub has_pps: bool,
+    /// Payload contains SEI
+    pub has_sei: bool,
+    /// Payload contains an IDR slice
+    pub has_idr: bool,
+    /// Payload contains a non-IDR slice
+    pub has_non_idr: bool,
+    /// Any slice in the payload has a non-zero `nal_ref_idc`
+    pub is_reference: bool,
+}
+
+impl PayloadInfo {
+    /// Scan a payload and summarize its NAL units
+    pub fn scan(data: &[u8]) -> Self {
+        let mut info = Self::default();
+        for nal in NalIter::new(data) {
+            info.nal_count = info.nal_count.saturating_add(1);
+            match nal.nal_type {
+                NalUnitType::Sps => info.has_sps = true,
+                NalUnitType::Pps => info.has_pps = true,
+                NalUnitType::Sei => info.has_sei = true,
+                NalUnitType::Idr => info.has_idr = true,
+                NalUnitType::NonIdrSlice | NalUnitType::DataPartition => info.has_non_idr = true,
+                _ => {}
+            }
+            if nal.nal_type.is_slice() && nal.is_reference() {
+                info.is_reference = true;
+            }
+        }
+        info
+    }
+
+    /// Classify the payload for drop decisions
+    pub fn kind(&self) -> FrameKind {
+        if self.has_idr {
+            FrameKind::Idr
+        } else if self.has_non_idr && self.is_reference {
+            FrameKind::Reference
+        } else if self.has_non_idr {
+            FrameKind::NonReference
+        } else if self.nal_count > 0 {
+            FrameKind::Config
+        } else {
+            FrameKind::Unknown
+        }
+    }
+}
+
+/// Classify a video payload in one call
+pub fn classify(data: &[u8]) -> FrameKind {
+    PayloadInfo::scan(data).kind()
+}
+
+/// Tracks GOP structure across a stream of video payloads
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GopTracker {
+    /// Total pictures observed
+    pub frames: u32,
+    /// IDR pictures observed
+    pub idr_frames: u32,
+    /// Non-reference pictures observed
+    pub non_reference_frames: u32,
+    /// Pictures since the last IDR (including it)
+    pub current_gop_len: u32,
+    /// Length of the last complete GOP (0 until two IDRs were seen)
+    pub last_gop_len: u32,
+}
+
+impl GopTracker {
+    /// Create an empty tracker
+    pub const fn new() -> Self {
+        Self {
+            frames: 0,
+            idr_frames: 0,
+            non_reference_frames: 0,
+            current_gop_len: 0,
+            last_gop_len: 0,
+        }
+    }
+
+    /// Observe one video payload and return its classification
+    pub fn observe(&mut self, data: &[u8]) -> FrameKind {
+        let kind = classify(data);
+        match kind {
+            FrameKind::Idr => {
+                if self.idr_frames > 0 {
+                    self.last_gop_len = self.current_gop_len;
+                }
+                self.idr_frames += 1;
+                self.current_gop_len = 1;
+            }
+            FrameKind::Reference | FrameKind::NonReference => {
+                if kind == FrameKind::NonReference {
+                    self.non_reference_frames += 1;
+                }
+                self.current_gop_len += 1;
+            }
+            FrameKind::Config | FrameKind::Unknown => return kind,
+        }
+        self.frames += 1;
+        kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPS: [u8; 4] = [0x67, 0x42, 0x00, 0x1F];
+    const PPS: [u8; 3] = [0x68, 0xCE, 0x3C];
+
+    #[test]
+    fn test_nal_iteration_mixed_start_codes() {
+        let stream = [
+            0x00, 0x00, 0x00, 0x01, SPS[0], SPS[1], SPS[2], SPS[3],
+            0x00, 0x00, 0x01, PPS[0], PPS[1], PPS[2],
+            0x00, 0x00, 0x00, 0x01, 0x65, 0x88, 0x84, 0x00,
+        ];
+
+        let nals: heapless::Vec<NalUnit, 4> = NalIter::new(&stream).collect();
+        assert_eq!(nals.len(), 3);
+        assert_eq!(nals[0].nal_type, NalUnitType::Sps);
+        assert_eq!(nals[0].data, &SPS);
+        assert_eq!(nals[1].nal_type, NalUnitType::Pps);
+        assert_eq!(nals[1].data, &PPS);
+        assert_eq!(nals[2].nal_type, NalUnitType::Idr);
+        // Trailing zero stripped
+        assert_eq!(nals[2].data, &[0x65, 0x88, 0x84]);
+    }
+
+    #[test]
+    fn test_classify_slices() {
+        // nal_ref_idc = 2, type 1
+        assert_eq!(classify(&[0, 0, 0, 1, 0x41, 0x9A]), FrameKind::Reference);
+        // nal_ref_idc = 0, type 1
+        assert_eq!(classify(&[0, 0, 1, 0x01, 0x9E]), FrameKind::NonReference);
+        assert!(FrameKind::NonReference.is_droppable());
+        assert_eq!(classify(&[0, 0, 0, 1, 0x06, 0x05]), FrameKind::Config);
+        assert_eq!(classify(&[0xDE, 0xAD, 0xBE, 0xEF]), FrameKind::Unknown);
+    }
+
+    #[test]
+    fn test_payload_info() {
+        let stream = [
+            0x00, 0x00, 0x00, 0x01, SPS[0], SPS[1],
+            0x00, 0x00, 0x00, 0x01, PPS[0], PPS[1],
+            0x00, 0x00, 0x00, 0x01, 0x06, 0x05,
+            0x00, 0x00, 0x00, 0x01, 0x65, 0x88,
+        ];
+        let info = PayloadInfo::scan(&stream);
+        assert_eq!(info.nal_count, 4);
+        assert!(info.has_sps && info.has_pps && info.has_sei && info.has_idr);
+        assert_eq!(info.kind(), FrameKind::Idr);
+        assert!(is_annex_b(&stream));
+    }
+
+    #[test]
+    fn test_forbidden_bit_skipped() {
+        let stream = [0x00, 0x00, 0x01, 0x81, 0x00, 0x00, 0x01, 0x41, 0x01];
+        let mut iter = NalIter::new(&stream);
+        assert_eq!(iter.next().unwrap().nal_type, NalUnitType::NonIdrSlice);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_gop_tracking() {
+        let idr = [0, 0, 0, 1, 0x65, 0x88];
+        let p = [0, 0, 0, 1, 0x41, 0x9A];
+        let b = [0, 0, 0, 1, 0x01, 0x9E];
+
+        let mut gop = GopTracker::new();
+        for frame in [&idr[..], &p, &b, &p, &idr, &p] {
+            gop.observe(frame);
+        }
+        assert_eq!(gop.frames, 6);
+        assert_eq!(gop.idr_frames, 2);
+        assert_eq!(gop.non_reference_frames, 1);
+        assert_eq!(gop.last_gop_len, 4);
+        assert_eq!(gop.current_gop_len, 2);
+    }
+}
@@ -0,0 +1,509 @@
+//! # Network Impairment
+//!
+//! Wrappers that make any `EndpointReader`/`EndpointWriter` behave like a
+//! poor WiFi link, so `DataForwarder` implementations can be tested under
+//! realistic conditions without radios.
+//!
+//! ```text
+//!   forwarder ──► ImpairedWriter ──► inner writer
+//!                  │ loss / burst loss: transfer silently discarded
+//!                  │ partial write:     only a prefix accepted
+//!                  │ bandwidth cap:     paced to N bits/s
+//!                  └ latency ± jitter:  held before delivery
+//! ```
+//!
+//! ## Model
+//!
+//! Each read or write is one transfer. Transfers are serialized, so latency
+//! also limits how many small transfers get through per second, much like a
+//! stop-and-wait link. Burst loss follows a Gilbert-Elliott model: the link
+//! flips between a good state (losing transfers with probability `loss`) and
+//! a bad state (losing every transfer).
+//!
+//! All randomness comes from a [`Rng`] seeded by [`ImpairmentConfig::seed`],
+//! so a run is reproducible given the same sequence of transfers. Delays are
+//! runtime-agnostic: one shared timer thread wakes every delayed task, so no
+//! particular async executor is needed.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::future::poll_fn;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::rng::Rng;
+use crate::traits::{EndpointReader, EndpointWriter, ForwarderResult};
+
+/// Gilbert-Elliott burst loss parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurstLoss {
+    /// Chance per transfer of entering the bad state
+    pub p_enter: f64,
+    /// Chance per transfer of leaving the bad state
+    pub p_exit: f64,
+}
+
+/// What to inflict on the wrapped endpoint (default: nothing)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImpairmentConfig {
+    /// Fixed delay per transfer
+    pub latency: Duration,
+    /// Additional random delay, uniform in `0..jitter`
+    pub jitter: Duration,
+    /// Throughput cap in bits per second (0 = unlimited)
+    pub bandwidth_bps: u64,
+    /// Chance of losing a transfer
+    pub loss: f64,
+    /// Bursts of consecutive losses
+    pub burst_loss: Option<BurstLoss>,
+    /// Chance of a write accepting only part of its data
+    pub partial_writes: f64,
+    /// Seed for every random decision
+    pub seed: u64,
+}
+
+impl Default for ImpairmentConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            bandwidth_bps: 0,
+            loss: 0.0,
+            burst_loss: None,
+            partial_writes: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// What the impairment has done so far
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImpairmentStats {
+    /// Transfers seen
+    pub transfers: u64,
+    /// Transfers discarded
+    pub lost: u64,
+    /// Bytes discarded
+    pub lost_bytes: u64,
+    /// Writes cut short
+    pub partial_writes: u64,
+    /// Total time spent delaying
+    pub delayed: Duration,
+}
+
+/// Random decisions and pacing shared by both wrappers
+struct Link {
+    config: ImpairmentConfig,
+    rng: Rng,
+    in_burst: bool,
+    next_free: Option<Instant>,
+    stats: ImpairmentStats,
+}
+
+impl Link {
+    fn new(config: ImpairmentConfig) -> Self {
+        Self {
+            config,
+            rng: Rng::new(config.seed),
+            in_burst: false,
+            next_free: None,
+            stats: ImpairmentStats::default(),
+        }
+    }
+
+    /// Decide whether the next transfer of `len` bytes is lost
+    fn lose(&mut self, len: usize) -> bool {
+        self.stats.transfers += 1;
+        if let Some(burst) = self.config.burst_loss {
+            let flip = if self.in_burst {
+                burst.p_exit
+            } else {
+                burst.p_enter
+            };
+            if self.rng.chance(flip) {
+                self.in_burst = !self.in_burst;
+            }
+        }
+        let lost = self.in_burst || self.rng.chance(self.config.loss);
+        if lost {
+            self.stats.lost += 1;
+            self.stats.lost_bytes += len as u64;
+        }
+        lost
+    }
+
+    /// Bytes a write of `len` bytes accepts
+    fn accept(&mut self, len: usize) -> usize {
+        if len > 1 && self.rng.chance(self.config.partial_writes) {
+            self.stats.partial_writes += 1;
+            1 + self.rng.below(len as u64 - 1) as usize
+        } else {
+            len
+        }
+    }
+
+    /// Hold a transfer of `len` bytes for latency, jitter and bandwidth
+    async fn delay(&mut self, len: usize) {
+        let now = Instant::now();
+        let mut deadline = now + self.config.latency;
+        if !self.config.jitter.is_zero() {
+            let jitter = self.rng.below(self.config.jitter.as_micros() as u64);
+            deadline += Duration::from_micros(jitter);
+        }
+        let bits_us = len as u64 * 8 * 1_000_000;
+        if let Some(airtime) = bits_us.checked_div(self.config.bandwidth_bps) {
+            let start = self.next_free.map_or(now, |free| free.max(now));
+            let free = start + Duration::from_micros(airtime);
+            self.next_free = Some(free);
+            deadline = deadline.max(free);
+        }
+        self.stats.delayed += deadline - now;
+        sleep_until(deadline).await;
+    }
+}
+
+/// A waker to call once its deadline passes
+struct Timer {
+    deadline: Instant,
+    seq: u64,
+    waker: Waker,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        (self.deadline, self.seq) == (other.deadline, other.seq)
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        (self.deadline, self.seq).cmp(&(other.deadline, other.seq))
+    }
+}
+
+/// Hand `waker` to the timer thread, starting it on first use
+///
+/// A waker whose future was dropped meanwhile is still called, which does
+/// nothing; it costs one heap entry rather than a sleeping thread.
+fn wake_at(deadline: Instant, waker: Waker) {
+    static TIMER: OnceLock<Sender<(Instant, Waker)>> = OnceLock::new();
+    let sender = TIMER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<(Instant, Waker)>();
+        thread::Builder::new()
+            .name("impair-timer".into())
+            .spawn(move || {
+                let mut timers: BinaryHeap<Reverse<Timer>> = BinaryHeap::new();
+                let mut seq = 0u64;
+                loop {
+                    let now = Instant::now();
+                    while timers.peek().is_some_and(|Reverse(t)| t.deadline <= now) {
+                        if let Some(Reverse(timer)) = timers.pop() {
+                            timer.waker.wake();
+                        }
+                    }
+                    let next = match timers.peek() {
+                        Some(Reverse(t)) => receiver.recv_timeout(t.deadline - now),
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    };
+                    match next {
+                        Ok((deadline, waker)) => {
+                            seq += 1;
+                            timers.push(Reverse(Timer { deadline, seq, waker }));
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+            .expect("failed to start impairment timer thread");
+        sender
+    });
+    // The receiving thread runs as long as the static sender exists
+    let _ = sender.send((deadline, waker));
+}
+
+/// Wait until `deadline` without depending on a particular executor
+async fn sleep_until(deadline: Instant) {
+    let mut armed = false;
+    poll_fn(move |cx| {
+        if Instant::now() >= deadline {
+            return Poll::Ready(());
+        }
+        if !armed {
+            armed = true;
+            wake_at(deadline, cx.waker().clone());
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// `EndpointReader` wrapper that impairs what is read
+///
+/// Reads go through the inner reader's `read_into_slice`, so lost data can
+/// be discarded before it reaches the caller's buffer.
+pub struct ImpairedReader<R> {
+    inner: R,
+    link: Link,
+    scratch: Vec<u8>,
+}
+
+impl<R: EndpointReader> ImpairedReader<R> {
+    /// Wrap a reader
+    pub fn new(inner: R, config: ImpairmentConfig) -> Self {
+        Self {
+            inner,
+            link: Link::new(config),
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Get what has been inflicted so far
+    pub fn stats(&self) -> &ImpairmentStats {
+        &self.link.stats
+    }
+
+    /// Unwrap the reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: EndpointReader> EndpointReader for ImpairedReader<R> {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        let max = buffer.writable_len().min(self.inner.max_packet_size());
+        let mut scratch = core::mem::take(&mut self.scratch);
+        scratch.resize(max, 0);
+        let result = self.read_into_slice(&mut scratch).await;
+        let result = match result {
+            Ok(n) => buffer.write(&scratch[..n]).map_err(Into::into),
+            Err(e) => Err(e),
+        };
+        self.scratch = scratch;
+        result
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        loop {
+            let n = self.inner.read_into_slice(buf).await?;
+            if n == 0 {
+                return Ok(0);
+            }
+            if !self.link.lose(n) {
+                self.link.delay(n).await;
+                return Ok(n);
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.inner.max_packet_size()
+    }
+}
+
+/// `EndpointWriter` wrapper that impairs what is written
+///
+/// Lost writes report success without reaching the inner writer, as a
+/// lossy link would.
+pub struct ImpairedWriter<W> {
+    inner: W,
+    link: Link,
+}
+
+impl<W: EndpointWriter> ImpairedWriter<W> {
+    /// Wrap a writer
+    pub fn new(inner: W, config: ImpairmentConfig) -> Self {
+        Self {
+            inner,
+            link: Link::new(config),
+        }
+    }
+
+    /// Get what has been inflicted so far
+    pub fn stats(&self) -> &ImpairmentStats {
+        &self.link.stats
+    }
+
+    /// Unwrap the writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: EndpointWriter> EndpointWriter for ImpairedWriter<W> {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        let len = self.link.accept(len);
+        self.link.delay(len).await;
+        if self.link.lose(len) {
+            return Ok(len);
+        }
+        self.inner.write_from_buffer(buffer, len).await
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        let len = self.link.accept(data.len());
+        self.link.delay(len).await;
+        if self.link.lose(len) {
+            return Ok(len);
+        }
+        self.inner.write_from_slice(&data[..len]).await
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        let len = self.link.accept(slice.len());
+        self.link.delay(len).await;
+        if self.link.lose(len) {
+            return Ok(len);
+        }
+        self.inner.write_vectored(slice.prefix(len)).await
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        self.inner.flush().await
+    }
+
+    async fn write_zlp(&mut self) -> ForwarderResult<()> {
+        self.inner.write_zlp().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct VecWriter(Vec<u8>);
+
+    impl EndpointWriter for VecWriter {
+        async fn write_from_buffer(
+            &mut self,
+            buffer: &ZeroCopyBuffer,
+            len: usize,
+        ) -> ForwarderResult<usize> {
+            let slice = buffer.readable_split(len);
+            self.0.extend_from_slice(slice.first);
+            self.0.extend_from_slice(slice.second);
+            Ok(slice.len())
+        }
+
+        async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+            self.0.extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        async fn flush(&mut self) -> ForwarderResult<()> {
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+    }
+
+    async fn lossy_run(seed: u64) -> (Vec<u8>, ImpairmentStats) {
+        let config = ImpairmentConfig {
+            loss: 0.3,
+            seed,
+            ..ImpairmentConfig::default()
+        };
+        let mut writer = ImpairedWriter::new(VecWriter::default(), config);
+        for byte in 0..100u8 {
+            assert_eq!(writer.write_from_slice(&[byte]).await, Ok(1));
+        }
+        let stats = *writer.stats();
+        (writer.into_inner().0, stats)
+    }
+
+    #[tokio::test]
+    async fn test_loss_is_reproducible() {
+        let (delivered, stats) = lossy_run(11).await;
+        assert_eq!(lossy_run(11).await.0, delivered);
+        assert_ne!(lossy_run(12).await.0, delivered);
+        assert_eq!(delivered.len() as u64 + stats.lost, 100);
+        assert!((15..45).contains(&stats.lost), "lost {}", stats.lost);
+    }
+
+    #[tokio::test]
+    async fn test_burst_loss_comes_in_runs() {
+        let config = ImpairmentConfig {
+            burst_loss: Some(BurstLoss {
+                p_enter: 0.1,
+                p_exit: 0.2,
+            }),
+            seed: 3,
+            ..ImpairmentConfig::default()
+        };
+        let mut writer = ImpairedWriter::new(VecWriter::default(), config);
+        for byte in 0..200u8 {
+            writer.write_from_slice(&[byte]).await.unwrap();
+        }
+        let delivered = writer.into_inner().0;
+        let longest_gap = delivered.windows(2).map(|w| w[1] - w[0] - 1).max().unwrap();
+        assert!(delivered.len() < 200);
+        assert!(longest_gap >= 3, "longest gap {longest_gap}");
+    }
+
+    #[tokio::test]
+    async fn test_partial_writes() {
+        let config = ImpairmentConfig {
+            partial_writes: 1.0,
+            ..ImpairmentConfig::default()
+        };
+        let mut writer = ImpairedWriter::new(VecWriter::default(), config);
+        let n = writer.write_from_slice(b"0123456789").await.unwrap();
+        assert!((1..10).contains(&n));
+        assert_eq!(writer.stats().partial_writes, 1);
+        assert_eq!(writer.into_inner().0, b"0123456789"[..n]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_timer_wakes_earliest_first() {
+        use crate::select::{select, Either};
+
+        let start = Instant::now();
+        // The later deadline reaches the timer thread first
+        let late = sleep_until(start + Duration::from_secs(5));
+        let early = sleep_until(start + Duration::from_millis(5));
+        assert!(matches!(select(late, early).await, Either::Second(())));
+    }
+
+    #[tokio::test]
+    async fn test_latency_and_bandwidth() {
+        let config = ImpairmentConfig {
+            latency: Duration::from_millis(20),
+            bandwidth_bps: 8_000,
+            ..ImpairmentConfig::default()
+        };
+        let mut writer = ImpairedWriter::new(VecWriter::default(), config);
+        let start = Instant::now();
+        // 50 bytes at 1000 bytes/s: 50 ms of airtime each
+        writer.write_from_slice(&[0; 50]).await.unwrap();
+        writer.write_from_slice(&[0; 50]).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,309 @@
+//! # embedded-io Adapters
+//!
+//! Wrappers that turn anything implementing `embedded_io_async::Read`/`Write`
+//! into an `EndpointReader`/`EndpointWriter`, so embassy-net sockets, UARTs
+//! and USB classes need no hand-written glue:
+//!
+//! ```ignore
+//! let reader = IoReader::new(usb_class_rx).with_max_packet_size(512);
+//! let writer = IoWriter::new(tcp_socket_tx);
+//! ```
+//!
+//! ## Error Mapping
+//!
+//! - `ConnectionReset`, `ConnectionAborted`, `NotConnected`, `BrokenPipe`,
+//!   `WriteZero` → `Disconnected`
+//! - `TimedOut` → `ReadTimeout` or `WriteTimeout`
+//! - `OutOfMemory` → `BufferOverflow`
+//! - `InvalidData`, `InvalidInput` → `ProtocolError`
+//! - anything else → `IoError`
+//!
+//! A read of 0 bytes is end of stream and also counts as `Disconnected`.
+//! `Interrupted` is retried. After a disconnect, `is_connected` is `false`.
+
+use embedded_io_async::{Error, ErrorKind, Read, Write};
+
+use crate::buffer::ZeroCopyBuffer;
+use crate::traits::{EndpointReader, EndpointWriter, ForwarderError, ForwarderResult};
+use crate::MTU;
+
+/// Map an `embedded-io` error kind; `timeout` is the variant for `TimedOut`
+pub fn map_error_kind(kind: ErrorKind, timeout: ForwarderError) -> ForwarderError {
+    match kind {
+        ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected
+        | ErrorKind::BrokenPipe
+        | ErrorKind::WriteZero => ForwarderError::Disconnected,
+        ErrorKind::TimedOut => timeout,
+        ErrorKind::OutOfMemory => ForwarderError::BufferOverflow,
+        ErrorKind::InvalidData | ErrorKind::InvalidInput => ForwarderError::ProtocolError,
+        _ => ForwarderError::IoError,
+    }
+}
+
+/// `EndpointReader` over any `embedded_io_async::Read`
+pub struct IoReader<T> {
+    inner: T,
+    max_packet_size: usize,
+    connected: bool,
+}
+
+impl<T: Read> IoReader<T> {
+    /// Wrap a reader; reads take at most [`MTU`] bytes
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            max_packet_size: MTU,
+            connected: true,
+        }
+    }
+
+    /// Limit how many bytes a single read takes
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Get a reference to the wrapped reader
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap the reader
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn fail(&mut self, kind: ErrorKind) -> ForwarderError {
+        let error = map_error_kind(kind, ForwarderError::ReadTimeout);
+        if error == ForwarderError::Disconnected {
+            self.connected = false;
+        }
+        error
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.inner.read(buf).await {
+                // End of stream
+                Ok(0) => return Err(self.fail(ErrorKind::NotConnected)),
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.fail(e.kind())),
+            }
+        }
+    }
+}
+
+impl<T: Read> EndpointReader for IoReader<T> {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        if buffer.is_full() {
+            return Ok(0);
+        }
+        // Only the contiguous part; the next read continues after the wrap
+        let region = buffer.writable_slice_mut(self.max_packet_size)?;
+        let n = self.read(region.first).await?;
+        buffer.commit(n)?;
+        Ok(n)
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        let len = buf.len().min(self.max_packet_size);
+        self.read(&mut buf[..len]).await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+}
+
+/// `EndpointWriter` over any `embedded_io_async::Write`
+pub struct IoWriter<T> {
+    inner: T,
+    connected: bool,
+}
+
+impl<T: Write> IoWriter<T> {
+    /// Wrap a writer
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            connected: true,
+        }
+    }
+
+    /// Get a reference to the wrapped writer
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap the writer
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn fail(&mut self, kind: ErrorKind) -> ForwarderError {
+        let error = map_error_kind(kind, ForwarderError::WriteTimeout);
+        if error == ForwarderError::Disconnected {
+            self.connected = false;
+        }
+        error
+    }
+
+    async fn write(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.inner.write(data).await {
+                Ok(0) => return Err(self.fail(ErrorKind::WriteZero)),
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.fail(e.kind())),
+            }
+        }
+    }
+}
+
+impl<T: Write> EndpointWriter for IoWriter<T> {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        // One contiguous chunk per call; a wrapped region takes two calls
+        let slice = buffer.readable_split(len);
+        let chunk = if slice.first.is_empty() {
+            slice.second
+        } else {
+            slice.first
+        };
+        self.write(chunk).await
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        self.write(data).await
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        match self.inner.flush().await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.fail(e.kind())),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_io_async::ErrorType;
+
+    /// Serves `data` in chunks, then fails with `error` (or EOF if `None`)
+    struct Scripted {
+        data: &'static [u8],
+        chunk: usize,
+        error: Option<ErrorKind>,
+        written: heapless::Vec<u8, 64>,
+    }
+
+    impl ErrorType for Scripted {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Scripted {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            if self.data.is_empty() {
+                return self.error.map_or(Ok(0), Err);
+            }
+            let n = buf.len().min(self.chunk).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    impl Write for Scripted {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            if let Some(kind) = self.error {
+                return Err(kind);
+            }
+            let n = buf.len().min(self.chunk);
+            self.written.extend_from_slice(&buf[..n]).unwrap();
+            Ok(n)
+        }
+    }
+
+    fn scripted(data: &'static [u8], chunk: usize, error: Option<ErrorKind>) -> Scripted {
+        Scripted {
+            data,
+            chunk,
+            error,
+            written: heapless::Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_respect_max_packet_size() {
+        let mut reader = IoReader::new(scripted(b"abcdefgh", 64, None)).with_max_packet_size(3);
+        let mut buffer = ZeroCopyBuffer::new();
+
+        assert_eq!(reader.read_into_buffer(&mut buffer).await, Ok(3));
+        assert_eq!(reader.read_into_buffer(&mut buffer).await, Ok(3));
+        assert_eq!(buffer.readable_slice(6), Some(&b"abcdef"[..]));
+        assert_eq!(reader.max_packet_size(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_eof_and_errors_map_to_forwarder_errors() {
+        let mut reader = IoReader::new(scripted(b"", 1, Some(ErrorKind::TimedOut)));
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            reader.read_into_slice(&mut buf).await,
+            Err(ForwarderError::ReadTimeout)
+        );
+        assert!(reader.is_connected());
+
+        let mut reader = IoReader::new(scripted(b"", 1, None));
+        assert_eq!(
+            reader.read_into_slice(&mut buf).await,
+            Err(ForwarderError::Disconnected)
+        );
+        assert!(!reader.is_connected());
+
+        let mut writer = IoWriter::new(scripted(b"", 1, Some(ErrorKind::BrokenPipe)));
+        assert_eq!(
+            writer.write_from_slice(b"x").await,
+            Err(ForwarderError::Disconnected)
+        );
+        assert!(!writer.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_short_writes_from_buffer() {
+        let mut writer = IoWriter::new(scripted(b"", 4, None));
+        let mut buffer = ZeroCopyBuffer::new();
+        buffer.write(b"0123456789").unwrap();
+
+        let n = writer.write_from_buffer(&buffer, 10).await.unwrap();
+        assert_eq!(n, 4);
+        buffer.consume(n).unwrap();
+        assert_eq!(writer.write_from_buffer(&buffer, 6).await, Ok(4));
+        assert_eq!(writer.get_ref().written, b"01234567");
+        assert_eq!(
+            map_error_kind(ErrorKind::InvalidData, ForwarderError::ReadTimeout),
+            ForwarderError::ProtocolError
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! # Adaptive Audio Jitter Buffer
+//!
+//! Audio payloads arrive over WiFi with variable delay. Playing them out as
+//! they arrive makes every jitter spike audible. This buffer holds a small,
+//! adaptive number of packets so the audio sink can pull at a steady cadence.
+//!
+//! ## Operation
+//!
+//! ```text
+//!   push(payload, now_us)            pop(out) at playout cadence
+//!          │                                   ▲
+//!          ▼                                   │
+//!   ┌──────────────────────────────────────────────┐
+//!   │ [pkt][pkt][pkt][pkt]  ...                     │  depth ≈ target_depth
+//!   └──────────────────────────────────────────────┘
+//!          │
+//!          └─► inter-arrival jitter (RFC 3550 style EWMA) ─► target_depth
+//! ```
+//!
+//! - **Priming**: after start or an underrun, `pop` returns
+//!   [`Playout::Buffering`] until `target_depth` packets are queued.
+//! - **Underrun**: if the queue runs dry while playing, `pop` returns
+//!   [`Playout::Gap`] once so the sink can conceal it (silence, repeat, PLC).
+//! - **Overflow**: if the queue exceeds `max_depth`, the oldest packet is
+//!   discarded to bound latency.
+//!
+//! The buffer is opt-in and meant only for the audio channel. Video and input
+//! payloads must bypass it so their latency is unaffected. Ordering is not
+//! handled here because the bridge transport (TCP) already delivers in order.
+
+use heapless::{Deque, Vec};
+
+/// Tuning for the jitter buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JitterConfig {
+    /// Minimum target depth in packets
+    pub min_depth: usize,
+    /// Maximum depth in packets before the oldest packet is discarded
+    pub max_depth: usize,
+    /// Jitter multiplier used to size the target depth
+    pub jitter_factor: u32,
+}
+
+impl Default for JitterConfig {
+    fn default() -> Self {
+        Self {
+            min_depth: 2,
+            max_depth: 12,
+            jitter_factor: 3,
+        }
+    }
+}
+
+/// Result of pulling one packet for playout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Playout {
+    /// A packet of `len` bytes was copied into the output buffer
+    Packet {
+        /// Number of bytes written
+        len: usize,
+    },
+    /// Underrun: nothing to play, conceal this slot
+    Gap,
+    /// Priming: not enough data queued yet to start playout
+    Buffering,
+}
+
+/// Errors from the jitter buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum JitterError {
+    /// Payload larger than the per-packet capacity
+    PacketTooLarge,
+    /// Output buffer smaller than the next packet
+    OutputTooSmall,
+}
+
+/// Jitter buffer statistics
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct JitterStats {
+    /// Packets currently queued
+    pub depth: usize,
+    /// Current adaptive target depth
+    pub target_depth: usize,
+    /// Smoothed inter-arrival jitter in microseconds
+    pub jitter_us: u32,
+    /// Smoothed inter-arrival interval in microseconds
+    pub interval_us: u32,
+    /// Number of times playout ran dry
+    pub underruns: u32,
+    /// Packets discarded because the buffer was over `max_depth`
+    pub overflows: u32,
+    /// Packets accepted
+    pub packets: u32,
+}
+
+/// Adaptive jitter buffer holding up to `N` packets of up to `P` bytes
+pub struct JitterBuffer<const N: usize, const P: usize> {
+    config: JitterConfig,
+    queue: Deque<Vec<u8, P>, N>,
+    /// Waiting for `target_depth` packets before (re)starting playout
+    priming: bool,
+    last_arrival_us: Option<u64>,
+    stats: JitterStats,
+}
+
+/// Jitter buffer sized for typical Android Auto audio payloads
+pub type AudioJitterBuffer = JitterBuffer<16, 2048>;
+
+impl<const N: usize, const P: usize> JitterBuffer<N, P> {
+    /// Create an empty buffer
+    ///
+    /// Depths are clamped to `1..=N`, so a zero `max_depth` still holds one
+    /// packet.
+    pub fn new(config: JitterConfig) -> Self {
+        const { assert!(N > 0, "jitter buffer must hold at least one packet") };
+        let max_depth = config.max_depth.clamp(1, N);
+        let config = JitterConfig {
+            max_depth,
+            min_depth: config.min_depth.clamp(1, max_depth),
+            ..config
+        };
+        Self {
+            queue: Deque::new(),
+            priming: true,
+            last_arrival_us: None,
+            stats: JitterStats {
+                target_depth: config.min_depth,
+                ..JitterStats::default()
+            },
+            config,
+        }
+    }
+
+    /// Queue an audio payload that arrived at `now_us`
+    pub fn push(&mut self, payload: &[u8], now_us: u64) -> Result<(), JitterError> {
+        let packet = Vec::from_slice(payload).map_err(|_| JitterError::PacketTooLarge)?;
+
+        self.update_jitter(now_us);
+
+        if self.queue.len() >= self.config.max_depth {
+            self.queue.pop_front();
+            self.stats.overflows = self.stats.overflows.saturating_add(1);
+        }
+        // Cannot fail: max_depth <= N and we just made room
+        let _ = self.queue.push_back(packet);
+        self.stats.packets = self.stats.packets.saturating_add(1);
+
+        if self.priming && self.queue.len() >= self.stats.target_depth {
+            self.priming = false;
+        }
+        Ok(())
+    }
+
+    /// Pull the next packet for playout into `out`
+    ///
+    /// Call this at the audio sink's cadence (once per packet duration).
+    pub fn pop(&mut self, out: &mut [u8]) -> Result<Playout, JitterError> {
+        if self.priming {
+            return Ok(Playout::Buffering);
+        }
+
+        let Some(packet) = self.queue.front() else {
+            self.priming = true;
+            self.stats.underruns = self.stats.underruns.saturating_add(1);
+            return Ok(Playout::Gap);
+        };
+
+        let len = packet.len();
+        let dst = out.get_mut(..len).ok_or(JitterError::OutputTooSmall)?;
+        dst.copy_from_slice(packet);
+        self.queue.pop_front();
+        Ok(Playout::Packet { len })
+    }
+
+    /// Number of packets queued
+    pub fn depth(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Current adaptive target depth
+    pub fn target_depth(&self) -> usize {
+        self.stats.target_depth
+    }
+
+    /// Get statistics
+    pub fn stats(&self) -> JitterStats {
+        JitterStats {
+            depth: self.queue.len(),
+            ..self.stats
+        }
+    }
+
+    /// Drop queued audio and restart priming (e.g. on stream restart)
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.priming = true;
+        self.last_arrival_us = None;
+    }
+
+    /// Update the interval/jitter estimates and the target depth
+    fn update_jitter(&mut self, now_us: u64) {
+        let Some(last) = self.last_arrival_us.replace(now_us) else {
+            return;
+        };
+        let delta = now_us.saturating_sub(last).min(u32::MAX as u64) as i64;
+
+        let interval = self.stats.interval_us as i64;
+        let interval = if interval == 0 {
+            delta
+        } else {
+            interval + (delta - interval) / 8
+        };
+        let jitter = self.stats.jitter_us as i64;
+        let jitter = jitter + ((delta - interval).abs() - jitter) / 16;
+
+        self.stats.interval_us = interval.max(0) as u32;
+        self.stats.jitter_us = jitter.max(0) as u32;
+
+        if self.stats.interval_us > 0 {
+            let spread = self.stats.jitter_us as u64 * self.config.jitter_factor as u64;
+            let extra = spread.div_ceil(self.stats.interval_us as u64) as usize;
+            self.stats.target_depth =
+                (1 + extra).clamp(self.config.min_depth, self.config.max_depth);
+        }
+    }
+}
+
+impl<const N: usize, const P: usize> Default for JitterBuffer<N, P> {
+    fn default() -> Self {
+        Self::new(JitterConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestBuffer = JitterBuffer<8, 16>;
+
+    #[test]
+    fn test_primes_before_playout() {
+        let mut jb = TestBuffer::default();
+        let mut out = [0u8; 16];
+
+        jb.push(b"a", 0).unwrap();
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Buffering));
+
+        jb.push(b"bc", 10_000).unwrap();
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Packet { len: 1 }));
+        assert_eq!(&out[..1], b"a");
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Packet { len: 2 }));
+        assert_eq!(&out[..2], b"bc");
+    }
+
+    #[test]
+    fn test_underrun_signals_gap_then_reprimes() {
+        let mut jb = TestBuffer::default();
+        let mut out = [0u8; 16];
+        jb.push(b"a", 0).unwrap();
+        jb.push(b"b", 10_000).unwrap();
+        jb.pop(&mut out).unwrap();
+        jb.pop(&mut out).unwrap();
+
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Gap));
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Buffering));
+        assert_eq!(jb.stats().underruns, 1);
+    }
+
+    #[test]
+    fn test_depth_adapts_to_jitter() {
+        let mut jb = TestBuffer::default();
+
+        // Steady 10ms arrivals: stays at the minimum depth
+        for i in 0..20u64 {
+            jb.push(b"x", i * 10_000).unwrap();
+        }
+        assert_eq!(jb.target_depth(), 2);
+
+        // Alternating 2ms / 18ms arrivals: depth grows
+        let mut now = 200_000;
+        for i in 0..40u64 {
+            now += if i % 2 == 0 { 2_000 } else { 18_000 };
+            jb.push(b"x", now).unwrap();
+        }
+        let stats = jb.stats();
+        assert!(stats.jitter_us > 4_000, "jitter {}", stats.jitter_us);
+        assert!(stats.target_depth > 2, "target {}", stats.target_depth);
+        assert!(stats.target_depth <= 8);
+    }
+
+    #[test]
+    fn test_overflow_discards_oldest() {
+        let mut jb = TestBuffer::new(JitterConfig {
+            max_depth: 3,
+            ..JitterConfig::default()
+        });
+        for (i, byte) in [b"1", b"2", b"3", b"4"].iter().enumerate() {
+            jb.push(*byte, i as u64 * 10_000).unwrap();
+        }
+        let mut out = [0u8; 16];
+        assert_eq!(jb.pop(&mut out), Ok(Playout::Packet { len: 1 }));
+        assert_eq!(out[0], b'2');
+        assert_eq!(jb.stats().overflows, 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut jb = TestBuffer::default();
+        assert_eq!(jb.push(&[0u8; 17], 0), Err(JitterError::PacketTooLarge));
+
+        jb.push(&[0u8; 16], 0).unwrap();
+        jb.push(&[0u8; 16], 1).unwrap();
+        let mut small = [0u8; 4];
+        assert_eq!(jb.pop(&mut small), Err(JitterError::OutputTooSmall));
+        assert_eq!(jb.depth(), 2);
+    }
+
+    #[test]
+    fn test_zero_max_depth_holds_one_packet() {
+        let mut jb = TestBuffer::new(JitterConfig {
+            min_depth: 0,
+            max_depth: 0,
+            ..JitterConfig::default()
+        });
+        jb.push(b"1", 0).unwrap();
+        jb.push(b"2", 20_000).unwrap();
+        assert_eq!(jb.depth(), 1);
+        assert_eq!(jb.stats().overflows, 1);
+    }
+}
@@ -5,6 +5,24 @@
 //! - **Protocol Messages**: Serializable message types for USB-WiFi communication
 //! - **DataForwarder Trait**: Abstraction for bidirectional data flow
 //! - **Zero-Copy Buffers**: Lock-free ring buffers for low-latency data transfer
+//! - **Flow Control**: Per-channel credit windows that keep senders within buffer capacity
+//! - **H.264 Scanner**: Annex-B NAL unit parsing for video-aware decisions
+//! - **Congestion Control**: Decodability-preserving video frame dropping
+//! - **Jitter Buffer**: Optional adaptive playout buffer for the audio channel
+//! - **Batching**: Coalescing of small input/control messages into one frame
+//! - **Capture**: Timestamped traffic recording with size-bounded rotation
+//! - **AOA Handshake**: Sans-IO Android Open Accessory 2.0 accessory-mode switch
+//! - **Head-Unit Quirks**: Per-car handshake timing and bulk-transfer adjustments
+//! - **AAP Headers**: Android Auto Protocol frame header decoding for tooling
+//! - **Packet Pool**: Refcounted MTU-sized buffers for sending one frame to several consumers
+//! - **Forwarding Statistics**: Automatic per-direction counters and latency histograms
+//! - **Future Combinators**: Executor-agnostic `select` for full-duplex forwarding
+//! - **Timeouts and Retries**: `Clock` trait and endpoint wrappers honoring `ForwarderConfig`
+//! - **Deterministic RNG**: Seeded generator for fault injection and simulation
+//! - **Network Impairment**: Latency, loss and bandwidth wrappers for endpoints (std)
+//! - **Loopback Endpoints**: In-memory pipes for driving a `DataForwarder` in tests (std)
+//! - **embedded-io Adapters**: Endpoints over any `embedded_io_async` reader or writer
+//! - **TCP Endpoints**: Endpoint adapters for tokio `TcpStream` halves (tokio)
 //!
 //! ## Architecture
 //!
@@ -26,14 +44,39 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod aap;
+pub mod aoa;
+pub mod batch;
 pub mod buffer;
+pub mod capture;
+pub mod congestion;
+pub mod flow;
+pub mod h264;
+#[cfg(feature = "std")]
+pub mod impair;
+pub mod io;
+pub mod jitter;
+#[cfg(feature = "std")]
+pub mod loopback;
+pub mod pool;
 pub mod protocol;
+pub mod quirks;
+pub mod rng;
+pub mod select;
+pub mod stats;
+#[cfg(feature = "tokio")]
+pub mod tcp;
+pub mod time;
 pub mod traits;
 
 // Re-export main types for convenience
-pub use buffer::{BufferError, BufferSlice, ZeroCopyBuffer, BUFFER_SIZE};
+pub use buffer::{BufferError, BufferSlice, RingBuffer, ZeroCopyBuffer, BUFFER_SIZE};
+pub use flow::{CreditGrantor, FlowConfig, FlowError, SendWindow};
 pub use protocol::{ControlMessage, DataPayload, Header, Message, MessageType};
-pub use traits::{DataForwarder, EndpointReader, EndpointWriter, ForwarderError};
+pub use traits::{
+    DataForwarder, EndpointForwarder, EndpointReader, EndpointWriter, ForwarderError,
+    TimedForwarder,
+};
 
 /// Library version for protocol compatibility checks
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
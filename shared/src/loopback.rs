@@ -0,0 +1,286 @@
+//! # In-Memory Loopback Endpoints
+//!
+//! Byte pipes implementing `EndpointReader`/`EndpointWriter`, so a
+//! `DataForwarder` can be driven entirely in memory: a test pushes bytes into
+//! the "USB" side and asserts what comes out of the "WiFi" side.
+//!
+//! ```text
+//!   test ──PipeWriter──► pipe ──PipeReader──► forwarder ──PipeWriter──► pipe ──PipeReader──► test
+//!          (USB host)                          (USB in)     (WiFi out)                        (phone)
+//! ```
+//!
+//! ## Behavior
+//!
+//! - Reads never wait: an empty pipe reads 0 bytes, like a poll with no data.
+//! - Writes accept what fits in the pipe's capacity (possibly 0 bytes).
+//! - Closing or dropping either end disconnects the pipe. The reader still
+//!   drains what was written before, then fails with `Disconnected`.
+//! - [`PipeReader::fail_next`]/[`PipeWriter::fail_next`] make the next
+//!   operation on that end fail with a chosen error.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::vec::Vec;
+
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::traits::{EndpointReader, EndpointWriter, ForwarderError, ForwarderResult};
+
+/// Default maximum read size, a high-speed USB bulk packet
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 512;
+
+#[derive(Debug)]
+struct PipeState {
+    data: VecDeque<u8>,
+    capacity: usize,
+    closed: bool,
+    read_error: Option<ForwarderError>,
+    write_error: Option<ForwarderError>,
+}
+
+#[derive(Debug, Clone)]
+struct Pipe(Arc<Mutex<PipeState>>);
+
+impl Pipe {
+    fn lock(&self) -> MutexGuard<'_, PipeState> {
+        // A panicking test must not cascade into every other end
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Create a pipe holding at most `capacity` bytes
+pub fn pipe(capacity: usize) -> (PipeWriter, PipeReader) {
+    let pipe = Pipe(Arc::new(Mutex::new(PipeState {
+        data: VecDeque::with_capacity(capacity),
+        capacity,
+        closed: false,
+        read_error: None,
+        write_error: None,
+    })));
+    (
+        PipeWriter { pipe: pipe.clone() },
+        PipeReader {
+            pipe,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        },
+    )
+}
+
+/// One end of a full-duplex link
+#[derive(Debug)]
+pub struct DuplexEnd {
+    /// Receives what the other end writes
+    pub reader: PipeReader,
+    /// Sends to the other end
+    pub writer: PipeWriter,
+}
+
+/// Create two connected ends, each direction holding at most `capacity` bytes
+pub fn duplex(capacity: usize) -> (DuplexEnd, DuplexEnd) {
+    let (a_writer, b_reader) = pipe(capacity);
+    let (b_writer, a_reader) = pipe(capacity);
+    (
+        DuplexEnd {
+            reader: a_reader,
+            writer: a_writer,
+        },
+        DuplexEnd {
+            reader: b_reader,
+            writer: b_writer,
+        },
+    )
+}
+
+/// Reading end of a pipe
+#[derive(Debug)]
+pub struct PipeReader {
+    pipe: Pipe,
+    max_packet_size: usize,
+}
+
+impl PipeReader {
+    /// Limit how many bytes a single read returns
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Take everything currently buffered
+    pub fn drain(&self) -> Vec<u8> {
+        self.pipe.lock().data.drain(..).collect()
+    }
+
+    /// Bytes waiting to be read
+    pub fn available(&self) -> usize {
+        self.pipe.lock().data.len()
+    }
+
+    /// Make the next read fail with `error`
+    pub fn fail_next(&self, error: ForwarderError) {
+        self.pipe.lock().read_error = Some(error);
+    }
+
+    /// Disconnect the pipe
+    pub fn close(&self) {
+        self.pipe.lock().closed = true;
+    }
+
+    /// Pop up to `max` bytes, or fail if injected or disconnected and empty
+    fn take(&self, max: usize) -> ForwarderResult<Vec<u8>> {
+        let mut state = self.pipe.lock();
+        if let Some(error) = state.read_error.take() {
+            return Err(error);
+        }
+        if state.data.is_empty() && state.closed {
+            return Err(ForwarderError::Disconnected);
+        }
+        let n = max.min(self.max_packet_size).min(state.data.len());
+        Ok(state.data.drain(..n).collect())
+    }
+}
+
+impl EndpointReader for PipeReader {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        let bytes = self.take(buffer.writable_len())?;
+        Ok(buffer.write(&bytes)?)
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        let bytes = self.take(buf.len())?;
+        buf[..bytes.len()].copy_from_slice(&bytes);
+        Ok(bytes.len())
+    }
+
+    fn is_connected(&self) -> bool {
+        let state = self.pipe.lock();
+        !state.closed || !state.data.is_empty()
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Writing end of a pipe
+#[derive(Debug)]
+pub struct PipeWriter {
+    pipe: Pipe,
+}
+
+impl PipeWriter {
+    /// Append as much of `data` as fits, without going through the trait
+    pub fn push(&self, data: &[u8]) -> ForwarderResult<usize> {
+        let mut state = self.pipe.lock();
+        if let Some(error) = state.write_error.take() {
+            return Err(error);
+        }
+        if state.closed {
+            return Err(ForwarderError::Disconnected);
+        }
+        let n = data.len().min(state.capacity - state.data.len());
+        state.data.extend(&data[..n]);
+        Ok(n)
+    }
+
+    /// Make the next write fail with `error`
+    pub fn fail_next(&self, error: ForwarderError) {
+        self.pipe.lock().write_error = Some(error);
+    }
+
+    /// Disconnect the pipe; data already written can still be read
+    pub fn close(&self) {
+        self.pipe.lock().closed = true;
+    }
+}
+
+impl EndpointWriter for PipeWriter {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        self.write_vectored(buffer.readable_split(len)).await
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        self.push(data)
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        let n = self.push(slice.first)?;
+        if n < slice.first.len() {
+            return Ok(n);
+        }
+        Ok(n + self.push(slice.second)?)
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.pipe.lock().closed
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pipe_capacity_and_packets() {
+        let (mut writer, reader) = pipe(8);
+        let mut reader = reader.with_max_packet_size(3);
+
+        assert_eq!(writer.write_from_slice(b"0123456789").await, Ok(8));
+        let mut buf = [0u8; 16];
+        assert_eq!(reader.read_into_slice(&mut buf).await, Ok(3));
+        assert_eq!(&buf[..3], b"012");
+        assert_eq!(reader.drain(), b"34567");
+        assert_eq!(reader.read_into_slice(&mut buf).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_close_drains_then_disconnects() {
+        let (writer, mut reader) = pipe(64);
+        writer.push(b"bye").unwrap();
+        drop(writer);
+
+        assert!(reader.is_connected());
+        let mut buffer = ZeroCopyBuffer::new();
+        assert_eq!(reader.read_into_buffer(&mut buffer).await, Ok(3));
+        assert!(!reader.is_connected());
+        assert_eq!(
+            reader.read_into_buffer(&mut buffer).await,
+            Err(ForwarderError::Disconnected)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_injected_errors_fire_once() {
+        let (a, b) = duplex(64);
+        a.writer.fail_next(ForwarderError::WifiError);
+        assert_eq!(a.writer.push(b"x"), Err(ForwarderError::WifiError));
+        assert_eq!(a.writer.push(b"x"), Ok(1));
+
+        let mut reader = b.reader;
+        reader.fail_next(ForwarderError::UsbError);
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            reader.read_into_slice(&mut buf).await,
+            Err(ForwarderError::UsbError)
+        );
+        assert_eq!(reader.read_into_slice(&mut buf).await, Ok(1));
+    }
+}
@@ -0,0 +1,465 @@
+//! # Packet Buffer Pool
+//!
+//! A fixed set of MTU-sized buffers with reference-counted handles, for
+//! frames that several consumers need at once (the forward path, the
+//! capture tap, the retransmit queue) without copying them.
+//!
+//! ```text
+//! alloc() ──► PacketBufMut ──freeze()──► PacketBuf ──clone()──► PacketBuf
+//!              (fill it)                    │                      │
+//!                                           └──── last drop ───────┴──► free
+//! ```
+//!
+//! A [`PacketBufMut`] is the only handle to its buffer and can be written.
+//! [`PacketBufMut::freeze`] turns it into a read-only [`PacketBuf`], which
+//! can be cloned cheaply and sent to other tasks; the buffer returns to the
+//! pool when the last handle drops. Like the ring buffer, the pool does no
+//! heap allocation, and `'static` handles come from a pool in static
+//! memory:
+//!
+//! ```ignore
+//! static PACKETS: PacketPool<8> = PacketPool::new();
+//!
+//! let packet = usb_reader.read_packet(&PACKETS).await?;
+//! capture_tx.send(packet.clone()).await;
+//! wifi_writer.write_packet(&packet).await?;
+//! ```
+//!
+//! ## Exhaustion
+//!
+//! When every buffer is in use, `alloc` fails with [`PoolError::Exhausted`]
+//! instead of waiting; [`PacketPool::stats`] counts these failures along
+//! with the peak number of buffers in use, to help size the pool.
+//!
+//! Reference counts use atomic read-modify-write operations, so targets
+//! without compare-and-swap need it emulated.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use crate::traits::ForwarderError;
+use crate::MTU;
+
+/// Errors from pool operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PoolError {
+    /// Every buffer is in use
+    Exhausted,
+    /// Data does not fit in one buffer
+    Overflow,
+}
+
+impl From<PoolError> for ForwarderError {
+    fn from(e: PoolError) -> Self {
+        match e {
+            PoolError::Exhausted | PoolError::Overflow => ForwarderError::BufferOverflow,
+        }
+    }
+}
+
+/// Snapshot of pool usage
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PoolStats {
+    /// Buffers currently held by at least one handle
+    pub in_use: usize,
+    /// Highest `in_use` seen
+    pub peak: usize,
+    /// Successful allocations
+    pub allocations: u32,
+    /// Allocations that failed because the pool was empty
+    pub exhaustions: u32,
+}
+
+/// Usage counters shared by the pool and its handles
+struct Counters {
+    in_use: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicU32,
+    exhaustions: AtomicU32,
+}
+
+/// One buffer and the number of handles to it (0 when free)
+struct Slot<const SIZE: usize> {
+    refs: AtomicUsize,
+    data: UnsafeCell<[u8; SIZE]>,
+}
+
+// SAFETY: the data is only written through the single `PacketBufMut` for
+// a slot, and only read through `PacketBuf`s once no writer is left.
+unsafe impl<const SIZE: usize> Sync for Slot<SIZE> {}
+
+impl<const SIZE: usize> Slot<SIZE> {
+    const fn new() -> Self {
+        Self {
+            refs: AtomicUsize::new(0),
+            data: UnsafeCell::new([0; SIZE]),
+        }
+    }
+}
+
+/// `COUNT` buffers of `SIZE` bytes each
+pub struct PacketPool<const COUNT: usize, const SIZE: usize = MTU> {
+    slots: [Slot<SIZE>; COUNT],
+    counters: Counters,
+}
+
+impl<const COUNT: usize, const SIZE: usize> PacketPool<COUNT, SIZE> {
+    /// Create a pool with every buffer free
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { Slot::new() }; COUNT],
+            counters: Counters {
+                in_use: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                allocations: AtomicU32::new(0),
+                exhaustions: AtomicU32::new(0),
+            },
+        }
+    }
+
+    /// Number of buffers in the pool
+    pub const fn capacity(&self) -> usize {
+        COUNT
+    }
+
+    /// Size of each buffer in bytes
+    pub const fn buffer_size(&self) -> usize {
+        SIZE
+    }
+
+    /// Take a free buffer, empty and ready to fill
+    pub fn alloc(&self) -> Result<PacketBufMut<'_, SIZE>, PoolError> {
+        let counters = &self.counters;
+        // Acquire pairs with the release in `release`, so the previous
+        // owners are done with the bytes before they are handed out again
+        let Some(slot) = self.slots.iter().find(|slot| {
+            slot.refs
+                .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        }) else {
+            counters.exhaustions.fetch_add(1, Ordering::Relaxed);
+            return Err(PoolError::Exhausted);
+        };
+
+        let in_use = counters.in_use.fetch_add(1, Ordering::Relaxed) + 1;
+        counters.peak.fetch_max(in_use, Ordering::Relaxed);
+        counters.allocations.fetch_add(1, Ordering::Relaxed);
+        Ok(PacketBufMut {
+            slot,
+            counters,
+            len: 0,
+        })
+    }
+
+    /// Allocate a buffer holding a copy of `data`
+    pub fn alloc_from(&self, data: &[u8]) -> Result<PacketBuf<'_, SIZE>, PoolError> {
+        if data.len() > SIZE {
+            return Err(PoolError::Overflow);
+        }
+        let mut packet = self.alloc()?;
+        packet.extend_from_slice(data)?;
+        Ok(packet.freeze())
+    }
+
+    /// Number of free buffers
+    pub fn available(&self) -> usize {
+        COUNT - self.counters.in_use.load(Ordering::Relaxed)
+    }
+
+    /// Current usage counters
+    pub fn stats(&self) -> PoolStats {
+        let counters = &self.counters;
+        PoolStats {
+            in_use: counters.in_use.load(Ordering::Relaxed),
+            peak: counters.peak.load(Ordering::Relaxed),
+            allocations: counters.allocations.load(Ordering::Relaxed),
+            exhaustions: counters.exhaustions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<const COUNT: usize, const SIZE: usize> Default for PacketPool<COUNT, SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drop one handle's reference, freeing the slot after the last one
+///
+/// The last handle leaves `in_use` before the slot can be allocated again,
+/// so `in_use` never counts a reused slot twice.
+fn release<const SIZE: usize>(slot: &Slot<SIZE>, counters: &Counters) {
+    let mut refs = slot.refs.load(Ordering::Relaxed);
+    loop {
+        if refs == 1 {
+            // The only handle left: nothing can clone it meanwhile
+            counters.in_use.fetch_sub(1, Ordering::Relaxed);
+            slot.refs.store(0, Ordering::Release);
+            return;
+        }
+        match slot
+            .refs
+            .compare_exchange_weak(refs, refs - 1, Ordering::Release, Ordering::Relaxed)
+        {
+            Ok(_) => return,
+            Err(actual) => refs = actual,
+        }
+    }
+}
+
+/// The only handle to a freshly allocated buffer, for filling it
+///
+/// Derefs to the bytes filled so far. Call [`freeze`](Self::freeze) to
+/// share it; dropping it returns the buffer to the pool.
+pub struct PacketBufMut<'a, const SIZE: usize = MTU> {
+    slot: &'a Slot<SIZE>,
+    counters: &'a Counters,
+    len: usize,
+}
+
+impl<'a, const SIZE: usize> PacketBufMut<'a, SIZE> {
+    /// Size of the buffer in bytes
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// The whole buffer, for filling in place before `set_len`
+    pub fn storage_mut(&mut self) -> &mut [u8; SIZE] {
+        // SAFETY: this is the only handle to the slot
+        unsafe { &mut *self.slot.data.get() }
+    }
+
+    /// Set how many bytes of the buffer hold the packet
+    pub fn set_len(&mut self, len: usize) -> Result<(), PoolError> {
+        if len > SIZE {
+            return Err(PoolError::Overflow);
+        }
+        self.len = len;
+        Ok(())
+    }
+
+    /// Append `data`; all or nothing
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), PoolError> {
+        let end = self.len + data.len();
+        if end > SIZE {
+            return Err(PoolError::Overflow);
+        }
+        let start = self.len;
+        self.storage_mut()[start..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Make the packet read-only so it can be shared
+    pub fn freeze(self) -> PacketBuf<'a, SIZE> {
+        let packet = PacketBuf {
+            slot: self.slot,
+            counters: self.counters,
+            len: self.len,
+        };
+        // The reference moves to the new handle
+        core::mem::forget(self);
+        packet
+    }
+}
+
+impl<const SIZE: usize> Deref for PacketBufMut<'_, SIZE> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: this is the only handle to the slot
+        unsafe { &(&*self.slot.data.get())[..self.len] }
+    }
+}
+
+impl<const SIZE: usize> DerefMut for PacketBufMut<'_, SIZE> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.storage_mut()[..len]
+    }
+}
+
+impl<const SIZE: usize> Drop for PacketBufMut<'_, SIZE> {
+    fn drop(&mut self) {
+        release(self.slot, self.counters);
+    }
+}
+
+/// Shared, read-only handle to a pooled packet
+///
+/// Cloning adds a reference instead of copying; the buffer returns to the
+/// pool when the last clone drops.
+pub struct PacketBuf<'a, const SIZE: usize = MTU> {
+    slot: &'a Slot<SIZE>,
+    counters: &'a Counters,
+    len: usize,
+}
+
+impl<const SIZE: usize> PacketBuf<'_, SIZE> {
+    /// Number of handles to this packet, including this one
+    pub fn ref_count(&self) -> usize {
+        self.slot.refs.load(Ordering::Relaxed)
+    }
+}
+
+impl<const SIZE: usize> Clone for PacketBuf<'_, SIZE> {
+    fn clone(&self) -> Self {
+        // The new handle is made from an existing one, which keeps the
+        // slot allocated, so no ordering is needed
+        self.slot.refs.fetch_add(1, Ordering::Relaxed);
+        Self {
+            slot: self.slot,
+            counters: self.counters,
+            len: self.len,
+        }
+    }
+}
+
+impl<const SIZE: usize> Deref for PacketBuf<'_, SIZE> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: a frozen packet has no writer left
+        unsafe { &(&*self.slot.data.get())[..self.len] }
+    }
+}
+
+impl<const SIZE: usize> Drop for PacketBuf<'_, SIZE> {
+    fn drop(&mut self) {
+        release(self.slot, self.counters);
+    }
+}
+
+impl<const SIZE: usize> core::fmt::Debug for PacketBuf<'_, SIZE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PacketBuf")
+            .field("len", &self.len)
+            .field("refs", &self.ref_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_handle_returns_buffer() {
+        let pool = PacketPool::<2, 64>::new();
+
+        let mut packet = pool.alloc().unwrap();
+        packet.extend_from_slice(b"frame").unwrap();
+        let packet = packet.freeze();
+        let tap = packet.clone();
+        let retransmit = packet.clone();
+        assert_eq!(packet.ref_count(), 3);
+        assert_eq!(pool.available(), 1);
+
+        drop(packet);
+        drop(tap);
+        assert_eq!(&*retransmit, b"frame");
+        assert_eq!(pool.available(), 1);
+        drop(retransmit);
+        assert_eq!(pool.available(), 2);
+    }
+
+    #[test]
+    fn test_exhaustion_accounting() {
+        let pool = PacketPool::<2, 64>::new();
+        let a = pool.alloc_from(b"a").unwrap();
+        let b = pool.alloc().unwrap();
+        assert_eq!(pool.alloc().err(), Some(PoolError::Exhausted));
+        assert_eq!(pool.alloc_from(&[0; 65]).err(), Some(PoolError::Overflow));
+
+        drop(b);
+        let c = pool.alloc_from(b"c").unwrap();
+        assert_eq!((&*a, &*c), (&b"a"[..], &b"c"[..]));
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                in_use: 2,
+                peak: 2,
+                allocations: 3,
+                exhaustions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_fill_in_place() {
+        let pool = PacketPool::<1, 8>::new();
+        let mut packet = pool.alloc().unwrap();
+        packet.storage_mut()[..4].copy_from_slice(b"abcd");
+        packet.set_len(4).unwrap();
+        packet[0] = b'A';
+        assert_eq!(packet.set_len(9), Err(PoolError::Overflow));
+        assert_eq!(packet.extend_from_slice(b"efghi"), Err(PoolError::Overflow));
+        assert_eq!(&*packet.freeze(), b"Abcd");
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[tokio::test]
+    async fn test_endpoint_packets() {
+        use crate::loopback::pipe;
+        use crate::traits::{EndpointReader, EndpointWriter};
+
+        let pool = PacketPool::<1, 64>::new();
+        let (mut usb_tx, mut usb_rx) = pipe(64);
+        let (mut wifi_tx, wifi_rx) = pipe(64);
+
+        usb_tx.write_from_slice(b"hello").await.unwrap();
+        let packet = usb_rx.read_packet(&pool).await.unwrap();
+        wifi_tx.write_packet(&packet).await.unwrap();
+        assert_eq!(wifi_rx.drain(), b"hello");
+
+        // No buffer free: the data stays in the transport
+        usb_tx.write_from_slice(b"next").await.unwrap();
+        assert_eq!(
+            usb_rx.read_packet(&pool).await.err(),
+            Some(ForwarderError::BufferOverflow)
+        );
+        assert_eq!(usb_rx.available(), 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_handles_across_threads() {
+        static POOL: PacketPool<4, 16> = PacketPool::new();
+
+        let packet = POOL.alloc_from(b"shared").unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..3 {
+                let copy = packet.clone();
+                scope.spawn(move || assert_eq!(&*copy, b"shared"));
+            }
+        });
+        drop(packet);
+        assert_eq!(POOL.stats().in_use, 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_in_use_never_exceeds_count() {
+        static POOL: PacketPool<1, 8> = PacketPool::new();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..200_000 {
+                        if let Ok(packet) = POOL.alloc_from(b"x") {
+                            drop(packet.clone());
+                        }
+                        // A freed slot may be reused only after it left in_use
+                        assert!(POOL.stats().in_use <= 1);
+                        assert!(POOL.available() <= 1);
+                    }
+                });
+            }
+        });
+        assert_eq!(POOL.available(), 1);
+    }
+}
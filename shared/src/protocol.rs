@@ -29,6 +29,12 @@ pub const FRAME_MAGIC: [u8; 4] = [0xAA, 0x57, 0x49, 0x46]; // "AAWIF" sort of
 /// Maximum payload size (MTU minus header overhead)
 pub const MAX_PAYLOAD_SIZE: usize = MTU - 16;
 
+/// Bytes added around every payload: magic(4) + header(6) + type(1) + CRC(2)
+pub const FRAME_OVERHEAD: usize = 13;
+
+/// Header flag: payload packs several messages (see [`crate::batch`])
+pub const FLAG_BATCH: u8 = 0x01;
+
 /// Message types for the bridge protocol
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -46,6 +52,8 @@ pub enum MessageType {
     Ping = 0x05,
     /// Keep-alive pong response
     Pong = 0x06,
+    /// Several small messages packed into one frame
+    Batch = 0x07,
     /// Debug/logging message (dev only)
     Debug = 0xFF,
 }
@@ -61,6 +69,7 @@ impl TryFrom<u8> for MessageType {
             0x04 => Ok(Self::Nack),
             0x05 => Ok(Self::Ping),
             0x06 => Ok(Self::Pong),
+            0x07 => Ok(Self::Batch),
             0xFF => Ok(Self::Debug),
             _ => Err(()),
         }
@@ -145,6 +154,22 @@ pub enum ControlMessage {
         /// Packets dropped
         packets_dropped: u32,
     },
+    /// Grant additional send credits on a channel (see [`crate::flow`])
+    WindowUpdate {
+        /// Channel the credits apply to
+        channel: u8,
+        /// Additional payload bytes the peer may send
+        credits: u32,
+    },
+    /// Ask the video source for an IDR frame (decoder recovery)
+    RequestKeyframe,
+    /// Ask the video source to adapt its encoder to the link
+    BitrateHint {
+        /// Target video bitrate in kbit/s
+        target_kbps: u32,
+        /// Maximum frame rate
+        max_fps: u8,
+    },
 }
 
 /// Data payload wrapper with channel information
@@ -194,12 +219,15 @@ mod heapless_serde {
         let slice: &[u8] = Deserialize::deserialize(deserializer)?;
         let mut vec = Vec::new();
         vec.extend_from_slice(slice)
-            .map_err(serde::de::Error::custom)?;
+            .map_err(|_| serde::de::Error::custom("payload too large"))?;
         Ok(vec)
     }
 }
 
 /// Complete message enum for the bridge protocol
+///
+/// Payloads are stored inline, with no heap, so `Data` dominates the size.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Message {
@@ -220,7 +248,7 @@ pub enum Message {
 impl Message {
     /// Serialize the message to a buffer using postcard
     pub fn serialize<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], postcard::Error> {
-        postcard::to_slice(self, buffer)
+        postcard::to_slice(self, buffer).map(|bytes| &*bytes)
     }
 
     /// Deserialize a message from bytes
@@ -277,7 +305,8 @@ impl FrameBuilder {
 
         // Serialize message to temp buffer (skip header space)
         let payload_start = 11; // magic(4) + header(6) + type(1)
-        let payload_buf = &mut buffer[payload_start..buffer.len() - 2];
+        let payload_end = buffer.len() - 2;
+        let payload_buf = &mut buffer[payload_start..payload_end];
         
         let payload = msg.serialize(payload_buf)
             .map_err(|_| FrameError::SerializationError)?;
@@ -309,8 +338,55 @@ impl FrameBuilder {
         Ok(crc_data_len + 2)
     }
 
+    /// Build a frame around an already-encoded payload
+    ///
+    /// Returns the number of bytes written to the buffer
+    pub fn build_raw_frame(
+        &mut self,
+        msg_type: MessageType,
+        channel: u8,
+        flags: u8,
+        payload: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize, FrameError> {
+        let frame_len = payload.len() + FRAME_OVERHEAD;
+        if payload.len() > u16::MAX as usize || buffer.len() < frame_len {
+            return Err(FrameError::BufferTooSmall);
+        }
+
+        let mut header = Header::new(self.next_sequence(), payload.len() as u16, channel);
+        header.flags = flags;
+
+        buffer[0..4].copy_from_slice(&FRAME_MAGIC);
+        buffer[4..6].copy_from_slice(&header.sequence.to_le_bytes());
+        buffer[6..8].copy_from_slice(&header.payload_len.to_le_bytes());
+        buffer[8] = header.channel;
+        buffer[9] = header.flags;
+        buffer[10] = msg_type as u8;
+        buffer[11..11 + payload.len()].copy_from_slice(payload);
+
+        let crc = crc16(&buffer[..frame_len - 2]);
+        buffer[frame_len - 2..frame_len].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(frame_len)
+    }
+
     /// Parse a frame from bytes
     pub fn parse_frame(data: &[u8]) -> Result<(Header, Message), FrameError> {
+        let raw = Self::parse_raw_frame(data)?;
+        if raw.header.flags & FLAG_BATCH != 0 {
+            return Err(FrameError::UnexpectedBatch);
+        }
+
+        // Parse message
+        let message = Message::deserialize(raw.payload)
+            .map_err(|_| FrameError::DeserializationError)?;
+
+        Ok((raw.header, message))
+    }
+
+    /// Validate framing and CRC without decoding the payload
+    pub fn parse_raw_frame(data: &[u8]) -> Result<RawFrame<'_>, FrameError> {
         if data.len() < 13 {
             return Err(FrameError::TooShort);
         }
@@ -349,12 +425,29 @@ impl FrameBuilder {
             return Err(FrameError::CrcMismatch);
         }
 
-        // Parse message
-        let payload_data = &data[11..11 + payload_len];
-        let message = Message::deserialize(payload_data)
-            .map_err(|_| FrameError::DeserializationError)?;
+        Ok(RawFrame {
+            header,
+            msg_type: data[10],
+            payload: &data[11..11 + payload_len],
+        })
+    }
+}
+
+/// A CRC-checked frame whose payload has not been decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFrame<'a> {
+    /// Frame header
+    pub header: Header,
+    /// Raw message type byte (see [`MessageType`])
+    pub msg_type: u8,
+    /// Encoded payload
+    pub payload: &'a [u8],
+}
 
-        Ok((header, message))
+impl RawFrame<'_> {
+    /// Total length of the frame on the wire
+    pub fn frame_len(&self) -> usize {
+        self.payload.len() + FRAME_OVERHEAD
     }
 }
 
@@ -380,10 +473,15 @@ pub enum FrameError {
     SerializationError,
     /// Deserialization failed
     DeserializationError,
+    /// Batched frame passed to `parse_frame` (use `batch::parse_frames`)
+    UnexpectedBatch,
 }
 
 /// Simple CRC-16-CCITT implementation
-fn crc16(data: &[u8]) -> u16 {
+///
+/// Frames carry the CRC of everything from the magic through the payload,
+/// little-endian, in their last two bytes.
+pub fn crc16(data: &[u8]) -> u16 {
     let mut crc: u16 = 0xFFFF;
     for byte in data {
         crc ^= (*byte as u16) << 8;
@@ -446,6 +544,37 @@ mod tests {
         assert!(matches!(result, Err(FrameError::CrcMismatch)));
     }
 
+    #[test]
+    fn test_window_update_roundtrip() {
+        let mut builder = FrameBuilder::new();
+        let msg = Message::Control(ControlMessage::WindowUpdate {
+            channel: 3,
+            credits: 28672,
+        });
+
+        let mut buffer = [0u8; 64];
+        let len = builder.build_frame(&msg, 3, &mut buffer).unwrap();
+
+        let (_, parsed_msg) = FrameBuilder::parse_frame(&buffer[..len]).unwrap();
+        assert_eq!(msg, parsed_msg);
+    }
+
+    #[test]
+    fn test_video_control_roundtrip() {
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+
+        for ctrl in [
+            ControlMessage::RequestKeyframe,
+            ControlMessage::BitrateHint { target_kbps: 4500, max_fps: 30 },
+        ] {
+            let msg = Message::Control(ctrl);
+            let len = builder.build_frame(&msg, 0, &mut buffer).unwrap();
+            let (_, parsed_msg) = FrameBuilder::parse_frame(&buffer[..len]).unwrap();
+            assert_eq!(msg, parsed_msg);
+        }
+    }
+
     #[test]
     fn test_message_type_conversion() {
         assert_eq!(MessageType::try_from(0x01), Ok(MessageType::Control));
@@ -0,0 +1,473 @@
+//! # Head-Unit Quirks
+//!
+//! Head units differ in how they react to the accessory handshake and to
+//! bulk traffic. A [`QuirksTable`] maps a head unit, identified by its USB
+//! vendor/product IDs and the manufacturer/product strings it reports, to
+//! [`Quirks`] that the AOA handshake and the USB writer honor.
+//!
+//! ## Config Format
+//!
+//! One entry per line, `#` starts a comment. Match keys select head units
+//! (all given keys must match, strings exactly); the rest are quirks:
+//!
+//! ```text
+//! # Slow to accept the identification strings
+//! vid=0x1234 pid=0x5678 request_delay_ms=20 start_delay_ms=500
+//! manufacturer="Example Audio" zlp max_transfer=4096
+//! ```
+//!
+//! | Key                        | Meaning                                      |
+//! |----------------------------|----------------------------------------------|
+//! | `vid`, `pid`               | USB vendor/product ID to match               |
+//! | `manufacturer`, `product`  | Reported USB strings to match                |
+//! | `request_delay_ms`         | Pause after each handshake request           |
+//! | `start_delay_ms`           | Extra pause before AOA START                 |
+//! | `reenumeration_timeout_ms` | Wait this long for accessory mode            |
+//! | `zlp`                      | Always end packet-aligned writes with a ZLP  |
+//! | `max_transfer`             | Largest single bulk write, at least 1 byte   |
+//!
+//! The first matching entry wins, so add specific entries before general
+//! ones. No entries are built in; they are added from configuration as head
+//! units are verified.
+
+use heapless::{String, Vec};
+
+use crate::aoa::UsbDeviceInfo;
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::traits::{EndpointWriter, ForwarderResult};
+
+/// Capacity of a match string
+pub const QUIRK_STRING_CAPACITY: usize = 32;
+
+/// A head unit as seen on the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadUnit<'a> {
+    /// Vendor and product ID
+    pub device: UsbDeviceInfo,
+    /// Manufacturer string descriptor (empty if none)
+    pub manufacturer: &'a str,
+    /// Product string descriptor (empty if none)
+    pub product: &'a str,
+}
+
+/// Behavior adjustments for one head unit (default: none)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Quirks {
+    /// Pause after each handshake request (ms)
+    pub request_delay_ms: u32,
+    /// Extra pause before AOA START (ms)
+    pub start_delay_ms: u32,
+    /// Override for the re-enumeration timeout (ms)
+    pub reenumeration_timeout_ms: Option<u32>,
+    /// End every packet-aligned bulk write with a zero-length packet
+    pub force_zlp: bool,
+    /// Largest single bulk write (bytes)
+    pub max_transfer_size: Option<usize>,
+}
+
+/// Which head units an entry applies to
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct QuirkMatch {
+    /// USB vendor ID
+    pub vendor_id: Option<u16>,
+    /// USB product ID
+    pub product_id: Option<u16>,
+    /// Manufacturer string
+    pub manufacturer: Option<String<QUIRK_STRING_CAPACITY>>,
+    /// Product string
+    pub product: Option<String<QUIRK_STRING_CAPACITY>>,
+}
+
+impl QuirkMatch {
+    /// Check if every given key matches `unit`
+    pub fn matches(&self, unit: &HeadUnit<'_>) -> bool {
+        self.vendor_id.is_none_or(|v| v == unit.device.vendor_id)
+            && self.product_id.is_none_or(|p| p == unit.device.product_id)
+            && self
+                .manufacturer
+                .as_ref()
+                .is_none_or(|m| m == unit.manufacturer)
+            && self.product.as_ref().is_none_or(|p| p == unit.product)
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == QuirkMatch::default()
+    }
+}
+
+/// Errors in a quirks entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum QuirkError {
+    /// Key not in the table above
+    UnknownKey,
+    /// Value missing, not a number, or out of range
+    InvalidValue,
+    /// Match string longer than [`QUIRK_STRING_CAPACITY`]
+    ValueTooLong,
+    /// Quoted value without a closing quote
+    UnterminatedQuote,
+    /// Entry would match every head unit
+    NoMatchKeys,
+    /// Table capacity reached
+    TableFull,
+}
+
+/// Error in a quirks config, with its 1-based line number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QuirkConfigError {
+    /// Line of the bad entry
+    pub line: usize,
+    /// What was wrong
+    pub error: QuirkError,
+}
+
+/// One table entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuirkEntry {
+    /// Head units it applies to
+    pub matcher: QuirkMatch,
+    /// What to adjust for them
+    pub quirks: Quirks,
+}
+
+impl QuirkEntry {
+    /// Parse a config line (see the module docs)
+    pub fn parse(line: &str) -> Result<Self, QuirkError> {
+        let mut entry = QuirkEntry {
+            matcher: QuirkMatch::default(),
+            quirks: Quirks::default(),
+        };
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let (key, value, tail) = next_pair(rest)?;
+            rest = tail.trim_start();
+            match (key, value) {
+                ("zlp", None) => entry.quirks.force_zlp = true,
+                ("vid", Some(v)) => entry.matcher.vendor_id = Some(parse_num(v)?),
+                ("pid", Some(v)) => entry.matcher.product_id = Some(parse_num(v)?),
+                ("manufacturer", Some(v)) => entry.matcher.manufacturer = Some(to_string(v)?),
+                ("product", Some(v)) => entry.matcher.product = Some(to_string(v)?),
+                ("request_delay_ms", Some(v)) => entry.quirks.request_delay_ms = parse_num(v)?,
+                ("start_delay_ms", Some(v)) => entry.quirks.start_delay_ms = parse_num(v)?,
+                ("reenumeration_timeout_ms", Some(v)) => {
+                    entry.quirks.reenumeration_timeout_ms = Some(parse_num(v)?)
+                }
+                ("max_transfer", Some(v)) => {
+                    // A zero cap would make every write take nothing, forever
+                    match parse_num::<u32>(v)? {
+                        0 => return Err(QuirkError::InvalidValue),
+                        n => entry.quirks.max_transfer_size = Some(n as usize),
+                    }
+                }
+                ("zlp" | "vid" | "pid" | "manufacturer" | "product", _)
+                | ("request_delay_ms" | "start_delay_ms", None)
+                | ("reenumeration_timeout_ms" | "max_transfer", None) => {
+                    return Err(QuirkError::InvalidValue)
+                }
+                _ => return Err(QuirkError::UnknownKey),
+            }
+        }
+        if entry.matcher.is_empty() {
+            return Err(QuirkError::NoMatchKeys);
+        }
+        Ok(entry)
+    }
+}
+
+/// Split off `key`, `key=value` or `key="quoted value"`
+fn next_pair(text: &str) -> Result<(&str, Option<&str>, &str), QuirkError> {
+    let end = text.find(|c: char| c == '=' || c.is_whitespace());
+    let Some(end) = end.filter(|&i| text.as_bytes()[i] == b'=') else {
+        let end = end.unwrap_or(text.len());
+        return Ok((&text[..end], None, &text[end..]));
+    };
+    let key = &text[..end];
+    let value = &text[end + 1..];
+    if let Some(quoted) = value.strip_prefix('"') {
+        let close = quoted.find('"').ok_or(QuirkError::UnterminatedQuote)?;
+        return Ok((key, Some(&quoted[..close]), &quoted[close + 1..]));
+    }
+    let close = value.find(char::is_whitespace).unwrap_or(value.len());
+    Ok((key, Some(&value[..close]), &value[close..]))
+}
+
+fn parse_num<T: TryFrom<u32>>(text: &str) -> Result<T, QuirkError> {
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    value
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or(QuirkError::InvalidValue)
+}
+
+fn to_string(text: &str) -> Result<String<QUIRK_STRING_CAPACITY>, QuirkError> {
+    let mut string = String::new();
+    string
+        .push_str(text)
+        .map_err(|_| QuirkError::ValueTooLong)?;
+    Ok(string)
+}
+
+/// Quirks database holding up to `N` entries
+#[derive(Debug, Clone, Default)]
+pub struct QuirksTable<const N: usize> {
+    entries: Vec<QuirkEntry, N>,
+}
+
+impl<const N: usize> QuirksTable<N> {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Append an entry; earlier entries take precedence
+    pub fn add(&mut self, entry: QuirkEntry) -> Result<(), QuirkError> {
+        self.entries.push(entry).map_err(|_| QuirkError::TableFull)
+    }
+
+    /// Append every entry in a config text; returns how many were added
+    pub fn load(&mut self, text: &str) -> Result<usize, QuirkConfigError> {
+        let mut added = 0;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            QuirkEntry::parse(line)
+                .and_then(|entry| self.add(entry))
+                .map_err(|error| QuirkConfigError {
+                    line: index + 1,
+                    error,
+                })?;
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// Quirks for `unit` from the first matching entry, or none
+    pub fn lookup(&self, unit: &HeadUnit<'_>) -> Quirks {
+        self.entries
+            .iter()
+            .find(|entry| entry.matcher.matches(unit))
+            .map(|entry| entry.quirks)
+            .unwrap_or_default()
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the table has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// `EndpointWriter` wrapper applying a head unit's bulk-transfer quirks
+///
+/// Caps each write at `max_transfer_size` and, with `force_zlp`, follows
+/// every write that ends on a packet boundary with a zero-length packet.
+pub struct QuirkWriter<W> {
+    inner: W,
+    quirks: Quirks,
+    packet_size: usize,
+}
+
+impl<W: EndpointWriter> QuirkWriter<W> {
+    /// Wrap a USB writer whose bulk endpoint uses `packet_size`-byte packets
+    pub fn new(inner: W, quirks: Quirks, packet_size: usize) -> Self {
+        Self {
+            inner,
+            quirks,
+            packet_size,
+        }
+    }
+
+    /// Unwrap the writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn cap(&self, len: usize) -> usize {
+        self.quirks
+            .max_transfer_size
+            .map_or(len, |max| len.min(max))
+    }
+
+    async fn finish(&mut self, written: usize) -> ForwarderResult<usize> {
+        if self.quirks.force_zlp && written > 0 && written.is_multiple_of(self.packet_size.max(1)) {
+            self.inner.write_zlp().await?;
+        }
+        Ok(written)
+    }
+}
+
+impl<W: EndpointWriter> EndpointWriter for QuirkWriter<W> {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        let written = self.inner.write_from_buffer(buffer, self.cap(len)).await?;
+        self.finish(written).await
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        let len = self.cap(data.len());
+        let written = self.inner.write_from_slice(&data[..len]).await?;
+        self.finish(written).await
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        let len = self.cap(slice.len());
+        let written = self.inner.write_vectored(slice.prefix(len)).await?;
+        self.finish(written).await
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        self.inner.flush().await
+    }
+
+    async fn write_zlp(&mut self) -> ForwarderResult<()> {
+        self.inner.write_zlp().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(vendor_id: u16, product_id: u16, manufacturer: &str) -> HeadUnit<'_> {
+        HeadUnit {
+            device: UsbDeviceInfo {
+                vendor_id,
+                product_id,
+            },
+            manufacturer,
+            product: "",
+        }
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        let entry = QuirkEntry::parse(
+            r#"vid=0x1234 manufacturer="Example Audio" start_delay_ms=500 zlp max_transfer=4096"#,
+        )
+        .unwrap();
+        assert_eq!(entry.matcher.vendor_id, Some(0x1234));
+        assert_eq!(entry.matcher.manufacturer.as_deref(), Some("Example Audio"));
+        assert_eq!(
+            entry.quirks,
+            Quirks {
+                start_delay_ms: 500,
+                force_zlp: true,
+                max_transfer_size: Some(4096),
+                ..Quirks::default()
+            }
+        );
+
+        assert_eq!(QuirkEntry::parse("zlp"), Err(QuirkError::NoMatchKeys));
+        assert_eq!(
+            QuirkEntry::parse("vid=0x1234 fast"),
+            Err(QuirkError::UnknownKey)
+        );
+        assert_eq!(
+            QuirkEntry::parse("vid=banana"),
+            Err(QuirkError::InvalidValue)
+        );
+        assert_eq!(
+            QuirkEntry::parse("vid=0x1234 max_transfer=0"),
+            Err(QuirkError::InvalidValue)
+        );
+        assert_eq!(
+            QuirkEntry::parse(r#"product="open"#),
+            Err(QuirkError::UnterminatedQuote)
+        );
+    }
+
+    #[test]
+    fn test_first_match_wins() {
+        let mut table = QuirksTable::<4>::new();
+        let added = table
+            .load(
+                "# specific model first\n\
+                 vid=0x1234 pid=0x0001 start_delay_ms=900\n\
+                 vid=0x1234 request_delay_ms=10\n",
+            )
+            .unwrap();
+        assert_eq!(added, 2);
+
+        assert_eq!(table.lookup(&unit(0x1234, 0x0001, "")).start_delay_ms, 900);
+        assert_eq!(table.lookup(&unit(0x1234, 0x0002, "")).request_delay_ms, 10);
+        assert_eq!(table.lookup(&unit(0x9999, 0x0001, "")), Quirks::default());
+
+        assert_eq!(
+            table.load("\nvid=1 warp=9"),
+            Err(QuirkConfigError {
+                line: 2,
+                error: QuirkError::UnknownKey
+            })
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        writes: heapless::Vec<usize, 8>,
+    }
+
+    impl EndpointWriter for Recorder {
+        async fn write_from_buffer(
+            &mut self,
+            _buffer: &ZeroCopyBuffer,
+            len: usize,
+        ) -> ForwarderResult<usize> {
+            self.writes.push(len).unwrap();
+            Ok(len)
+        }
+
+        async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+            self.writes.push(data.len()).unwrap();
+            Ok(data.len())
+        }
+
+        async fn flush(&mut self) -> ForwarderResult<()> {
+            Ok(())
+        }
+
+        async fn write_zlp(&mut self) -> ForwarderResult<()> {
+            self.writes.push(0).unwrap();
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quirk_writer_caps_and_terminates() {
+        let quirks = Quirks {
+            force_zlp: true,
+            max_transfer_size: Some(128),
+            ..Quirks::default()
+        };
+        let mut writer = QuirkWriter::new(Recorder::default(), quirks, 64);
+
+        assert_eq!(writer.write_from_slice(&[0; 300]).await, Ok(128));
+        assert_eq!(writer.write_from_slice(&[0; 100]).await, Ok(100));
+        // 128 is packet-aligned and gets a ZLP; 100 ends in a short packet
+        assert_eq!(writer.into_inner().writes, [128, 0, 100]);
+    }
+}
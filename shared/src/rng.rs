@@ -0,0 +1,94 @@
+//! # Deterministic Random Numbers
+//!
+//! A small seeded generator (xorshift64*) for test tooling: fault injection,
+//! impairment and simulation. The same seed always yields the same sequence,
+//! so a failure seen once can be replayed exactly. Not suitable for anything
+//! security-related.
+
+/// Seeded xorshift64* generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator from a seed (any value, including 0)
+    pub const fn new(seed: u64) -> Self {
+        // Scramble so nearby seeds diverge quickly; xorshift state must be non-zero
+        let state = (seed ^ 0x9E37_79B9_7F4A_7C15).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        Self {
+            state: if state == 0 { 0x2545_F491_4F6C_DD1D } else { state },
+        }
+    }
+
+    /// Next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Next 32 random bits
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform value in `0..bound` (0 if `bound` is 0)
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Uniform value in `0.0..1.0`
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `true` with probability `p` (clamped to `0.0..=1.0`)
+    pub fn chance(&mut self, p: f64) -> bool {
+        if p <= 0.0 {
+            return false;
+        }
+        p >= 1.0 || self.unit() < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let seq: [u64; 4] = core::array::from_fn(|_| a.next_u64());
+        assert_eq!(seq, core::array::from_fn(|_| b.next_u64()));
+        assert_ne!(seq[0], c.next_u64());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = Rng::new(0);
+        for _ in 0..1000 {
+            assert!(rng.below(10) < 10);
+            let u = rng.unit();
+            assert!((0.0..1.0).contains(&u));
+        }
+        assert_eq!(rng.below(0), 0);
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+    }
+
+    #[test]
+    fn test_chance_is_roughly_fair() {
+        let mut rng = Rng::new(7);
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2_200..2_800).contains(&hits), "hits {hits}");
+    }
+}
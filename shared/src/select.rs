@@ -0,0 +1,91 @@
+//! # Future Combinators
+//!
+//! Minimal `select` and `yield_now` for running several futures inside one
+//! task. They only use `core`, so they work the same under embassy, tokio
+//! or any other executor, and keep executor-specific crates out of the
+//! shared library.
+//!
+//! ```ignore
+//! match select(usb.read(), wifi.read()).await {
+//!     Either::First(from_usb) => ...,
+//!     Either::Second(from_wifi) => ...,
+//! }
+//! ```
+
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+
+/// Output of [`select`]: which future finished first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Either<A, B> {
+    /// The first future finished
+    First(A),
+    /// The second future finished
+    Second(B),
+}
+
+/// Run two futures concurrently until either finishes; the other is dropped
+///
+/// The first future is polled first, so it wins when both are ready.
+pub async fn select<A: Future, B: Future>(a: A, b: B) -> Either<A::Output, B::Output> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = a.as_mut().poll(cx) {
+            return Poll::Ready(Either::First(output));
+        }
+        if let Poll::Ready(output) = b.as_mut().poll(cx) {
+            return Poll::Ready(Either::Second(output));
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Give other futures in the task (and other tasks) a turn
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::pending;
+
+    #[tokio::test]
+    async fn test_select_returns_first_finished() {
+        let result = select(pending::<()>(), async { 7 }).await;
+        assert_eq!(result, Either::Second(7));
+
+        let result = select(async { 'a' }, async { 'b' }).await;
+        assert_eq!(result, Either::First('a'));
+    }
+
+    #[tokio::test]
+    async fn test_yield_lets_other_future_run() {
+        let flag = core::cell::Cell::new(false);
+        let spinner = async {
+            // Never finishes without the other side's help
+            while !flag.get() {
+                yield_now().await;
+            }
+            "spinner"
+        };
+        let setter = async {
+            flag.set(true);
+            pending::<&str>().await
+        };
+        assert_eq!(select(spinner, setter).await, Either::First("spinner"));
+    }
+}
@@ -0,0 +1,287 @@
+//! # Forwarding Statistics
+//!
+//! Per-direction counters that `ForwardPath::forward` keeps up to date, so
+//! `DataForwarder` implementations get accounting without extra code, and
+//! a fixed-bucket latency histogram for p50/p99 figures.
+//!
+//! ## Latency Buckets
+//!
+//! ```text
+//! bucket   0     1     2      3      4           23
+//! range   0µs   1µs  2–3µs  4–7µs  8–15µs  …  ≥ 4.2s
+//! ```
+//!
+//! Buckets double in width, so 24 `u32` counters span microseconds to
+//! seconds with no allocation. Percentiles report the upper edge of the
+//! bucket they fall in (capped at the largest sample), which is at most
+//! twice the real value.
+//!
+//! Latency is the time from a read completing to the write of that chunk
+//! completing, which is the delay the bridge itself adds. It is only
+//! recorded when a microsecond time source is set with
+//! [`DirectionStats::with_timer`].
+
+use crate::traits::{ForwarderError, ForwarderResult};
+
+/// Number of latency buckets
+pub const LATENCY_BUCKETS: usize = 24;
+
+/// Histogram of per-chunk latencies in power-of-two microsecond buckets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LatencyHistogram {
+    buckets: [u32; LATENCY_BUCKETS],
+    count: u32,
+    sum_us: u64,
+    max_us: u32,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram
+    pub const fn new() -> Self {
+        Self {
+            buckets: [0; LATENCY_BUCKETS],
+            count: 0,
+            sum_us: 0,
+            max_us: 0,
+        }
+    }
+
+    /// Add one sample
+    pub fn record(&mut self, latency_us: u32) {
+        let bucket = (u32::BITS - latency_us.leading_zeros()) as usize;
+        let slot = &mut self.buckets[bucket.min(LATENCY_BUCKETS - 1)];
+        *slot = slot.saturating_add(1);
+        self.count = self.count.saturating_add(1);
+        self.sum_us = self.sum_us.saturating_add(u64::from(latency_us));
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    /// Number of samples
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Samples per bucket (see the module docs for the ranges)
+    pub fn buckets(&self) -> &[u32; LATENCY_BUCKETS] {
+        &self.buckets
+    }
+
+    /// Largest sample (µs)
+    pub fn max_us(&self) -> u32 {
+        self.max_us
+    }
+
+    /// Mean latency (µs), `None` if empty
+    pub fn mean_us(&self) -> Option<u32> {
+        self.sum_us
+            .checked_div(u64::from(self.count))
+            .map(|mean| mean as u32)
+    }
+
+    /// Upper bound for the `percent`th percentile (µs), `None` if empty
+    pub fn percentile_us(&self, percent: u8) -> Option<u32> {
+        if self.count == 0 {
+            return None;
+        }
+        // Rank of the sample, rounding up so p100 is the last one
+        let rank = (u64::from(self.count) * u64::from(percent.min(100)))
+            .div_ceil(100)
+            .max(1);
+        let mut seen = 0u64;
+        for (bucket, &samples) in self.buckets.iter().enumerate() {
+            seen += u64::from(samples);
+            if seen >= rank {
+                let upper = if bucket == 0 { 0 } else { (1u32 << bucket) - 1 };
+                return Some(upper.min(self.max_us));
+            }
+        }
+        Some(self.max_us)
+    }
+
+    /// Median latency bound (µs)
+    pub fn p50_us(&self) -> Option<u32> {
+        self.percentile_us(50)
+    }
+
+    /// 99th percentile latency bound (µs)
+    pub fn p99_us(&self) -> Option<u32> {
+        self.percentile_us(99)
+    }
+
+    /// Add another histogram's samples
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, samples) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket = bucket.saturating_add(samples);
+        }
+        self.count = self.count.saturating_add(other.count);
+        self.sum_us = self.sum_us.saturating_add(other.sum_us);
+        self.max_us = self.max_us.max(other.max_us);
+    }
+}
+
+/// Counters for one forwarding direction
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DirectionStats {
+    /// Bytes written to the destination
+    pub bytes: u64,
+    /// Reads that returned data
+    pub reads: u32,
+    /// Reads that failed with `BufferOverflow` (data loss)
+    pub overflows: u32,
+    /// Bytes waiting in the buffer after the last iteration
+    pub buffer_used: usize,
+    /// Highest `buffer_used` seen
+    pub buffer_peak: usize,
+    /// Read-to-write latency per chunk
+    pub latency: LatencyHistogram,
+    now_us: Option<fn() -> u64>,
+}
+
+impl DirectionStats {
+    /// Create zeroed counters without latency recording
+    pub const fn new() -> Self {
+        Self {
+            bytes: 0,
+            reads: 0,
+            overflows: 0,
+            buffer_used: 0,
+            buffer_peak: 0,
+            latency: LatencyHistogram::new(),
+            now_us: None,
+        }
+    }
+
+    /// Record latency using `now_us`, a monotonic microsecond clock
+    pub fn with_timer(mut self, now_us: fn() -> u64) -> Self {
+        self.now_us = Some(now_us);
+        self
+    }
+
+    /// Current time, if a timer is set
+    pub(crate) fn now_us(&self) -> Option<u64> {
+        self.now_us.map(|now| now())
+    }
+
+    /// Account for one read, successful or not
+    pub(crate) fn on_read(&mut self, result: &ForwarderResult<usize>) {
+        match result {
+            Ok(0) => {}
+            Ok(_) => self.reads = self.reads.saturating_add(1),
+            Err(ForwarderError::BufferOverflow) => {
+                self.overflows = self.overflows.saturating_add(1)
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Account for a completed write of a chunk read at `read_at`
+    pub(crate) fn on_write(&mut self, written: usize, read_at: Option<u64>) {
+        self.bytes = self.bytes.saturating_add(written as u64);
+        if let (Some(start), Some(end)) = (read_at, self.now_us()) {
+            let latency = end.saturating_sub(start).min(u64::from(u32::MAX));
+            self.latency.record(latency as u32);
+        }
+    }
+
+    /// Note the buffer level after an iteration
+    pub(crate) fn on_buffer_level(&mut self, used: usize) {
+        self.buffer_used = used;
+        self.buffer_peak = self.buffer_peak.max(used);
+    }
+
+    /// Clear the counters, keeping the timer
+    pub fn reset(&mut self) {
+        *self = Self {
+            now_us: self.now_us,
+            ..Self::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.p50_us(), None);
+
+        // 98 fast chunks, 2 slow ones
+        for _ in 0..98 {
+            histogram.record(100);
+        }
+        histogram.record(5_000);
+        histogram.record(9_000);
+
+        assert_eq!(histogram.count(), 100);
+        // 100µs falls in 64–127
+        assert_eq!(histogram.p50_us(), Some(127));
+        // 5000µs falls in 4096–8191
+        assert_eq!(histogram.p99_us(), Some(8_191));
+        // Capped at the largest sample
+        assert_eq!(histogram.percentile_us(100), Some(9_000));
+        assert_eq!(histogram.mean_us(), Some(238));
+    }
+
+    #[test]
+    fn test_extremes_and_merge() {
+        let mut a = LatencyHistogram::new();
+        a.record(0);
+        a.record(u32::MAX);
+        assert_eq!(a.buckets()[0], 1);
+        assert_eq!(a.buckets()[LATENCY_BUCKETS - 1], 1);
+        assert_eq!(a.percentile_us(50), Some(0));
+
+        let mut b = LatencyHistogram::new();
+        b.record(3);
+        b.merge(&a);
+        assert_eq!(b.count(), 3);
+        assert_eq!(b.max_us(), u32::MAX);
+    }
+
+    #[test]
+    fn test_direction_stats_accounting() {
+        fn clock() -> u64 {
+            42
+        }
+        let mut stats = DirectionStats::new().with_timer(clock);
+        stats.on_read(&Ok(10));
+        stats.on_read(&Ok(0));
+        stats.on_read(&Err(ForwarderError::BufferOverflow));
+        stats.on_read(&Err(ForwarderError::IoError));
+        stats.on_write(10, Some(40));
+        stats.on_buffer_level(6);
+        stats.on_buffer_level(2);
+
+        assert_eq!((stats.reads, stats.overflows, stats.bytes), (1, 1, 10));
+        assert_eq!((stats.buffer_used, stats.buffer_peak), (2, 6));
+        assert_eq!(stats.latency.max_us(), 2);
+
+        stats.reset();
+        assert_eq!(stats.bytes, 0);
+        assert_eq!(stats.now_us(), Some(42));
+    }
+
+    #[test]
+    fn test_counters_saturate() {
+        let mut stats = DirectionStats::new();
+        stats.reads = u32::MAX;
+        stats.overflows = u32::MAX;
+        stats.bytes = u64::MAX;
+        stats.latency.buckets[0] = u32::MAX;
+        stats.on_read(&Ok(1));
+        stats.on_read(&Err(ForwarderError::BufferOverflow));
+        stats.on_write(1, None);
+        stats.latency.record(0);
+        assert_eq!(
+            (stats.reads, stats.overflows, stats.bytes),
+            (u32::MAX, u32::MAX, u64::MAX)
+        );
+        assert_eq!(stats.latency.buckets()[0], u32::MAX);
+
+        let combined = crate::traits::ForwardingStats::from_directions(&stats, &stats);
+        assert_eq!(combined.overflows, u32::MAX);
+    }
+}
@@ -0,0 +1,271 @@
+//! # tokio TCP Endpoints
+//!
+//! `EndpointReader`/`EndpointWriter` over the halves of a tokio `TcpStream`,
+//! so a `DataForwarder` can run on real sockets. Enabled by the `tokio`
+//! feature.
+//!
+//! ```text
+//! TcpStream ──split()──► TcpReader ── read_into_buffer ──► ZeroCopyBuffer
+//!                   └──► TcpWriter ◄─ write_from_buffer ── (both halves of a wrap)
+//! ```
+//!
+//! Reads wait until the socket has data and land directly in the buffer's
+//! writable region; writes send both halves of a wrapped region in one
+//! vectored call. Either may return fewer bytes than asked.
+//!
+//! EOF, reset, abort and broken pipe all mark the connection as gone: the
+//! failing call returns `Disconnected` and `is_connected` turns `false` on
+//! both halves.
+
+use std::io::{self, IoSlice};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::traits::{EndpointReader, EndpointWriter, ForwarderError, ForwarderResult};
+use crate::MTU;
+
+/// Split a stream into a reader and writer sharing connection state
+pub fn split(stream: TcpStream) -> (TcpReader, TcpWriter) {
+    let (read, write) = stream.into_split();
+    let connected = Arc::new(AtomicBool::new(true));
+    (
+        TcpReader {
+            inner: read,
+            connected: Arc::clone(&connected),
+            max_packet_size: MTU,
+        },
+        TcpWriter {
+            inner: write,
+            connected,
+        },
+    )
+}
+
+/// Map a socket error, noting when it means the peer is gone
+fn map_error(error: io::Error, connected: &AtomicBool, timeout: ForwarderError) -> ForwarderError {
+    match error.kind() {
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::UnexpectedEof => {
+            connected.store(false, Ordering::Release);
+            ForwarderError::Disconnected
+        }
+        io::ErrorKind::TimedOut => timeout,
+        _ => ForwarderError::IoError,
+    }
+}
+
+/// Reading half of a TCP connection
+#[derive(Debug)]
+pub struct TcpReader {
+    inner: OwnedReadHalf,
+    connected: Arc<AtomicBool>,
+    max_packet_size: usize,
+}
+
+impl TcpReader {
+    /// Limit how many bytes a single read takes (default [`MTU`])
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// Unwrap the read half
+    pub fn into_inner(self) -> OwnedReadHalf {
+        self.inner
+    }
+
+    fn eof(&self) -> ForwarderError {
+        self.connected.store(false, Ordering::Release);
+        ForwarderError::Disconnected
+    }
+}
+
+impl EndpointReader for TcpReader {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        if buffer.is_full() {
+            return Ok(0);
+        }
+        loop {
+            self.inner
+                .readable()
+                .await
+                .map_err(|e| map_error(e, &self.connected, ForwarderError::ReadTimeout))?;
+
+            // Only the contiguous part: a short read is fine, the next call
+            // picks up at the start of the buffer
+            let region = buffer.writable_slice_mut(self.max_packet_size)?;
+            match self.inner.try_read(region.first) {
+                Ok(0) => return Err(self.eof()),
+                Ok(n) => {
+                    buffer.commit(n)?;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(map_error(e, &self.connected, ForwarderError::ReadTimeout)),
+            }
+        }
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        let len = buf.len().min(self.max_packet_size);
+        if len == 0 {
+            return Ok(0);
+        }
+        loop {
+            self.inner
+                .readable()
+                .await
+                .map_err(|e| map_error(e, &self.connected, ForwarderError::ReadTimeout))?;
+            match self.inner.try_read(&mut buf[..len]) {
+                Ok(0) => return Err(self.eof()),
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(map_error(e, &self.connected, ForwarderError::ReadTimeout)),
+            }
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+}
+
+/// Writing half of a TCP connection
+#[derive(Debug)]
+pub struct TcpWriter {
+    inner: OwnedWriteHalf,
+    connected: Arc<AtomicBool>,
+}
+
+impl TcpWriter {
+    /// Unwrap the write half
+    pub fn into_inner(self) -> OwnedWriteHalf {
+        self.inner
+    }
+
+    async fn write_bufs(&mut self, bufs: &[IoSlice<'_>]) -> ForwarderResult<usize> {
+        if bufs.iter().all(|b| b.is_empty()) {
+            return Ok(0);
+        }
+        loop {
+            self.inner
+                .writable()
+                .await
+                .map_err(|e| map_error(e, &self.connected, ForwarderError::WriteTimeout))?;
+            match self.inner.try_write_vectored(bufs) {
+                Ok(n) => return Ok(n),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(map_error(e, &self.connected, ForwarderError::WriteTimeout)),
+            }
+        }
+    }
+}
+
+impl EndpointWriter for TcpWriter {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        self.write_vectored(buffer.readable_split(len)).await
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        self.write_bufs(&[IoSlice::new(data)]).await
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        self.write_bufs(&[IoSlice::new(slice.first), IoSlice::new(slice.second)])
+            .await
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        // Writes go straight to the socket; nothing is held back here
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap());
+        let (client, accepted) = tokio::join!(client, listener.accept());
+        (client.unwrap(), accepted.unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_through_buffer() {
+        let (a, b) = connected_pair().await;
+        let (_, mut writer) = split(a);
+        let (mut reader, _) = split(b);
+
+        assert_eq!(writer.write_from_slice(b"hello socket").await, Ok(12));
+        let mut buffer = ZeroCopyBuffer::new();
+        let mut received = 0;
+        while received < 12 {
+            received += reader.read_into_buffer(&mut buffer).await.unwrap();
+        }
+        assert_eq!(buffer.readable_slice(12), Some(&b"hello socket"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_write_from_wrapped_buffer() {
+        let (a, b) = connected_pair().await;
+        let (_, mut writer) = split(a);
+        let (mut reader, _) = split(b);
+
+        // Move the indices so the next write wraps around the end
+        let mut buffer = ZeroCopyBuffer::new();
+        let filler = vec![0u8; buffer.capacity() - 4];
+        buffer.write(&filler).unwrap();
+        buffer.consume(filler.len()).unwrap();
+        buffer.write(b"wrapped!").unwrap();
+
+        let mut sent = 0;
+        while sent < 8 {
+            let n = writer.write_from_buffer(&buffer, 8 - sent).await.unwrap();
+            buffer.consume(n).unwrap();
+            sent += n;
+        }
+        let mut out = [0u8; 8];
+        let mut received = 0;
+        while received < 8 {
+            received += reader.read_into_slice(&mut out[received..]).await.unwrap();
+        }
+        assert_eq!(&out, b"wrapped!");
+    }
+
+    #[tokio::test]
+    async fn test_eof_marks_disconnected() {
+        let (a, b) = connected_pair().await;
+        let (mut reader, writer) = split(b);
+        drop(a);
+
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            reader.read_into_slice(&mut buf).await,
+            Err(ForwarderError::Disconnected)
+        );
+        assert!(!reader.is_connected());
+        assert!(!writer.is_connected());
+    }
+}
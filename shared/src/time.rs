@@ -0,0 +1,469 @@
+//! # Timeouts and Retries
+//!
+//! A runtime-agnostic [`Clock`] plus endpoint wrappers that apply the
+//! [`ForwarderConfig`] timeouts and retry policy to any reader or writer:
+//!
+//! ```ignore
+//! let config = ForwarderConfig::default();
+//! let usb_reader = TimedReader::new(usb_reader, clock, &config);
+//! let wifi_writer = TimedWriter::new(wifi_writer, clock, &config);
+//!
+//! // Or wrap all four endpoints of a forwarder at once
+//! let forwarder = EndpointForwarder::with_config(
+//!     usb_reader, usb_writer, wifi_reader, wifi_writer, clock, &config,
+//! );
+//! ```
+//!
+//! - An attempt that takes longer than `read_timeout_ms`/`write_timeout_ms`
+//!   is cancelled and fails with `ReadTimeout`/`WriteTimeout` (0 disables)
+//! - `UsbError` and `WifiError` are retried up to `max_retries` times,
+//!   waiting `retry_backoff_ms`, then twice that, and so on in between
+//! - Every other error, and timeouts, are returned as they are
+//!
+//! On a link that can sit idle, a read timeout fires whenever nothing
+//! arrives. `ForwardPath::pump` (and so `DataForwarder::run`) takes that as
+//! idleness and reads again; other callers should use a timeout of 0 or
+//! handle `ReadTimeout` the same way.
+//!
+//! ## Implementing `Clock`
+//!
+//! ```ignore
+//! #[derive(Clone, Copy)]
+//! struct EmbassyClock;
+//!
+//! impl Clock for EmbassyClock {
+//!     fn now_ms(&self) -> u64 {
+//!         embassy_time::Instant::now().as_millis()
+//!     }
+//!
+//!     async fn delay_ms(&self, ms: u32) {
+//!         embassy_time::Timer::after_millis(ms.into()).await
+//!     }
+//! }
+//! ```
+//!
+//! With the `tokio` feature, [`TokioClock`] is provided.
+
+use core::future::Future;
+
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::select::{select, Either};
+use crate::traits::{
+    EndpointReader, EndpointWriter, ForwarderConfig, ForwarderError, ForwarderResult,
+};
+
+/// Time source and timer of the running executor
+pub trait Clock {
+    /// Milliseconds since an arbitrary, fixed starting point
+    fn now_ms(&self) -> u64;
+
+    /// Wait for `ms` milliseconds
+    fn delay_ms(&self, ms: u32) -> impl Future<Output = ()>;
+}
+
+/// Run `future` for at most `ms` milliseconds (0: no limit)
+///
+/// Returns `None` if the time ran out; the future is dropped.
+pub async fn timeout<C: Clock, F: Future>(clock: &C, ms: u32, future: F) -> Option<F::Output> {
+    if ms == 0 {
+        return Some(future.await);
+    }
+    match select(future, clock.delay_ms(ms)).await {
+        Either::First(output) => Some(output),
+        Either::Second(()) => None,
+    }
+}
+
+/// Timeout and retry settings taken from a [`ForwarderConfig`]
+#[derive(Debug, Clone, Copy)]
+struct Policy {
+    timeout_ms: u32,
+    timeout_error: ForwarderError,
+    max_retries: u8,
+    backoff_ms: u32,
+}
+
+impl Policy {
+    /// Run one attempt under the timeout
+    async fn attempt<C: Clock, T>(
+        &self,
+        clock: &C,
+        future: impl Future<Output = ForwarderResult<T>>,
+    ) -> ForwarderResult<T> {
+        timeout(clock, self.timeout_ms, future)
+            .await
+            .unwrap_or(Err(self.timeout_error))
+    }
+
+    /// After a failed attempt, wait and report whether to try again
+    async fn retry<C: Clock>(&self, clock: &C, error: ForwarderError, attempt: &mut u8) -> bool {
+        if !error.is_transient() || *attempt >= self.max_retries {
+            return false;
+        }
+        let shift = u32::from(*attempt).min(16);
+        clock
+            .delay_ms(self.backoff_ms.saturating_mul(1 << shift))
+            .await;
+        *attempt += 1;
+        true
+    }
+}
+
+/// `EndpointReader` wrapper applying the read timeout and retry policy
+pub struct TimedReader<R, C> {
+    inner: R,
+    clock: C,
+    policy: Policy,
+}
+
+impl<R: EndpointReader, C: Clock> TimedReader<R, C> {
+    /// Wrap a reader
+    pub fn new(inner: R, clock: C, config: &ForwarderConfig) -> Self {
+        Self {
+            inner,
+            clock,
+            policy: Policy {
+                timeout_ms: config.read_timeout_ms,
+                timeout_error: ForwarderError::ReadTimeout,
+                max_retries: config.max_retries,
+                backoff_ms: config.retry_backoff_ms,
+            },
+        }
+    }
+
+    /// Unwrap the reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: EndpointReader, C: Clock> EndpointReader for TimedReader<R, C> {
+    async fn read_into_buffer(&mut self, buffer: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+        let mut attempt = 0;
+        loop {
+            let read = self.inner.read_into_buffer(buffer);
+            let result = self.policy.attempt(&self.clock, read).await;
+            if let Err(e) = result {
+                if self.policy.retry(&self.clock, e, &mut attempt).await {
+                    continue;
+                }
+            }
+            return result;
+        }
+    }
+
+    async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+        let mut attempt = 0;
+        loop {
+            let read = self.inner.read_into_slice(buf);
+            let result = self.policy.attempt(&self.clock, read).await;
+            if let Err(e) = result {
+                if self.policy.retry(&self.clock, e, &mut attempt).await {
+                    continue;
+                }
+            }
+            return result;
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn max_packet_size(&self) -> usize {
+        self.inner.max_packet_size()
+    }
+}
+
+/// `EndpointWriter` wrapper applying the write timeout and retry policy
+pub struct TimedWriter<W, C> {
+    inner: W,
+    clock: C,
+    policy: Policy,
+}
+
+impl<W: EndpointWriter, C: Clock> TimedWriter<W, C> {
+    /// Wrap a writer
+    pub fn new(inner: W, clock: C, config: &ForwarderConfig) -> Self {
+        Self {
+            inner,
+            clock,
+            policy: Policy {
+                timeout_ms: config.write_timeout_ms,
+                timeout_error: ForwarderError::WriteTimeout,
+                max_retries: config.max_retries,
+                backoff_ms: config.retry_backoff_ms,
+            },
+        }
+    }
+
+    /// Unwrap the writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: EndpointWriter, C: Clock> EndpointWriter for TimedWriter<W, C> {
+    async fn write_from_buffer(
+        &mut self,
+        buffer: &ZeroCopyBuffer,
+        len: usize,
+    ) -> ForwarderResult<usize> {
+        let mut attempt = 0;
+        loop {
+            let write = self.inner.write_from_buffer(buffer, len);
+            let result = self.policy.attempt(&self.clock, write).await;
+            if let Err(e) = result {
+                if self.policy.retry(&self.clock, e, &mut attempt).await {
+                    continue;
+                }
+            }
+            return result;
+        }
+    }
+
+    async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+        let mut attempt = 0;
+        loop {
+            let write = self.inner.write_from_slice(data);
+            let result = self.policy.attempt(&self.clock, write).await;
+            if let Err(e) = result {
+                if self.policy.retry(&self.clock, e, &mut attempt).await {
+                    continue;
+                }
+            }
+            return result;
+        }
+    }
+
+    async fn write_vectored(&mut self, slice: BufferSlice<'_>) -> ForwarderResult<usize> {
+        let mut attempt = 0;
+        loop {
+            let write = self.inner.write_vectored(slice);
+            let result = self.policy.attempt(&self.clock, write).await;
+            if let Err(e) = result {
+                if self.policy.retry(&self.clock, e, &mut attempt).await {
+                    continue;
+                }
+            }
+            return result;
+        }
+    }
+
+    async fn flush(&mut self) -> ForwarderResult<()> {
+        let flush = self.inner.flush();
+        self.policy.attempt(&self.clock, flush).await
+    }
+
+    async fn write_zlp(&mut self) -> ForwarderResult<()> {
+        self.inner.write_zlp().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+}
+
+/// Microseconds since the first call, for `DirectionStats::with_timer`
+#[cfg(feature = "std")]
+pub fn monotonic_us() -> u64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START
+        .get_or_init(std::time::Instant::now)
+        .elapsed()
+        .as_micros() as u64
+}
+
+/// [`Clock`] for the tokio runtime, counting from its creation
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy)]
+pub struct TokioClock {
+    start: tokio::time::Instant,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioClock {
+    /// Start a clock at 0 ms
+    pub fn new() -> Self {
+        Self {
+            start: tokio::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Clock for TokioClock {
+    fn now_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    async fn delay_ms(&self, ms: u32) {
+        tokio::time::sleep(core::time::Duration::from_millis(ms.into())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::{Cell, RefCell};
+    use core::future::pending;
+
+    /// Virtual time: delays finish at once and move the clock forward
+    #[derive(Default)]
+    struct TestClock {
+        now: Cell<u64>,
+        delays: RefCell<heapless::Vec<u32, 8>>,
+    }
+
+    impl Clock for &TestClock {
+        fn now_ms(&self) -> u64 {
+            self.now.get()
+        }
+
+        async fn delay_ms(&self, ms: u32) {
+            self.delays.borrow_mut().push(ms).unwrap();
+            self.now.set(self.now.get() + u64::from(ms));
+        }
+    }
+
+    /// Fails with `errors` in order, then reads/writes everything
+    struct Flaky {
+        errors: heapless::Vec<ForwarderError, 8>,
+        hang: bool,
+    }
+
+    impl Flaky {
+        async fn next(&mut self, len: usize) -> ForwarderResult<usize> {
+            if self.hang {
+                pending::<()>().await;
+            }
+            match self.errors.pop() {
+                Some(e) => Err(e),
+                None => Ok(len),
+            }
+        }
+    }
+
+    impl EndpointReader for Flaky {
+        async fn read_into_buffer(&mut self, _: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+            self.next(1).await
+        }
+
+        async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+            self.next(buf.len()).await
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+
+        fn max_packet_size(&self) -> usize {
+            64
+        }
+    }
+
+    impl EndpointWriter for Flaky {
+        async fn write_from_buffer(
+            &mut self,
+            _: &ZeroCopyBuffer,
+            len: usize,
+        ) -> ForwarderResult<usize> {
+            self.next(len).await
+        }
+
+        async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+            self.next(data.len()).await
+        }
+
+        async fn flush(&mut self) -> ForwarderResult<()> {
+            self.next(0).await.map(|_| ())
+        }
+
+        fn is_connected(&self) -> bool {
+            true
+        }
+    }
+
+    fn flaky(errors: &[ForwarderError]) -> Flaky {
+        Flaky {
+            errors: errors.iter().rev().copied().collect(),
+            hang: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hang_becomes_timeout() {
+        let clock = TestClock::default();
+        let config = ForwarderConfig::default();
+        let hung = Flaky {
+            errors: heapless::Vec::new(),
+            hang: true,
+        };
+
+        let mut reader = TimedReader::new(hung, &clock, &config);
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            reader.read_into_slice(&mut buf).await,
+            Err(ForwarderError::ReadTimeout)
+        );
+        assert_eq!(clock.now.get(), u64::from(config.read_timeout_ms));
+
+        let mut writer = TimedWriter::new(reader.into_inner(), &clock, &config);
+        assert_eq!(
+            writer.write_from_slice(b"x").await,
+            Err(ForwarderError::WriteTimeout)
+        );
+
+        // Timeouts are not retried
+        assert_eq!(clock.delays.borrow().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_retried_with_backoff() {
+        let clock = TestClock::default();
+        let config = ForwarderConfig {
+            retry_backoff_ms: 10,
+            ..ForwarderConfig::default()
+        };
+        let inner = flaky(&[ForwarderError::UsbError, ForwarderError::UsbError]);
+
+        let mut writer = TimedWriter::new(inner, &clock, &config);
+        assert_eq!(writer.write_from_slice(b"abc").await, Ok(3));
+        // The attempts fail at once; only the backoff waits
+        assert_eq!(*clock.delays.borrow(), [10, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_retries_give_up() {
+        let clock = TestClock::default();
+        let config = ForwarderConfig {
+            read_timeout_ms: 0,
+            max_retries: 1,
+            ..ForwarderConfig::default()
+        };
+
+        let inner = flaky(&[ForwarderError::WifiError, ForwarderError::WifiError]);
+        let mut reader = TimedReader::new(inner, &clock, &config);
+        let mut buffer = ZeroCopyBuffer::new();
+        assert_eq!(
+            reader.read_into_buffer(&mut buffer).await,
+            Err(ForwarderError::WifiError)
+        );
+
+        // Not transient: returned straight away
+        let inner = flaky(&[ForwarderError::ProtocolError]);
+        let mut reader = TimedReader::new(inner, &clock, &config);
+        assert_eq!(
+            reader.read_into_buffer(&mut buffer).await,
+            Err(ForwarderError::ProtocolError)
+        );
+        assert_eq!(*clock.delays.borrow(), [config.retry_backoff_ms]);
+    }
+}
@@ -15,10 +15,12 @@
 //! 3. **Async Support**: Native async/await for embassy compatibility
 
 use core::future::Future;
-use heapless::Vec;
 
-use crate::buffer::ZeroCopyBuffer;
-use crate::MTU;
+use crate::buffer::{BufferSlice, ZeroCopyBuffer};
+use crate::pool::{PacketBuf, PacketPool};
+use crate::select::{select, yield_now, Either};
+use crate::stats::{DirectionStats, LatencyHistogram};
+use crate::time::{Clock, TimedReader, TimedWriter};
 
 /// Errors that can occur during data forwarding operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +46,13 @@ pub enum ForwarderError {
     IoError,
 }
 
+impl ForwarderError {
+    /// Check if retrying the operation may succeed (USB or WiFi hiccup)
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ForwarderError::UsbError | ForwarderError::WifiError)
+    }
+}
+
 /// Result type alias for forwarder operations
 pub type ForwarderResult<T> = Result<T, ForwarderError>;
 
@@ -92,6 +101,22 @@ pub trait EndpointReader {
         buf: &mut [u8],
     ) -> impl Future<Output = ForwarderResult<usize>>;
 
+    /// Read into a buffer taken from `pool`, for sharing without copies
+    ///
+    /// Fails with `BufferOverflow` before reading if the pool is exhausted.
+    /// The default fills the buffer with `read_into_slice`.
+    fn read_packet<'p, const COUNT: usize, const SIZE: usize>(
+        &mut self,
+        pool: &'p PacketPool<COUNT, SIZE>,
+    ) -> impl Future<Output = ForwarderResult<PacketBuf<'p, SIZE>>> {
+        async move {
+            let mut packet = pool.alloc()?;
+            let len = self.read_into_slice(packet.storage_mut()).await?;
+            packet.set_len(len)?;
+            Ok(packet.freeze())
+        }
+    }
+
     /// Check if the endpoint is connected and ready
     fn is_connected(&self) -> bool;
 
@@ -126,13 +151,132 @@ pub trait EndpointWriter {
         data: &[u8],
     ) -> impl Future<Output = ForwarderResult<usize>>;
 
+    /// Write both chunks of a buffer view in one transfer
+    ///
+    /// Returns the number of bytes written, counted from the start of
+    /// `slice.first`. Transports with scatter/gather I/O should override
+    /// this; the default writes the chunks one after the other, and if the
+    /// second write fails after the first succeeded, reports the first
+    /// (the error resurfaces on the next call).
+    fn write_vectored(
+        &mut self,
+        slice: BufferSlice<'_>,
+    ) -> impl Future<Output = ForwarderResult<usize>> {
+        async move {
+            let written = self.write_from_slice(slice.first).await?;
+            if written < slice.first.len() || slice.second.is_empty() {
+                return Ok(written);
+            }
+            match self.write_from_slice(slice.second).await {
+                Ok(n) => Ok(written + n),
+                Err(_) if written > 0 => Ok(written),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Write all of a pooled packet
+    ///
+    /// The default calls `write_from_slice` until every byte is taken; a
+    /// write of 0 bytes counts as `Disconnected`.
+    fn write_packet<const SIZE: usize>(
+        &mut self,
+        packet: &PacketBuf<'_, SIZE>,
+    ) -> impl Future<Output = ForwarderResult<()>> {
+        async move {
+            let mut written = 0;
+            while written < packet.len() {
+                match self.write_from_slice(&packet[written..]).await? {
+                    0 => return Err(ForwarderError::Disconnected),
+                    n => written += n,
+                }
+            }
+            Ok(())
+        }
+    }
+
     /// Flush any buffered data to the underlying transport
     fn flush(&mut self) -> impl Future<Output = ForwarderResult<()>>;
 
+    /// Send a zero-length packet, terminating a USB bulk transfer
+    ///
+    /// Transports without packet framing can ignore this; the default does
+    /// nothing.
+    fn write_zlp(&mut self) -> impl Future<Output = ForwarderResult<()>> {
+        async { Ok(()) }
+    }
+
     /// Check if the endpoint is connected and ready
     fn is_connected(&self) -> bool;
 }
 
+/// One forwarding direction: a reader, a writer and the buffer between them
+///
+/// Borrowed as a unit so the reader can fill the buffer and the writer can
+/// drain it within one call, which separate accessors cannot allow.
+pub struct ForwardPath<'a, R, W> {
+    /// Source endpoint
+    pub reader: &'a mut R,
+    /// Destination endpoint
+    pub writer: &'a mut W,
+    /// Buffer between them
+    pub buffer: &'a mut ZeroCopyBuffer,
+    /// Counters updated by [`ForwardPath::forward`]
+    pub stats: &'a mut DirectionStats,
+}
+
+impl<R: EndpointReader, W: EndpointWriter> ForwardPath<'_, R, W> {
+    /// Read once, then write everything buffered (single iteration)
+    ///
+    /// Returns the number of bytes written; bytes the writer did not take
+    /// stay in the buffer and go out first next time. While any are
+    /// pending the read is skipped, so they never wait behind a reader
+    /// that has nothing to deliver.
+    pub async fn forward(&mut self) -> ForwarderResult<usize> {
+        let read_at = if self.buffer.is_empty() {
+            // Read into buffer (zero-copy)
+            let read = self.reader.read_into_buffer(self.buffer).await;
+            self.stats.on_read(&read);
+            if read? == 0 {
+                return Ok(0);
+            }
+            self.stats.now_us()
+        } else {
+            None
+        };
+
+        // Both halves of a wrapped region go out in one transfer
+        let pending = self.buffer.readable_split(self.buffer.readable_len());
+        let bytes_written = self.writer.write_vectored(pending).await?;
+
+        // Consume the written bytes from buffer
+        self.buffer.consume(bytes_written)?;
+
+        self.stats.on_write(bytes_written, read_at);
+        self.stats.on_buffer_level(self.buffer.readable_len());
+        Ok(bytes_written)
+    }
+
+    /// Forward until the reader disconnects or an endpoint fails
+    ///
+    /// Yields to the executor whenever an iteration moves no data, so a
+    /// non-blocking reader cannot starve whatever runs alongside. A
+    /// `ReadTimeout` only means the link was idle: the connection is
+    /// checked again and forwarding carries on.
+    pub async fn pump(&mut self) -> ForwarderError {
+        loop {
+            if !self.reader.is_connected() {
+                return ForwarderError::Disconnected;
+            }
+            match self.forward().await {
+                Ok(0) => yield_now().await,
+                Ok(_) | Err(ForwarderError::ReadTimeout) => {}
+                Err(e) => return e,
+            }
+        }
+    }
+}
+
 /// Main trait that abstracts the bidirectional data flow between USB and WiFi
 ///
 /// # Architecture
@@ -188,36 +332,34 @@ pub trait DataForwarder {
     /// Get reference to the WiFi→USB buffer
     fn wifi_to_usb_buffer(&mut self) -> &mut ZeroCopyBuffer;
 
+    /// Borrow both directions at once (USB→WiFi, WiFi→USB)
+    ///
+    /// The two paths share nothing, which is what lets `run` drive them
+    /// concurrently.
+    #[allow(clippy::type_complexity)]
+    fn paths(
+        &mut self,
+    ) -> (
+        ForwardPath<'_, Self::UsbReader, Self::WifiWriter>,
+        ForwardPath<'_, Self::WifiReader, Self::UsbWriter>,
+    );
+
+    /// Borrow the USB reader, WiFi writer and USB→WiFi buffer together
+    fn usb_to_wifi_path(&mut self) -> ForwardPath<'_, Self::UsbReader, Self::WifiWriter> {
+        self.paths().0
+    }
+
+    /// Borrow the WiFi reader, USB writer and WiFi→USB buffer together
+    fn wifi_to_usb_path(&mut self) -> ForwardPath<'_, Self::WifiReader, Self::UsbWriter> {
+        self.paths().1
+    }
+
     /// Forward data from USB to WiFi (single iteration)
     ///
     /// Returns the number of bytes forwarded, or an error.
     /// This is a non-blocking operation that processes available data.
     fn forward_usb_to_wifi(&mut self) -> impl Future<Output = ForwarderResult<usize>> {
-        async {
-            let buffer = self.usb_to_wifi_buffer();
-            let reader = self.usb_reader();
-            
-            // Read from USB into buffer (zero-copy)
-            let bytes_read = reader.read_into_buffer(buffer).await?;
-            
-            if bytes_read == 0 {
-                return Ok(0);
-            }
-
-            // Get the data slice from buffer
-            let data = buffer.readable_slice(bytes_read)
-                .ok_or(ForwarderError::BufferUnderflow)?;
-            
-            // Write to WiFi (zero-copy from buffer)
-            let writer = self.wifi_writer();
-            let bytes_written = writer.write_from_slice(data).await?;
-            
-            // Consume the written bytes from buffer
-            let buffer = self.usb_to_wifi_buffer();
-            buffer.consume(bytes_written)?;
-            
-            Ok(bytes_written)
-        }
+        async { self.usb_to_wifi_path().forward().await }
     }
 
     /// Forward data from WiFi to USB (single iteration)
@@ -225,62 +367,30 @@ pub trait DataForwarder {
     /// Returns the number of bytes forwarded, or an error.
     /// This is a non-blocking operation that processes available data.
     fn forward_wifi_to_usb(&mut self) -> impl Future<Output = ForwarderResult<usize>> {
-        async {
-            let buffer = self.wifi_to_usb_buffer();
-            let reader = self.wifi_reader();
-            
-            // Read from WiFi into buffer (zero-copy)
-            let bytes_read = reader.read_into_buffer(buffer).await?;
-            
-            if bytes_read == 0 {
-                return Ok(0);
-            }
-
-            // Get the data slice from buffer
-            let data = buffer.readable_slice(bytes_read)
-                .ok_or(ForwarderError::BufferUnderflow)?;
-            
-            // Write to USB (zero-copy from buffer)
-            let writer = self.usb_writer();
-            let bytes_written = writer.write_from_slice(data).await?;
-            
-            // Consume the written bytes from buffer
-            let buffer = self.wifi_to_usb_buffer();
-            buffer.consume(bytes_written)?;
-            
-            Ok(bytes_written)
-        }
+        async { self.wifi_to_usb_path().forward().await }
     }
 
     /// Run the forwarding loop until disconnection
     ///
-    /// This method runs both USB→WiFi and WiFi→USB forwarding concurrently.
-    /// It returns when either endpoint disconnects.
+    /// Both directions run concurrently, each at its own pace: a read that
+    /// waits in one direction does not hold up the other. The paths are
+    /// driven directly, so overriding `forward_*` does not affect `run`.
+    ///
+    /// When either direction fails, the other is cancelled at its next
+    /// await point (its buffered bytes stay in its buffer), both writers
+    /// are flushed on a best-effort basis, and the first error is returned.
     fn run(&mut self) -> impl Future<Output = ForwarderResult<()>> {
         async {
-            loop {
-                // Check connection status
-                if !self.usb_reader().is_connected() || !self.wifi_reader().is_connected() {
-                    return Err(ForwarderError::Disconnected);
-                }
+            let (mut usb_to_wifi, mut wifi_to_usb) = self.paths();
+            let error = match select(usb_to_wifi.pump(), wifi_to_usb.pump()).await {
+                Either::First(e) | Either::Second(e) => e,
+            };
 
-                // Forward in both directions
-                // In a real implementation, these would run concurrently
-                // using embassy's select! or join! macros
-                let usb_to_wifi = self.forward_usb_to_wifi().await;
-                let wifi_to_usb = self.forward_wifi_to_usb().await;
-
-                // Handle errors
-                match (usb_to_wifi, wifi_to_usb) {
-                    (Err(ForwarderError::Disconnected), _) |
-                    (_, Err(ForwarderError::Disconnected)) => {
-                        return Err(ForwarderError::Disconnected);
-                    }
-                    (Err(e), _) => return Err(e),
-                    (_, Err(e)) => return Err(e),
-                    (Ok(_), Ok(_)) => continue,
-                }
-            }
+            // The surviving direction may have written into a buffering
+            // transport; push that out before handing back
+            let _ = usb_to_wifi.writer.flush().await;
+            let _ = wifi_to_usb.writer.flush().await;
+            Err(error)
         }
     }
 
@@ -309,19 +419,46 @@ pub struct ForwardingStats {
     pub usb_to_wifi_buffer_used: usize,
     /// Current WiFi→USB buffer usage (bytes)
     pub wifi_to_usb_buffer_used: usize,
+    /// USB→WiFi per-chunk latency
+    pub usb_to_wifi_latency: LatencyHistogram,
+    /// WiFi→USB per-chunk latency
+    pub wifi_to_usb_latency: LatencyHistogram,
+}
+
+impl ForwardingStats {
+    /// Combine the per-direction counters kept by the forwarding paths
+    pub fn from_directions(usb_to_wifi: &DirectionStats, wifi_to_usb: &DirectionStats) -> Self {
+        Self {
+            usb_to_wifi_bytes: usb_to_wifi.bytes,
+            wifi_to_usb_bytes: wifi_to_usb.bytes,
+            usb_reads: usb_to_wifi.reads,
+            wifi_reads: wifi_to_usb.reads,
+            overflows: usb_to_wifi.overflows.saturating_add(wifi_to_usb.overflows),
+            usb_to_wifi_buffer_used: usb_to_wifi.buffer_used,
+            wifi_to_usb_buffer_used: wifi_to_usb.buffer_used,
+            usb_to_wifi_latency: usb_to_wifi.latency,
+            wifi_to_usb_latency: wifi_to_usb.latency,
+        }
+    }
 }
 
 /// Configuration for the data forwarder
+///
+/// Timeouts and retries are applied by the `time` module's
+/// `TimedReader`/`TimedWriter` wrappers, which
+/// [`EndpointForwarder::with_config`] puts around every endpoint.
 #[derive(Debug, Clone)]
 pub struct ForwarderConfig {
-    /// Timeout for read operations in milliseconds
+    /// Timeout for read operations in milliseconds (0: none)
     pub read_timeout_ms: u32,
-    /// Timeout for write operations in milliseconds
+    /// Timeout for write operations in milliseconds (0: none)
     pub write_timeout_ms: u32,
     /// Enable statistics collection (slight overhead)
     pub enable_stats: bool,
     /// Maximum retries on transient errors
     pub max_retries: u8,
+    /// Wait before the first retry; doubles for each further one (ms)
+    pub retry_backoff_ms: u32,
 }
 
 impl Default for ForwarderConfig {
@@ -331,17 +468,163 @@ impl Default for ForwarderConfig {
             write_timeout_ms: 1000,
             enable_stats: true,
             max_retries: 3,
+            retry_backoff_ms: 10,
         }
     }
 }
 
+/// Ready-made `DataForwarder` over any four endpoints
+///
+/// Owns both buffers and relies on the trait's default forwarding methods,
+/// so it serves as the reference implementation in tests.
+pub struct EndpointForwarder<UR, UW, WR, WW> {
+    usb_reader: UR,
+    usb_writer: UW,
+    wifi_reader: WR,
+    wifi_writer: WW,
+    usb_to_wifi: ZeroCopyBuffer,
+    wifi_to_usb: ZeroCopyBuffer,
+    usb_to_wifi_stats: DirectionStats,
+    wifi_to_usb_stats: DirectionStats,
+}
+
+impl<UR, UW, WR, WW> EndpointForwarder<UR, UW, WR, WW>
+where
+    UR: EndpointReader,
+    UW: EndpointWriter,
+    WR: EndpointReader,
+    WW: EndpointWriter,
+{
+    /// Create a forwarder with empty buffers
+    pub fn new(usb_reader: UR, usb_writer: UW, wifi_reader: WR, wifi_writer: WW) -> Self {
+        Self {
+            usb_reader,
+            usb_writer,
+            wifi_reader,
+            wifi_writer,
+            usb_to_wifi: ZeroCopyBuffer::new(),
+            wifi_to_usb: ZeroCopyBuffer::new(),
+            usb_to_wifi_stats: DirectionStats::new(),
+            wifi_to_usb_stats: DirectionStats::new(),
+        }
+    }
+
+    /// Record per-chunk latency using a monotonic microsecond clock
+    pub fn with_timer(mut self, now_us: fn() -> u64) -> Self {
+        self.usb_to_wifi_stats = self.usb_to_wifi_stats.with_timer(now_us);
+        self.wifi_to_usb_stats = self.wifi_to_usb_stats.with_timer(now_us);
+        self
+    }
+
+    /// Unwrap the endpoints (USB reader, USB writer, WiFi reader, WiFi writer)
+    pub fn into_parts(self) -> (UR, UW, WR, WW) {
+        (self.usb_reader, self.usb_writer, self.wifi_reader, self.wifi_writer)
+    }
+}
+
+/// `EndpointForwarder` whose endpoints apply a [`ForwarderConfig`]'s policy
+pub type TimedForwarder<UR, UW, WR, WW, C> = EndpointForwarder<
+    TimedReader<UR, C>,
+    TimedWriter<UW, C>,
+    TimedReader<WR, C>,
+    TimedWriter<WW, C>,
+>;
+
+impl<UR, UW, WR, WW, C> TimedForwarder<UR, UW, WR, WW, C>
+where
+    UR: EndpointReader,
+    UW: EndpointWriter,
+    WR: EndpointReader,
+    WW: EndpointWriter,
+    C: Clock + Clone,
+{
+    /// Create a forwarder applying `config`'s timeouts and retries to every endpoint
+    pub fn with_config(
+        usb_reader: UR,
+        usb_writer: UW,
+        wifi_reader: WR,
+        wifi_writer: WW,
+        clock: C,
+        config: &ForwarderConfig,
+    ) -> Self {
+        Self::new(
+            TimedReader::new(usb_reader, clock.clone(), config),
+            TimedWriter::new(usb_writer, clock.clone(), config),
+            TimedReader::new(wifi_reader, clock.clone(), config),
+            TimedWriter::new(wifi_writer, clock, config),
+        )
+    }
+}
+
+impl<UR, UW, WR, WW> DataForwarder for EndpointForwarder<UR, UW, WR, WW>
+where
+    UR: EndpointReader,
+    UW: EndpointWriter,
+    WR: EndpointReader,
+    WW: EndpointWriter,
+{
+    type UsbReader = UR;
+    type UsbWriter = UW;
+    type WifiReader = WR;
+    type WifiWriter = WW;
+
+    fn usb_reader(&mut self) -> &mut UR {
+        &mut self.usb_reader
+    }
+
+    fn usb_writer(&mut self) -> &mut UW {
+        &mut self.usb_writer
+    }
+
+    fn wifi_reader(&mut self) -> &mut WR {
+        &mut self.wifi_reader
+    }
+
+    fn wifi_writer(&mut self) -> &mut WW {
+        &mut self.wifi_writer
+    }
+
+    fn usb_to_wifi_buffer(&mut self) -> &mut ZeroCopyBuffer {
+        &mut self.usb_to_wifi
+    }
+
+    fn wifi_to_usb_buffer(&mut self) -> &mut ZeroCopyBuffer {
+        &mut self.wifi_to_usb
+    }
+
+    fn paths(&mut self) -> (ForwardPath<'_, UR, WW>, ForwardPath<'_, WR, UW>) {
+        (
+            ForwardPath {
+                reader: &mut self.usb_reader,
+                writer: &mut self.wifi_writer,
+                buffer: &mut self.usb_to_wifi,
+                stats: &mut self.usb_to_wifi_stats,
+            },
+            ForwardPath {
+                reader: &mut self.wifi_reader,
+                writer: &mut self.usb_writer,
+                buffer: &mut self.wifi_to_usb,
+                stats: &mut self.wifi_to_usb_stats,
+            },
+        )
+    }
+
+    fn is_connected(&self) -> bool {
+        self.usb_reader.is_connected()
+            && self.usb_writer.is_connected()
+            && self.wifi_reader.is_connected()
+            && self.wifi_writer.is_connected()
+    }
+
+    fn stats(&self) -> ForwardingStats {
+        ForwardingStats::from_directions(&self.usb_to_wifi_stats, &self.wifi_to_usb_stats)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // Mock implementations for testing would go here
-    // In a real implementation, you'd have mock USB and WiFi endpoints
-
     #[test]
     fn test_forwarder_config_default() {
         let config = ForwarderConfig::default();
@@ -349,5 +632,273 @@ mod tests {
         assert_eq!(config.write_timeout_ms, 1000);
         assert!(config.enable_stats);
         assert_eq!(config.max_retries, 3);
+        assert_eq!(config.retry_backoff_ms, 10);
+        assert!(ForwarderError::WifiError.is_transient());
+        assert!(!ForwarderError::Disconnected.is_transient());
+    }
+
+    #[cfg(feature = "std")]
+    mod forwarding {
+        use super::*;
+        use crate::loopback::{duplex, DuplexEnd, PipeReader, PipeWriter};
+
+        type Forwarder = EndpointForwarder<PipeReader, PipeWriter, PipeReader, PipeWriter>;
+
+        /// Forwarder plus the test's ends: the USB host and the phone
+        fn bridge() -> (Forwarder, DuplexEnd, DuplexEnd) {
+            let (usb_host, usb) = duplex(4096);
+            let (wifi, phone) = duplex(4096);
+            let forwarder = EndpointForwarder::new(usb.reader, usb.writer, wifi.reader, wifi.writer);
+            (forwarder, usb_host, phone)
+        }
+
+        #[tokio::test]
+        async fn test_forwards_both_directions() {
+            let (mut forwarder, usb_host, phone) = bridge();
+
+            usb_host.writer.push(b"from usb").unwrap();
+            assert_eq!(forwarder.forward_usb_to_wifi().await, Ok(8));
+            assert_eq!(phone.reader.drain(), b"from usb");
+
+            phone.writer.push(b"from phone").unwrap();
+            assert_eq!(forwarder.forward_wifi_to_usb().await, Ok(10));
+            assert_eq!(usb_host.reader.drain(), b"from phone");
+
+            // Nothing pending
+            assert_eq!(forwarder.forward_usb_to_wifi().await, Ok(0));
+            assert!(forwarder.is_connected());
+        }
+
+        #[tokio::test]
+        async fn test_wrapped_data_goes_out_in_one_call() {
+            let (mut forwarder, usb_host, phone) = bridge();
+
+            // Leave 4 bytes before the end so the next read wraps
+            let buffer = forwarder.usb_to_wifi_buffer();
+            let filler = vec![0u8; buffer.capacity() - 4];
+            buffer.write(&filler).unwrap();
+            buffer.consume(filler.len()).unwrap();
+
+            usb_host.writer.push(b"wrapped!").unwrap();
+            assert_eq!(forwarder.forward_usb_to_wifi().await, Ok(8));
+            assert_eq!(phone.reader.drain(), b"wrapped!");
+        }
+
+        #[tokio::test]
+        async fn test_stats_accounted_automatically() {
+            let (forwarder, usb_host, phone) = bridge();
+            let mut forwarder = forwarder.with_timer(crate::time::monotonic_us);
+
+            usb_host.writer.push(b"abc").unwrap();
+            forwarder.forward_usb_to_wifi().await.unwrap();
+            usb_host.writer.push(b"defg").unwrap();
+            forwarder.forward_usb_to_wifi().await.unwrap();
+            phone.writer.push(b"hi").unwrap();
+            forwarder.forward_wifi_to_usb().await.unwrap();
+            // Idle read: not counted
+            forwarder.forward_wifi_to_usb().await.unwrap();
+
+            let stats = forwarder.stats();
+            assert_eq!((stats.usb_to_wifi_bytes, stats.usb_reads), (7, 2));
+            assert_eq!((stats.wifi_to_usb_bytes, stats.wifi_reads), (2, 1));
+            assert_eq!(stats.usb_to_wifi_latency.count(), 2);
+            assert!(stats.wifi_to_usb_latency.p99_us().is_some());
+        }
+
+        #[tokio::test]
+        async fn test_partial_write_keeps_remainder_buffered() {
+            let (usb_host, usb) = duplex(4096);
+            let (wifi, phone) = duplex(4);
+            let mut forwarder =
+                EndpointForwarder::new(usb.reader, usb.writer, wifi.reader, wifi.writer);
+
+            usb_host.writer.push(b"abcdef").unwrap();
+            assert_eq!(forwarder.forward_usb_to_wifi().await, Ok(4));
+            assert_eq!(forwarder.stats().usb_to_wifi_buffer_used, 2);
+            assert_eq!(phone.reader.drain(), b"abcd");
+        }
+
+        #[tokio::test]
+        async fn test_run_stops_on_disconnect() {
+            let (mut forwarder, usb_host, phone) = bridge();
+
+            usb_host.writer.push(b"last words").unwrap();
+            drop(usb_host);
+            assert_eq!(forwarder.run().await, Err(ForwarderError::Disconnected));
+            assert_eq!(phone.reader.drain(), b"last words");
+        }
+
+        #[tokio::test]
+        async fn test_run_propagates_endpoint_errors() {
+            let (mut forwarder, _usb_host, phone) = bridge();
+
+            phone.writer.push(b"ping").unwrap();
+            forwarder.usb_writer().fail_next(ForwarderError::UsbError);
+            assert_eq!(forwarder.run().await, Err(ForwarderError::UsbError));
+        }
+
+        /// USB reader whose reads never complete
+        struct Stalled;
+
+        impl EndpointReader for Stalled {
+            async fn read_into_buffer(&mut self, _: &mut ZeroCopyBuffer) -> ForwarderResult<usize> {
+                core::future::pending().await
+            }
+
+            async fn read_into_slice(&mut self, _: &mut [u8]) -> ForwarderResult<usize> {
+                core::future::pending().await
+            }
+
+            fn is_connected(&self) -> bool {
+                true
+            }
+
+            fn max_packet_size(&self) -> usize {
+                64
+            }
+        }
+
+        /// Reader that waits forever once its pipe is empty
+        struct IdleWhenEmpty(PipeReader);
+
+        impl EndpointReader for IdleWhenEmpty {
+            async fn read_into_buffer(
+                &mut self,
+                buffer: &mut ZeroCopyBuffer,
+            ) -> ForwarderResult<usize> {
+                if self.0.available() == 0 {
+                    core::future::pending::<()>().await;
+                }
+                self.0.read_into_buffer(buffer).await
+            }
+
+            async fn read_into_slice(&mut self, buf: &mut [u8]) -> ForwarderResult<usize> {
+                if self.0.available() == 0 {
+                    core::future::pending::<()>().await;
+                }
+                self.0.read_into_slice(buf).await
+            }
+
+            fn is_connected(&self) -> bool {
+                true
+            }
+
+            fn max_packet_size(&self) -> usize {
+                self.0.max_packet_size()
+            }
+        }
+
+        /// Writer that takes one byte per call
+        struct Trickle(PipeWriter);
+
+        impl EndpointWriter for Trickle {
+            async fn write_from_buffer(
+                &mut self,
+                buffer: &ZeroCopyBuffer,
+                len: usize,
+            ) -> ForwarderResult<usize> {
+                self.write_vectored(buffer.readable_split(len)).await
+            }
+
+            async fn write_from_slice(&mut self, data: &[u8]) -> ForwarderResult<usize> {
+                self.0.push(&data[..data.len().min(1)])
+            }
+
+            async fn flush(&mut self) -> ForwarderResult<()> {
+                Ok(())
+            }
+
+            fn is_connected(&self) -> bool {
+                self.0.is_connected()
+            }
+        }
+
+        #[tokio::test]
+        async fn test_pending_bytes_do_not_wait_for_next_read() {
+            let (usb_host, usb) = duplex(4096);
+            let (wifi, phone) = duplex(4096);
+            let mut forwarder = EndpointForwarder::new(
+                IdleWhenEmpty(usb.reader),
+                usb.writer,
+                IdleWhenEmpty(wifi.reader),
+                Trickle(wifi.writer),
+            );
+
+            // One read, then the USB side goes quiet
+            usb_host.writer.push(b"hello").unwrap();
+            let watch = async {
+                for _ in 0..1000 {
+                    if phone.reader.available() == 5 {
+                        break;
+                    }
+                    yield_now().await;
+                }
+            };
+            assert!(matches!(
+                select(forwarder.run(), watch).await,
+                Either::Second(())
+            ));
+            assert_eq!(phone.reader.drain(), b"hello");
+        }
+
+        /// Virtual clock whose delays pass after one trip through the executor
+        #[derive(Clone, Copy)]
+        struct YieldClock;
+
+        impl Clock for YieldClock {
+            fn now_ms(&self) -> u64 {
+                0
+            }
+
+            async fn delay_ms(&self, _: u32) {
+                yield_now().await
+            }
+        }
+
+        #[tokio::test]
+        async fn test_run_survives_idle_link() {
+            let (usb_host, usb) = duplex(4096);
+            let (wifi, phone) = duplex(4096);
+            let config = ForwarderConfig::default();
+            let mut forwarder = EndpointForwarder::with_config(
+                IdleWhenEmpty(usb.reader),
+                usb.writer,
+                IdleWhenEmpty(wifi.reader),
+                wifi.writer,
+                YieldClock,
+                &config,
+            );
+
+            let watch = async {
+                // Plenty of read timeouts on both sides before anything arrives
+                for _ in 0..100 {
+                    yield_now().await;
+                }
+                usb_host.writer.push(b"still here").unwrap();
+                for _ in 0..1000 {
+                    if phone.reader.available() == 10 {
+                        break;
+                    }
+                    yield_now().await;
+                }
+            };
+            assert!(matches!(
+                select(forwarder.run(), watch).await,
+                Either::Second(())
+            ));
+            assert_eq!(phone.reader.drain(), b"still here");
+        }
+
+        #[tokio::test]
+        async fn test_run_blocked_direction_does_not_stall_other() {
+            let (usb_host, usb) = duplex(4096);
+            let (wifi, phone) = duplex(4096);
+            let mut forwarder = EndpointForwarder::new(Stalled, usb.writer, wifi.reader, wifi.writer);
+
+            phone.writer.push(b"to the car").unwrap();
+            drop(phone.writer);
+            assert_eq!(forwarder.run().await, Err(ForwarderError::Disconnected));
+            assert_eq!(usb_host.reader.drain(), b"to the car");
+        }
     }
 }
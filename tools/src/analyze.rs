@@ -0,0 +1,786 @@
+//! # Protocol Analyzer
+//!
+//! Turns recorded traffic into a stream of [`Event`]s: one per decoded frame,
+//! plus sequence gaps, CRC errors, ping round trips, session boundaries and
+//! periodic per-channel bandwidth. Events print as text (`Display`) or as
+//! JSON lines (`Serialize`).
+//!
+//! ```text
+//!   Record ─► Deframer (per direction) ─► parse_frames ─► Event...
+//!                                              │
+//!                          seq / ping / session / bandwidth tracking
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+use shared::aap::AapHeader;
+use shared::batch::parse_frames;
+use shared::capture::Direction;
+use shared::protocol::{ControlMessage, FrameBuilder, Message, MessageType, FLAG_BATCH};
+
+use crate::deframe::{Deframed, Deframer};
+use crate::input::Record;
+
+/// Transfer direction as printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    /// Received by the recording endpoint
+    Rx,
+    /// Transmitted by the recording endpoint
+    Tx,
+}
+
+impl From<Direction> for Side {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Rx => Side::Rx,
+            Direction::Tx => Side::Tx,
+        }
+    }
+}
+
+impl Side {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            Side::Rx => Side::Tx,
+            Side::Tx => Side::Rx,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Side::Rx => "rx",
+            Side::Tx => "tx",
+        }
+    }
+}
+
+/// A decoded frame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrameEvent {
+    /// Capture timestamp in microseconds
+    pub timestamp_us: u64,
+    /// Transfer direction
+    pub direction: Side,
+    /// Stream offset of the frame
+    pub offset: u64,
+    /// Frame sequence number
+    pub sequence: u16,
+    /// Frame channel
+    pub channel: u8,
+    /// Message type name
+    pub msg_type: String,
+    /// Frame length on the wire
+    pub len: usize,
+    /// Human-readable message summary
+    pub summary: String,
+    /// AAP header of a `Data` payload, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aap: Option<String>,
+}
+
+/// Bytes seen on one channel during one interval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BandwidthEvent {
+    /// Interval start in microseconds
+    pub start_us: u64,
+    /// Interval length in microseconds
+    pub duration_us: u64,
+    /// Frame channel
+    pub channel: u8,
+    /// Frame bytes in the interval
+    pub bytes: u64,
+    /// Average rate in kbit/s
+    pub kbps: u64,
+}
+
+/// Per-channel totals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChannelTotals {
+    /// Frame channel
+    pub channel: u8,
+    /// Frames seen
+    pub frames: u64,
+    /// Frame bytes seen
+    pub bytes: u64,
+}
+
+/// End-of-input totals
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// Frames decoded
+    pub frames: u64,
+    /// Frame bytes decoded
+    pub bytes: u64,
+    /// Frames with a CRC mismatch
+    pub crc_errors: u64,
+    /// Frames that passed the CRC but did not decode
+    pub decode_errors: u64,
+    /// Bytes skipped while resynchronizing
+    pub resync_bytes: u64,
+    /// Sequence discontinuities
+    pub sequence_gaps: u64,
+    /// Frames missing according to sequence numbers
+    pub missing_frames: u64,
+    /// Ping/pong pairs matched
+    pub rtt_samples: u64,
+    /// Smallest round trip in microseconds
+    pub rtt_min_us: Option<u64>,
+    /// Average round trip in microseconds
+    pub rtt_avg_us: Option<u64>,
+    /// Largest round trip in microseconds
+    pub rtt_max_us: Option<u64>,
+    /// Sessions started
+    pub sessions: u64,
+    /// Time from first to last record in microseconds
+    pub duration_us: u64,
+    /// Per-channel totals, by channel
+    pub channels: Vec<ChannelTotals>,
+}
+
+/// Session boundary kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    /// Capture marked a session start
+    Start,
+    /// Capture marked a session end
+    End,
+    /// A `HandshakeResponse` assigned a session ID
+    Handshake,
+    /// A `Disconnect` was sent
+    Disconnect,
+}
+
+/// Analyzer output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A decoded frame
+    Frame(FrameEvent),
+    /// Sequence number did not follow the previous frame
+    SequenceGap {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Transfer direction
+        direction: Side,
+        /// Expected sequence number
+        expected: u16,
+        /// Received sequence number
+        got: u16,
+    },
+    /// A frame failed the CRC check
+    CrcError {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Transfer direction
+        direction: Side,
+        /// Stream offset of the frame
+        offset: u64,
+    },
+    /// A frame passed the CRC check but did not decode
+    DecodeError {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Transfer direction
+        direction: Side,
+        /// Stream offset of the frame
+        offset: u64,
+    },
+    /// Bytes skipped while looking for a frame
+    Resync {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Transfer direction
+        direction: Side,
+        /// Stream offset of the skipped bytes
+        offset: u64,
+        /// Number of bytes skipped
+        len: usize,
+    },
+    /// A pong matched an earlier ping
+    Rtt {
+        /// Capture timestamp of the pong in microseconds
+        timestamp_us: u64,
+        /// Direction of the ping
+        direction: Side,
+        /// Ping timestamp field
+        ping: u32,
+        /// Round trip in microseconds
+        rtt_us: u64,
+    },
+    /// Session boundary
+    Session {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Boundary kind
+        kind: SessionKind,
+        /// Session ID, or disconnect reason
+        value: u32,
+    },
+    /// Per-channel bandwidth for one interval
+    Bandwidth(BandwidthEvent),
+    /// End-of-input totals
+    Summary(Summary),
+}
+
+/// Analyzer settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalyzerConfig {
+    /// Bandwidth reporting interval in microseconds
+    pub interval_us: u64,
+    /// Emit an event per frame
+    pub frames: bool,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            interval_us: 1_000_000,
+            frames: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stream {
+    deframer: Deframer,
+    next_sequence: Option<u16>,
+}
+
+/// Stateful analyzer fed one record at a time
+pub struct Analyzer {
+    config: AnalyzerConfig,
+    streams: [Stream; 2],
+    pings: HashMap<(Side, u32), u64>,
+    bucket_start: Option<u64>,
+    bucket: BTreeMap<u8, u64>,
+    channels: BTreeMap<u8, ChannelTotals>,
+    first_us: Option<u64>,
+    last_us: u64,
+    rtt_sum: u64,
+    summary: Summary,
+}
+
+impl Analyzer {
+    /// Create an analyzer
+    pub fn new(config: AnalyzerConfig) -> Self {
+        Self {
+            config: AnalyzerConfig {
+                interval_us: config.interval_us.max(1),
+                ..config
+            },
+            streams: Default::default(),
+            pings: HashMap::new(),
+            bucket_start: None,
+            bucket: BTreeMap::new(),
+            channels: BTreeMap::new(),
+            first_us: None,
+            last_us: 0,
+            rtt_sum: 0,
+            summary: Summary::default(),
+        }
+    }
+
+    /// Process one record, appending resulting events to `out`
+    pub fn process(&mut self, record: &Record, out: &mut Vec<Event>) {
+        let now = record.timestamp_us();
+        self.first_us.get_or_insert(now);
+        self.last_us = self.last_us.max(now);
+        self.roll_bandwidth(now, out);
+
+        match record {
+            Record::SessionStart { session_id, .. } => {
+                self.summary.sessions += 1;
+                for stream in &mut self.streams {
+                    *stream = Stream::default();
+                }
+                self.pings.clear();
+                out.push(Event::Session {
+                    timestamp_us: now,
+                    kind: SessionKind::Start,
+                    value: *session_id,
+                });
+            }
+            Record::SessionEnd { session_id, .. } => out.push(Event::Session {
+                timestamp_us: now,
+                kind: SessionKind::End,
+                value: *session_id,
+            }),
+            Record::Data { direction, bytes, .. } => {
+                let side = Side::from(*direction);
+                self.streams[side.index()].deframer.push(bytes);
+                while let Some(item) = self.streams[side.index()].deframer.next_frame() {
+                    self.handle(now, side, item, out);
+                }
+            }
+        }
+    }
+
+    /// Flush pending state and append the final bandwidth and summary events
+    pub fn finish(mut self, out: &mut Vec<Event>) -> Summary {
+        for side in [Side::Rx, Side::Tx] {
+            if let Some(item) = self.streams[side.index()].deframer.finish() {
+                self.handle(self.last_us, side, item, out);
+            }
+        }
+        self.flush_bandwidth(out);
+
+        let mut summary = self.summary;
+        summary.duration_us = self.last_us - self.first_us.unwrap_or(self.last_us);
+        summary.rtt_avg_us = self.rtt_sum.checked_div(summary.rtt_samples);
+        summary.channels = self.channels.into_values().collect();
+        out.push(Event::Summary(summary.clone()));
+        summary
+    }
+
+    fn handle(&mut self, now: u64, side: Side, item: Deframed, out: &mut Vec<Event>) {
+        let event = match item {
+            Deframed::Frame { offset, bytes } => {
+                self.handle_frame(now, side, offset, &bytes, out);
+                return;
+            }
+            Deframed::CrcError { offset } => {
+                self.summary.crc_errors += 1;
+                Event::CrcError { timestamp_us: now, direction: side, offset }
+            }
+            Deframed::Skipped { offset, len } => {
+                self.summary.resync_bytes += len as u64;
+                Event::Resync { timestamp_us: now, direction: side, offset, len }
+            }
+        };
+        out.push(event);
+    }
+
+    fn handle_frame(&mut self, now: u64, side: Side, offset: u64, bytes: &[u8], out: &mut Vec<Event>) {
+        // The deframer only yields CRC-checked frames
+        let Ok(raw) = FrameBuilder::parse_raw_frame(bytes) else {
+            return;
+        };
+        let header = raw.header;
+        let len = raw.frame_len();
+
+        self.summary.frames += 1;
+        self.summary.bytes += len as u64;
+        *self.bucket.entry(header.channel).or_default() += len as u64;
+        let totals = self.channels.entry(header.channel).or_insert(ChannelTotals {
+            channel: header.channel,
+            frames: 0,
+            bytes: 0,
+        });
+        totals.frames += 1;
+        totals.bytes += len as u64;
+
+        let stream = &mut self.streams[side.index()];
+        if let Some(expected) = stream.next_sequence {
+            if header.sequence != expected {
+                self.summary.sequence_gaps += 1;
+                let missing = header.sequence.wrapping_sub(expected);
+                if missing < 0x8000 {
+                    self.summary.missing_frames += missing as u64;
+                }
+                out.push(Event::SequenceGap {
+                    timestamp_us: now,
+                    direction: side,
+                    expected,
+                    got: header.sequence,
+                });
+            }
+        }
+        stream.next_sequence = Some(header.sequence.wrapping_add(1));
+
+        let msg_type = match MessageType::try_from(raw.msg_type) {
+            Ok(t) => format!("{t:?}"),
+            Err(()) => format!("0x{:02x}", raw.msg_type),
+        };
+
+        let mut parts = Vec::new();
+        let mut aap = None;
+        let mut decode_failed = false;
+        // Frame CRC already checked above
+        for item in parse_frames(bytes).into_iter().flatten() {
+            let Ok((_, msg)) = item else {
+                decode_failed = true;
+                continue;
+            };
+            self.track_message(now, side, &msg, out);
+            if let Message::Data(payload) = &msg {
+                aap = AapHeader::parse(&payload.data).map(|h| describe_aap(&h));
+            }
+            parts.push(describe(&msg));
+        }
+
+        if decode_failed {
+            self.summary.decode_errors += 1;
+            out.push(Event::DecodeError { timestamp_us: now, direction: side, offset });
+        }
+
+        if self.config.frames {
+            let summary = if header.flags & FLAG_BATCH != 0 {
+                format!("[{}] {}", parts.len(), parts.join("; "))
+            } else {
+                parts.join("; ")
+            };
+            out.push(Event::Frame(FrameEvent {
+                timestamp_us: now,
+                direction: side,
+                offset,
+                sequence: header.sequence,
+                channel: header.channel,
+                msg_type,
+                len,
+                summary,
+                aap,
+            }));
+        }
+    }
+
+    fn track_message(&mut self, now: u64, side: Side, msg: &Message, out: &mut Vec<Event>) {
+        match msg {
+            Message::Ping { timestamp } => {
+                self.pings.insert((side, *timestamp), now);
+            }
+            Message::Pong { timestamp } => {
+                let Some(sent) = self.pings.remove(&(side.opposite(), *timestamp)) else {
+                    return;
+                };
+                let rtt_us = now.saturating_sub(sent);
+                self.summary.rtt_samples += 1;
+                self.rtt_sum += rtt_us;
+                self.summary.rtt_min_us = Some(self.summary.rtt_min_us.map_or(rtt_us, |m| m.min(rtt_us)));
+                self.summary.rtt_max_us = Some(self.summary.rtt_max_us.map_or(rtt_us, |m| m.max(rtt_us)));
+                out.push(Event::Rtt {
+                    timestamp_us: now,
+                    direction: side.opposite(),
+                    ping: *timestamp,
+                    rtt_us,
+                });
+            }
+            Message::Control(ControlMessage::HandshakeResponse { session_id, .. }) => {
+                out.push(Event::Session {
+                    timestamp_us: now,
+                    kind: SessionKind::Handshake,
+                    value: *session_id,
+                });
+            }
+            Message::Control(ControlMessage::Disconnect { reason }) => {
+                out.push(Event::Session {
+                    timestamp_us: now,
+                    kind: SessionKind::Disconnect,
+                    value: *reason as u32,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Emit bandwidth for finished intervals before `now`
+    fn roll_bandwidth(&mut self, now: u64, out: &mut Vec<Event>) {
+        let interval = self.config.interval_us;
+        let start = now - now % interval;
+        match self.bucket_start {
+            Some(current) if current == start => {}
+            Some(_) => {
+                self.flush_bandwidth(out);
+                self.bucket_start = Some(start);
+            }
+            None => self.bucket_start = Some(start),
+        }
+    }
+
+    fn flush_bandwidth(&mut self, out: &mut Vec<Event>) {
+        let Some(start_us) = self.bucket_start else {
+            return;
+        };
+        let duration_us = self.config.interval_us;
+        for (channel, bytes) in std::mem::take(&mut self.bucket) {
+            out.push(Event::Bandwidth(BandwidthEvent {
+                start_us,
+                duration_us,
+                channel,
+                bytes,
+                kbps: bytes * 8_000 / duration_us,
+            }));
+        }
+    }
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new(AnalyzerConfig::default())
+    }
+}
+
+/// One-line description of a message
+fn describe(msg: &Message) -> String {
+    match msg {
+        Message::Control(ctrl) => format!("{ctrl:?}"),
+        Message::Data(payload) => format!("Data len={}", payload.len()),
+        other => format!("{other:?}"),
+    }
+}
+
+/// One-line description of an AAP header
+fn describe_aap(header: &AapHeader) -> String {
+    let mut text = format!("ch={} len={}", header.channel, header.frame_len);
+    for (set, name) in [
+        (header.is_first(), "first"),
+        (header.is_last(), "last"),
+        (header.is_control(), "ctrl"),
+        (header.is_encrypted(), "enc"),
+    ] {
+        if set {
+            text.push(' ');
+            text.push_str(name);
+        }
+    }
+    if let Some(total) = header.total_len {
+        text.push_str(&format!(" total={total}"));
+    }
+    if let Some(id) = header.message_id {
+        text.push_str(&format!(" msg=0x{id:04x}"));
+    }
+    text
+}
+
+/// Format microseconds as seconds with six decimals
+struct Seconds(u64);
+
+impl fmt::Display for Seconds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6}.{:06}", self.0 / 1_000_000, self.0 % 1_000_000)
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Frame(e) => {
+                write!(
+                    f,
+                    "{} {} seq={:<5} ch={:<3} {:<7} len={:<5} {}",
+                    Seconds(e.timestamp_us),
+                    e.direction.as_str(),
+                    e.sequence,
+                    e.channel,
+                    e.msg_type,
+                    e.len,
+                    e.summary
+                )?;
+                if let Some(aap) = &e.aap {
+                    write!(f, " [aap {aap}]")?;
+                }
+                Ok(())
+            }
+            Event::SequenceGap { timestamp_us, direction, expected, got } => write!(
+                f,
+                "{} {} !! sequence gap: expected {expected}, got {got}",
+                Seconds(*timestamp_us),
+                direction.as_str()
+            ),
+            Event::CrcError { timestamp_us, direction, offset } => write!(
+                f,
+                "{} {} !! CRC error at offset {offset}",
+                Seconds(*timestamp_us),
+                direction.as_str()
+            ),
+            Event::DecodeError { timestamp_us, direction, offset } => write!(
+                f,
+                "{} {} !! undecodable payload at offset {offset}",
+                Seconds(*timestamp_us),
+                direction.as_str()
+            ),
+            Event::Resync { timestamp_us, direction, offset, len } => write!(
+                f,
+                "{} {} !! skipped {len} bytes at offset {offset}",
+                Seconds(*timestamp_us),
+                direction.as_str()
+            ),
+            Event::Rtt { timestamp_us, direction, ping, rtt_us } => write!(
+                f,
+                "{} {} -- ping {ping} rtt {}.{:03} ms",
+                Seconds(*timestamp_us),
+                direction.as_str(),
+                rtt_us / 1000,
+                rtt_us % 1000
+            ),
+            Event::Session { timestamp_us, kind, value } => {
+                let what = match kind {
+                    SessionKind::Start => format!("session {value} start"),
+                    SessionKind::End => format!("session {value} end"),
+                    SessionKind::Handshake => format!("handshake, session {value}"),
+                    SessionKind::Disconnect => format!("disconnect, reason {value}"),
+                };
+                write!(f, "{} == {what}", Seconds(*timestamp_us))
+            }
+            Event::Bandwidth(e) => write!(
+                f,
+                "{} bw ch={:<3} {} bytes {} kbit/s",
+                Seconds(e.start_us),
+                e.channel,
+                e.bytes,
+                e.kbps
+            ),
+            Event::Summary(s) => {
+                writeln!(f, "== summary ==")?;
+                writeln!(
+                    f,
+                    "duration:       {}.{:06} s",
+                    s.duration_us / 1_000_000,
+                    s.duration_us % 1_000_000
+                )?;
+                writeln!(f, "frames:         {} ({} bytes)", s.frames, s.bytes)?;
+                writeln!(f, "sessions:       {}", s.sessions)?;
+                writeln!(
+                    f,
+                    "sequence gaps:  {} ({} frames missing)",
+                    s.sequence_gaps, s.missing_frames
+                )?;
+                writeln!(f, "crc errors:     {}", s.crc_errors)?;
+                writeln!(f, "decode errors:  {}", s.decode_errors)?;
+                writeln!(f, "resync bytes:   {}", s.resync_bytes)?;
+                if let (Some(min), Some(avg), Some(max)) = (s.rtt_min_us, s.rtt_avg_us, s.rtt_max_us) {
+                    writeln!(
+                        f,
+                        "rtt (us):       min {min} avg {avg} max {max} ({} samples)",
+                        s.rtt_samples
+                    )?;
+                }
+                write!(f, "channels:")?;
+                for c in &s.channels {
+                    write!(f, "\n  ch={:<3} {} frames {} bytes", c.channel, c.frames, c.bytes)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(builder: &mut FrameBuilder, msgs: &[(&Message, u8)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (msg, channel) in msgs {
+            let mut buffer = [0u8; 128];
+            let len = builder.build_frame(msg, *channel, &mut buffer).unwrap();
+            out.extend_from_slice(&buffer[..len]);
+        }
+        out
+    }
+
+    fn data(timestamp_us: u64, direction: Direction, bytes: Vec<u8>) -> Record {
+        Record::Data { timestamp_us, session_id: 1, direction, bytes, truncated: 0 }
+    }
+
+    #[test]
+    fn test_frames_and_rtt() {
+        let mut tx = FrameBuilder::new();
+        let mut rx = FrameBuilder::new();
+        let mut analyzer = Analyzer::default();
+        let mut out = Vec::new();
+
+        let ping = frames(&mut tx, &[(&Message::Ping { timestamp: 9 }, 0)]);
+        let pong = frames(&mut rx, &[(&Message::Pong { timestamp: 9 }, 0)]);
+        analyzer.process(&data(1_000, Direction::Tx, ping), &mut out);
+        analyzer.process(&data(3_500, Direction::Rx, pong), &mut out);
+
+        assert!(matches!(&out[0], Event::Frame(f) if f.summary == "Ping { timestamp: 9 }"));
+        assert!(out.contains(&Event::Rtt {
+            timestamp_us: 3_500,
+            direction: Side::Tx,
+            ping: 9,
+            rtt_us: 2_500,
+        }));
+
+        let summary = analyzer.finish(&mut out);
+        assert_eq!(summary.frames, 2);
+        assert_eq!(summary.rtt_avg_us, Some(2_500));
+        assert_eq!(summary.sequence_gaps, 0);
+    }
+
+    #[test]
+    fn test_sequence_gap_and_crc_error() {
+        let mut builder = FrameBuilder::new();
+        let a = frames(&mut builder, &[(&Message::Ack { sequence: 0 }, 1)]);
+        let mut b = frames(&mut builder, &[(&Message::Ack { sequence: 1 }, 1)]);
+        let c = frames(&mut builder, &[(&Message::Ack { sequence: 2 }, 1)]);
+        let last = b.len() - 1;
+        b[last] ^= 0xFF;
+
+        let mut stream = a;
+        stream.extend_from_slice(&b);
+        stream.extend_from_slice(&c);
+
+        let mut analyzer = Analyzer::new(AnalyzerConfig { frames: false, ..Default::default() });
+        let mut out = Vec::new();
+        analyzer.process(&data(0, Direction::Rx, stream), &mut out);
+        let summary = analyzer.finish(&mut out);
+
+        assert_eq!(summary.crc_errors, 1);
+        assert_eq!(summary.sequence_gaps, 1);
+        assert_eq!(summary.missing_frames, 1);
+        assert!(out.iter().any(|e| matches!(e, Event::SequenceGap { expected: 1, got: 2, .. })));
+    }
+
+    #[test]
+    fn test_bandwidth_intervals() {
+        let mut builder = FrameBuilder::new();
+        let mut analyzer = Analyzer::new(AnalyzerConfig { interval_us: 1_000, frames: false });
+        let mut out = Vec::new();
+
+        let frame = frames(&mut builder, &[(&Message::Ack { sequence: 0 }, 2)]);
+        let len = frame.len() as u64;
+        analyzer.process(&data(100, Direction::Rx, frame), &mut out);
+        let frame = frames(&mut builder, &[(&Message::Ack { sequence: 1 }, 2)]);
+        analyzer.process(&data(2_100, Direction::Rx, frame), &mut out);
+        analyzer.finish(&mut out);
+
+        let bandwidth: Vec<_> = out
+            .iter()
+            .filter_map(|e| match e {
+                Event::Bandwidth(b) => Some((b.start_us, b.channel, b.bytes)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bandwidth, vec![(0, 2, len), (2_000, 2, len)]);
+    }
+
+    #[test]
+    fn test_session_events_and_json() {
+        let mut builder = FrameBuilder::new();
+        let mut analyzer = Analyzer::default();
+        let mut out = Vec::new();
+
+        analyzer.process(&Record::SessionStart { timestamp_us: 0, session_id: 4 }, &mut out);
+        let msg = Message::Control(ControlMessage::HandshakeResponse {
+            version: 1,
+            features: 0,
+            session_id: 4,
+        });
+        analyzer.process(&data(10, Direction::Tx, frames(&mut builder, &[(&msg, 0)])), &mut out);
+
+        assert_eq!(
+            out[0],
+            Event::Session { timestamp_us: 0, kind: SessionKind::Start, value: 4 }
+        );
+        assert_eq!(
+            out[1],
+            Event::Session { timestamp_us: 10, kind: SessionKind::Handshake, value: 4 }
+        );
+        let json = serde_json::to_string(&out[1]).unwrap();
+        assert_eq!(
+            json,
+            r#"{"event":"session","timestamp_us":10,"kind":"handshake","value":4}"#
+        );
+        assert_eq!(out[1].to_string(), "     0.000010 == handshake, session 4");
+    }
+}
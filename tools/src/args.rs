@@ -0,0 +1,95 @@
+//! # Command-Line Arguments
+//!
+//! Minimal flag parsing shared by the tools, so they need no CLI framework.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+/// Errors while parsing arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    /// A flag was given without its value
+    MissingValue(String),
+    /// A flag value did not parse
+    InvalidValue {
+        /// The flag
+        flag: String,
+        /// The rejected value
+        value: String,
+    },
+    /// An unrecognized flag
+    Unknown(String),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            ArgError::InvalidValue { flag, value } => write!(f, "invalid value for {flag}: {value}"),
+            ArgError::Unknown(flag) => write!(f, "unknown option {flag}"),
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+/// Argument cursor
+pub struct Args {
+    args: VecDeque<String>,
+}
+
+impl Args {
+    /// Arguments of the current process, without the program name
+    pub fn from_env() -> Self {
+        Self::new(std::env::args().skip(1))
+    }
+
+    /// Wrap an argument list
+    pub fn new<I: IntoIterator<Item = String>>(args: I) -> Self {
+        Self {
+            args: args.into_iter().collect(),
+        }
+    }
+
+    /// Take the next argument
+    pub fn next_arg(&mut self) -> Option<String> {
+        self.args.pop_front()
+    }
+
+    /// Take and parse the value following `flag`
+    pub fn value<T: FromStr>(&mut self, flag: &str) -> Result<T, ArgError> {
+        let value = self
+            .args
+            .pop_front()
+            .ok_or_else(|| ArgError::MissingValue(flag.to_string()))?;
+        value.parse().map_err(|_| ArgError::InvalidValue {
+            flag: flag.to_string(),
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values() {
+        let mut args = Args::new(["--port", "5288", "--speed", "fast"].map(String::from));
+        assert_eq!(args.next_arg().as_deref(), Some("--port"));
+        assert_eq!(args.value::<u16>("--port"), Ok(5288));
+        args.next_arg();
+        assert_eq!(
+            args.value::<f64>("--speed"),
+            Err(ArgError::InvalidValue {
+                flag: "--speed".into(),
+                value: "fast".into()
+            })
+        );
+        assert_eq!(
+            args.value::<u16>("--port"),
+            Err(ArgError::MissingValue("--port".into()))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! # aa-analyze
+//!
+//! Decode recorded bridge traffic frame by frame.
+//!
+//! ```text
+//! aa-analyze [--json] [--interval-ms N] [--no-frames] FILE...
+//! ```
+//!
+//! `FILE` is a capture (`.aacap`, see `shared::capture`) or a hex dump (see
+//! `bridge_tools::input`). Several files, e.g. rotated captures, are analyzed
+//! as one continuous recording. `--json` prints one JSON object per line.
+
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use bridge_tools::analyze::{Analyzer, AnalyzerConfig, Event};
+use bridge_tools::args::{ArgError, Args};
+use bridge_tools::input;
+
+const USAGE: &str = "usage: aa-analyze [--json] [--interval-ms N] [--no-frames] FILE...";
+
+struct Options {
+    json: bool,
+    config: AnalyzerConfig,
+    files: Vec<PathBuf>,
+}
+
+fn parse_options(mut args: Args) -> Result<Options, ArgError> {
+    let mut options = Options {
+        json: false,
+        config: AnalyzerConfig::default(),
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next_arg() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--no-frames" => options.config.frames = false,
+            "--interval-ms" => options.config.interval_us = args.value::<u64>(&arg)? * 1000,
+            flag if flag.starts_with("--") => return Err(ArgError::Unknown(arg)),
+            _ => options.files.push(arg.into()),
+        }
+    }
+    Ok(options)
+}
+
+fn emit(out: &mut impl Write, events: &mut Vec<Event>, json: bool) -> io::Result<()> {
+    for event in events.drain(..) {
+        if json {
+            serde_json::to_writer(&mut *out, &event)?;
+            writeln!(out)?;
+        } else {
+            writeln!(out, "{event}")?;
+        }
+    }
+    Ok(())
+}
+
+fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let mut analyzer = Analyzer::new(options.config);
+    let mut events = Vec::new();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    for path in &options.files {
+        let records = input::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        for record in &records {
+            analyzer.process(record, &mut events);
+            emit(&mut out, &mut events, options.json)?;
+        }
+    }
+
+    analyzer.finish(&mut events);
+    emit(&mut out, &mut events, options.json)?;
+    out.flush()?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(Args::from_env()) {
+        Ok(options) if !options.files.is_empty() => options,
+        Ok(_) => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("aa-analyze: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Output piped into `head` and closed early
+        Err(e) if e.downcast_ref::<io::Error>().map(io::Error::kind) == Some(io::ErrorKind::BrokenPipe) => {
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("aa-analyze: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
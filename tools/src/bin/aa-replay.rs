@@ -0,0 +1,220 @@
+//! # aa-replay
+//!
+//! Replay one side of a recorded conversation against a live peer.
+//!
+//! ```text
+//! aa-replay [--speed X] [--loop N] [--send tx|rx] [--no-verify] [--settle-ms N] HOST:PORT FILE...
+//! ```
+//!
+//! Frames from the `--send` direction of the capture (default `tx`, what the
+//! recording endpoint sent) go out with their original spacing divided by
+//! `--speed`. `--loop 0` repeats forever. Frames are renumbered and session
+//! IDs remapped so the stream stays valid for the peer. Unless `--no-verify`
+//! is given, the peer's answers are checked against the other direction of
+//! the capture and the exit status reports whether they matched.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bridge_tools::args::{ArgError, Args};
+use bridge_tools::deframe::{Deframed, Deframer};
+use bridge_tools::input;
+use bridge_tools::replay::{Rewriter, Script, Verifier};
+use shared::capture::Direction;
+
+const USAGE: &str = "usage: aa-replay [--speed X] [--loop N] [--send tx|rx] [--no-verify] \
+                     [--settle-ms N] HOST:PORT FILE...";
+
+struct Options {
+    speed: f64,
+    passes: u32,
+    send: Direction,
+    verify: bool,
+    settle: Duration,
+    peer: String,
+    files: Vec<PathBuf>,
+}
+
+fn parse_options(mut args: Args) -> Result<Options, ArgError> {
+    let mut options = Options {
+        speed: 1.0,
+        passes: 1,
+        send: Direction::Tx,
+        verify: true,
+        settle: Duration::from_secs(1),
+        peer: String::new(),
+        files: Vec::new(),
+    };
+    while let Some(arg) = args.next_arg() {
+        match arg.as_str() {
+            "--speed" => {
+                options.speed = args.value(&arg)?;
+                if options.speed.is_nan() || options.speed <= 0.0 {
+                    let value = options.speed.to_string();
+                    return Err(ArgError::InvalidValue { flag: arg, value });
+                }
+            }
+            "--loop" => options.passes = args.value(&arg)?,
+            "--send" => {
+                options.send = match args.value::<String>(&arg)?.as_str() {
+                    "tx" => Direction::Tx,
+                    "rx" => Direction::Rx,
+                    other => {
+                        return Err(ArgError::InvalidValue { flag: arg, value: other.into() })
+                    }
+                }
+            }
+            "--no-verify" => options.verify = false,
+            "--settle-ms" => options.settle = Duration::from_millis(args.value(&arg)?),
+            flag if flag.starts_with("--") => return Err(ArgError::Unknown(arg)),
+            _ if options.peer.is_empty() => options.peer = arg,
+            _ => options.files.push(arg.into()),
+        }
+    }
+    Ok(options)
+}
+
+/// Forward deframed peer frames to the main thread until the connection ends
+fn spawn_reader(mut stream: TcpStream) -> Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut deframer = Deframer::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => n,
+            };
+            deframer.push(&buf[..n]);
+            while let Some(item) = deframer.next_frame() {
+                if let Deframed::Frame { bytes, .. } = item {
+                    if tx.send(bytes).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// Feed peer frames to the verifier until `deadline`
+///
+/// Returns `false` once the peer has closed the connection.
+fn drain(responses: &Receiver<Vec<u8>>, verifier: &mut Verifier, deadline: Instant) -> bool {
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match responses.recv_timeout(timeout) {
+            Ok(frame) => verifier.observe(&frame),
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    for path in &options.files {
+        files.push(input::load(path).map_err(|e| format!("{}: {e}", path.display()))?);
+    }
+    // Rotated captures may be listed in any order; replay them oldest first
+    files.sort_by_key(|records| records.first().map_or(0, |r| r.timestamp_us()));
+    let records: Vec<_> = files.into_iter().flatten().collect();
+    let script = Script::build(&records, options.send);
+    eprintln!(
+        "replaying {} frames in {} steps over {:.3} s to {}",
+        script.frames_to_send(),
+        script.steps.len(),
+        script.duration_us() as f64 / 1e6 / options.speed,
+        options.peer
+    );
+
+    let mut stream = TcpStream::connect(&options.peer)?;
+    stream.set_nodelay(true)?;
+    let responses = spawn_reader(stream.try_clone()?);
+
+    let mut rewriter = Rewriter::new();
+    let mut verifier = Verifier::new();
+    let mut connected = true;
+    let mut out = Vec::new();
+    let mut pass = 0;
+
+    'passes: while connected && (options.passes == 0 || pass < options.passes) {
+        let start = Instant::now();
+        for step in &script.steps {
+            let due = start + Duration::from_secs_f64(step.at_us as f64 / 1e6 / options.speed);
+            connected = drain(&responses, &mut verifier, due);
+            if !connected {
+                eprintln!("peer closed the connection");
+                break 'passes;
+            }
+
+            out.clear();
+            for frame in &step.send {
+                rewriter
+                    .rewrite(frame, verifier.sessions(), &mut out)
+                    .map_err(|e| format!("cannot rewrite frame: {e:?}"))?;
+            }
+            stream.write_all(&out)?;
+
+            if options.verify {
+                for frame in &step.expect {
+                    verifier.expect(frame);
+                }
+            }
+        }
+        pass += 1;
+        eprintln!("pass {pass} done");
+    }
+
+    // Give the peer time to answer the last requests
+    let deadline = Instant::now() + options.settle;
+    while connected && verifier.pending() > 0 && Instant::now() < deadline {
+        connected = drain(&responses, &mut verifier, deadline);
+    }
+
+    if rewriter.skipped() > 0 {
+        eprintln!("skipped {} frames of unknown type", rewriter.skipped());
+    }
+    if !options.verify {
+        return Ok(true);
+    }
+
+    let report = verifier.finish();
+    for detail in &report.details {
+        println!("{detail}");
+    }
+    println!(
+        "matched {} missing {} unexpected {} undecodable {}",
+        report.matched, report.missing, report.unexpected, report.undecodable
+    );
+    Ok(report.is_clean())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(Args::from_env()) {
+        Ok(options) if !options.peer.is_empty() && !options.files.is_empty() => options,
+        Ok(_) => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("aa-replay: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("aa-replay: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! # aa-sim
+//!
+//! Mock ESP32 dongle for developing the Android side without hardware.
+//!
+//! ```text
+//! aa-sim [--bind ADDR] [--port N] [--sink] [--first-session N] [--verbose]
+//!        [--scenario FILE] [--seed N]
+//! ```
+//!
+//! Listens on `0.0.0.0:5288` by default, like the firmware's access point
+//! server. Point the app (or `aa-replay`) at this machine's address instead
+//! of `192.168.4.1`. `--scenario` injects faults (see `bridge_tools::fault`);
+//! `--seed` overrides the scenario's seed.
+
+use std::io;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use bridge_tools::args::{ArgError, Args};
+use bridge_tools::fault::Scenario;
+use bridge_tools::sim::{Log, SimConfig, Simulator, DEFAULT_PORT};
+
+const USAGE: &str = "usage: aa-sim [--bind ADDR] [--port N] [--sink] [--first-session N] \
+                     [--verbose] [--scenario FILE] [--seed N]";
+
+struct Options {
+    bind: String,
+    port: u16,
+    first_session: u32,
+    config: SimConfig,
+    scenario: Option<PathBuf>,
+    seed: Option<u64>,
+}
+
+fn parse_options(mut args: Args) -> Result<Options, ArgError> {
+    let mut options = Options {
+        bind: "0.0.0.0".into(),
+        port: DEFAULT_PORT,
+        first_session: 1,
+        config: SimConfig::default(),
+        scenario: None,
+        seed: None,
+    };
+    while let Some(arg) = args.next_arg() {
+        match arg.as_str() {
+            "--bind" => options.bind = args.value(&arg)?,
+            "--port" => options.port = args.value(&arg)?,
+            "--sink" => options.config.echo = false,
+            "--first-session" => options.first_session = args.value(&arg)?,
+            "--verbose" | "-v" => options.config.verbose = true,
+            "--scenario" => options.scenario = Some(args.value(&arg)?),
+            "--seed" => options.seed = Some(args.value(&arg)?),
+            _ => return Err(ArgError::Unknown(arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(Args::from_env()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("aa-sim: {e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut scenario = match &options.scenario {
+        Some(path) => match Scenario::load(path) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                eprintln!("aa-sim: {}: {e}", path.display());
+                return ExitCode::from(2);
+            }
+        },
+        None => Scenario::default(),
+    };
+    if let Some(seed) = options.seed {
+        scenario.seed = seed;
+    }
+
+    let listener = match TcpListener::bind((options.bind.as_str(), options.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "aa-sim: cannot listen on {}:{}: {e}",
+                options.bind, options.port
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "[INFO] Simulated dongle listening on {}:{} ({} mode)",
+        options.bind,
+        options.port,
+        if options.config.echo { "echo" } else { "sink" }
+    );
+    if !scenario.rules.is_empty() {
+        println!(
+            "[INFO] Injecting {} fault rule(s), seed {}",
+            scenario.rules.len(),
+            scenario.seed
+        );
+    }
+
+    match Simulator::new(options.config, options.first_session)
+        .with_scenario(scenario)
+        .with_log(Log::to(io::stdout()))
+        .run(listener)
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("aa-sim: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! # Stream Deframing
+//!
+//! Bridge traffic is a byte stream: one transfer may carry part of a frame or
+//! several frames. [`Deframer`] buffers the stream and yields whole,
+//! CRC-checked frames, resynchronizing on [`FRAME_MAGIC`] after garbage or
+//! corruption.
+
+use shared::protocol::{FrameBuilder, FrameError, FRAME_MAGIC};
+
+/// One item recovered from the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deframed {
+    /// A complete frame that passed the CRC check
+    Frame {
+        /// Stream offset of the first byte
+        offset: u64,
+        /// Frame bytes, magic through CRC
+        bytes: Vec<u8>,
+    },
+    /// A frame start whose CRC did not match; scanning resumes after its magic
+    CrcError {
+        /// Stream offset of the magic
+        offset: u64,
+    },
+    /// Bytes discarded while looking for the next frame magic
+    Skipped {
+        /// Stream offset of the first discarded byte
+        offset: u64,
+        /// Number of bytes discarded
+        len: usize,
+    },
+}
+
+/// Reassembles frames from a byte stream
+#[derive(Debug, Default)]
+pub struct Deframer {
+    buf: Vec<u8>,
+    offset: u64,
+}
+
+impl Deframer {
+    /// Create an empty deframer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append stream bytes
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Bytes buffered but not yet returned
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Total stream bytes consumed so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Return the next item, or `None` if more data is needed
+    pub fn next_frame(&mut self) -> Option<Deframed> {
+        let start = self
+            .buf
+            .windows(FRAME_MAGIC.len())
+            .position(|w| w == FRAME_MAGIC);
+
+        match start {
+            Some(0) => {}
+            Some(n) => return Some(self.skip(n)),
+            None => {
+                // Keep a possible partial magic at the tail
+                let keep = self.buf.len().min(FRAME_MAGIC.len() - 1);
+                let n = self.buf.len() - keep;
+                return (n > 0).then(|| self.skip(n));
+            }
+        }
+
+        match FrameBuilder::parse_raw_frame(&self.buf) {
+            Ok(raw) => {
+                let offset = self.offset;
+                let len = raw.frame_len();
+                let bytes = self.buf[..len].to_vec();
+                self.consume(len);
+                Some(Deframed::Frame { offset, bytes })
+            }
+            Err(FrameError::TooShort) => None,
+            Err(_) => {
+                let offset = self.offset;
+                self.consume(1);
+                Some(Deframed::CrcError { offset })
+            }
+        }
+    }
+
+    /// Discard whatever is left at the end of the stream
+    pub fn finish(&mut self) -> Option<Deframed> {
+        let n = self.buf.len();
+        (n > 0).then(|| self.skip(n))
+    }
+
+    /// Drop all state (e.g. on a new session)
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.offset = 0;
+    }
+
+    fn skip(&mut self, len: usize) -> Deframed {
+        let offset = self.offset;
+        self.consume(len);
+        Deframed::Skipped { offset, len }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.buf.drain(..len);
+        self.offset += len as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::protocol::Message;
+
+    fn frame(msg: &Message) -> Vec<u8> {
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+        let len = builder.build_frame(msg, 1, &mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    #[test]
+    fn test_split_and_merged_transfers() {
+        let a = frame(&Message::Ping { timestamp: 1 });
+        let b = frame(&Message::Pong { timestamp: 1 });
+        let mut stream = a.clone();
+        stream.extend_from_slice(&b);
+
+        let mut deframer = Deframer::new();
+        deframer.push(&stream[..5]);
+        assert_eq!(deframer.next_frame(), None);
+        deframer.push(&stream[5..]);
+
+        assert_eq!(
+            deframer.next_frame(),
+            Some(Deframed::Frame { offset: 0, bytes: a.clone() })
+        );
+        assert_eq!(
+            deframer.next_frame(),
+            Some(Deframed::Frame { offset: a.len() as u64, bytes: b })
+        );
+        assert_eq!(deframer.next_frame(), None);
+        assert_eq!(deframer.buffered(), 0);
+    }
+
+    #[test]
+    fn test_resync_after_garbage() {
+        let a = frame(&Message::Ack { sequence: 3 });
+        let mut stream = vec![0x11, 0x22, 0x33];
+        stream.extend_from_slice(&a);
+
+        let mut deframer = Deframer::new();
+        deframer.push(&stream);
+        assert_eq!(deframer.next_frame(), Some(Deframed::Skipped { offset: 0, len: 3 }));
+        assert!(matches!(deframer.next_frame(), Some(Deframed::Frame { offset: 3, .. })));
+    }
+
+    #[test]
+    fn test_crc_error_then_recovery() {
+        let mut bad = frame(&Message::Ping { timestamp: 7 });
+        let last = bad.len() - 1;
+        bad[last] ^= 0xFF;
+        let good = frame(&Message::Ping { timestamp: 8 });
+
+        let mut deframer = Deframer::new();
+        deframer.push(&bad);
+        deframer.push(&good);
+
+        assert_eq!(deframer.next_frame(), Some(Deframed::CrcError { offset: 0 }));
+        assert_eq!(
+            deframer.next_frame(),
+            Some(Deframed::Skipped { offset: 1, len: bad.len() - 1 })
+        );
+        assert!(matches!(deframer.next_frame(), Some(Deframed::Frame { .. })));
+        assert_eq!(deframer.finish(), None);
+    }
+}
@@ -0,0 +1,540 @@
+//! # Simulator Fault Injection
+//!
+//! Makes the simulated dongle misbehave on demand, driven by a scenario file
+//! so the same failures can be reproduced in automated tests.
+//!
+//! ## Scenario Format
+//!
+//! ```text
+//! # Comments start with '#'
+//! seed 42
+//!
+//! # <trigger> <action> [p=PROBABILITY] [times=N]
+//! handshake delay 2s
+//! handshake version 9
+//! pong drop p=0.3
+//! reply corrupt-crc p=0.01
+//! data truncate 7 times=1
+//! stats unknown-type 0x42
+//! frame 20 stall 5000
+//! after 30s reset
+//! ```
+//!
+//! **Triggers**: `handshake`, `pong`, `stats`, `data` and `reply` (any
+//! outgoing frame) fire on replies; `read` fires on each read from the
+//! client; `frame N` on the Nth frame received (from 1); `after DURATION`
+//! once the connection is that old (once unless `times` says otherwise).
+//!
+//! **Actions**: `drop` (skip the reply or ignore the received frame),
+//! `delay D` (hold the reply), `corrupt-crc`, `truncate N` (send only the
+//! first N bytes), `version V` (handshake only), `unknown-type T` (send the
+//! reply with message type byte T and a valid CRC), `stall D` (stop reading
+//! for D), `reset` (abort the connection with a TCP reset).
+//!
+//! Durations are milliseconds, or take an `ms` or `s` suffix. Probabilities
+//! are drawn from a [`Rng`] seeded per connection, so a scenario run with the
+//! same seed and the same client traffic fails the same way every time.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use shared::protocol::{crc16, ControlMessage, Message};
+use shared::rng::Rng;
+
+/// When a rule is considered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Outgoing `HandshakeResponse`
+    Handshake,
+    /// Outgoing `Pong`
+    Pong,
+    /// Outgoing `StatsResponse`
+    Stats,
+    /// Outgoing `Data`
+    Data,
+    /// Any outgoing frame
+    Reply,
+    /// Each read from the client
+    Read,
+    /// The Nth frame received from the client (1-based)
+    Frame(u64),
+    /// Connection age in milliseconds
+    After(u64),
+}
+
+impl Trigger {
+    fn is_reply(self) -> bool {
+        matches!(
+            self,
+            Trigger::Handshake | Trigger::Pong | Trigger::Stats | Trigger::Data | Trigger::Reply
+        )
+    }
+}
+
+/// What a rule does when it fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Skip the reply, or ignore the received frame
+    Drop,
+    /// Hold the reply for some milliseconds
+    Delay(u64),
+    /// Flip the CRC of the reply
+    CorruptCrc,
+    /// Send only the first N bytes of the reply
+    Truncate(usize),
+    /// Answer the handshake with this protocol version
+    Version(u8),
+    /// Send the reply with this message type byte
+    UnknownType(u8),
+    /// Stop reading for some milliseconds
+    Stall(u64),
+    /// Abort the connection
+    Reset,
+}
+
+/// One scenario line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    /// When the rule is considered
+    pub trigger: Trigger,
+    /// What it does
+    pub action: Action,
+    /// Chance of firing each time it is considered
+    pub probability: f64,
+    /// Maximum number of firings per connection
+    pub times: Option<u32>,
+}
+
+/// A parsed scenario file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    /// Seed for probabilistic rules
+    pub seed: u64,
+    /// Rules in file order
+    pub rules: Vec<Rule>,
+}
+
+/// Errors when loading a scenario
+#[derive(Debug)]
+pub enum ScenarioError {
+    /// Reading the file failed
+    Io(io::Error),
+    /// A line did not parse
+    Syntax {
+        /// 1-based line number
+        line: usize,
+        /// What was wrong
+        message: String,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "{e}"),
+            ScenarioError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<io::Error> for ScenarioError {
+    fn from(e: io::Error) -> Self {
+        ScenarioError::Io(e)
+    }
+}
+
+impl Scenario {
+    /// Load a scenario file
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parse scenario text (see the module docs for the format)
+    pub fn parse(text: &str) -> Result<Self, ScenarioError> {
+        let mut scenario = Scenario::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            let syntax = |message: String| ScenarioError::Syntax {
+                line: index + 1,
+                message,
+            };
+
+            if tokens[0] == "seed" {
+                scenario.seed = match tokens.as_slice() {
+                    [_, seed] => parse_int(seed).map_err(syntax)?,
+                    _ => return Err(syntax("expected `seed N`".into())),
+                };
+                continue;
+            }
+            scenario.rules.push(parse_rule(&tokens).map_err(syntax)?);
+        }
+        Ok(scenario)
+    }
+}
+
+fn parse_rule(tokens: &[&str]) -> Result<Rule, String> {
+    let mut tokens = tokens.iter().copied();
+    let mut arg = |what: &str| tokens.next().ok_or(format!("missing {what}"));
+
+    let trigger = match arg("trigger")? {
+        "handshake" => Trigger::Handshake,
+        "pong" => Trigger::Pong,
+        "stats" => Trigger::Stats,
+        "data" => Trigger::Data,
+        "reply" => Trigger::Reply,
+        "read" => Trigger::Read,
+        "frame" => Trigger::Frame(parse_int(arg("frame number")?)?),
+        "after" => Trigger::After(parse_ms(arg("duration")?)?),
+        other => return Err(format!("unknown trigger `{other}`")),
+    };
+
+    let action = match arg("action")? {
+        "drop" => Action::Drop,
+        "delay" => Action::Delay(parse_ms(arg("duration")?)?),
+        "corrupt-crc" => Action::CorruptCrc,
+        "truncate" => Action::Truncate(parse_int(arg("length")?)?),
+        "version" => Action::Version(parse_int(arg("version")?)?),
+        "unknown-type" => Action::UnknownType(parse_int(arg("type")?)?),
+        "stall" => Action::Stall(parse_ms(arg("duration")?)?),
+        "reset" => Action::Reset,
+        other => return Err(format!("unknown action `{other}`")),
+    };
+
+    let reply_only = matches!(
+        action,
+        Action::Delay(_)
+            | Action::CorruptCrc
+            | Action::Truncate(_)
+            | Action::Version(_)
+            | Action::UnknownType(_)
+    );
+    if reply_only && !trigger.is_reply() {
+        return Err(format!("{action:?} needs a reply trigger"));
+    }
+    if matches!(action, Action::Version(_)) && trigger != Trigger::Handshake {
+        return Err("version needs the handshake trigger".into());
+    }
+    if action == Action::Drop && !(trigger.is_reply() || matches!(trigger, Trigger::Frame(_))) {
+        return Err("drop needs a reply or frame trigger".into());
+    }
+
+    let mut rule = Rule {
+        trigger,
+        action,
+        probability: 1.0,
+        times: matches!(trigger, Trigger::After(_)).then_some(1),
+    };
+    for option in tokens {
+        match option.split_once('=') {
+            Some(("p", value)) => {
+                rule.probability = value
+                    .parse()
+                    .ok()
+                    .filter(|p| (0.0..=1.0).contains(p))
+                    .ok_or(format!("invalid probability `{value}`"))?;
+            }
+            Some(("times", value)) => rule.times = Some(parse_int(value)?),
+            _ => return Err(format!("unexpected `{option}`")),
+        }
+    }
+    Ok(rule)
+}
+
+fn parse_int<T: TryFrom<u64>>(text: &str) -> Result<T, String> {
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    value
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or(format!("invalid number `{text}`"))
+}
+
+fn parse_ms(text: &str) -> Result<u64, String> {
+    if let Some(ms) = text.strip_suffix("ms") {
+        parse_int(ms)
+    } else if let Some(s) = text.strip_suffix('s') {
+        parse_int::<u64>(s).map(|s| s * 1000)
+    } else {
+        parse_int(text)
+    }
+}
+
+/// Combined effect of the rules that fired for one event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Skip the reply, or ignore the received frame
+    pub drop: bool,
+    /// Hold the reply this long (ms)
+    pub delay_ms: u64,
+    /// Flip the reply's CRC
+    pub corrupt_crc: bool,
+    /// Send only this many bytes of the reply
+    pub truncate: Option<usize>,
+    /// Rewrite the handshake version
+    pub version: Option<u8>,
+    /// Rewrite the message type byte
+    pub unknown_type: Option<u8>,
+    /// Stop reading this long (ms)
+    pub stall_ms: u64,
+    /// Abort the connection
+    pub reset: bool,
+}
+
+impl Plan {
+    /// Check if nothing fired
+    pub fn is_noop(&self) -> bool {
+        *self == Plan::default()
+    }
+
+    /// Apply message-level faults before framing
+    pub fn apply_to_message(&self, msg: &mut Message) {
+        if let (Some(v), Message::Control(ControlMessage::HandshakeResponse { version, .. })) =
+            (self.version, msg)
+        {
+            *version = v;
+        }
+    }
+
+    /// Apply byte-level faults to a built frame
+    pub fn apply_to_frame(&self, frame: &mut Vec<u8>) {
+        let len = frame.len();
+        if len < 13 {
+            return;
+        }
+        if let Some(msg_type) = self.unknown_type {
+            frame[10] = msg_type;
+            let crc = crc16(&frame[..len - 2]);
+            frame[len - 2..].copy_from_slice(&crc.to_le_bytes());
+        }
+        if self.corrupt_crc {
+            frame[len - 1] ^= 0xFF;
+        }
+        if let Some(n) = self.truncate {
+            frame.truncate(n);
+        }
+    }
+
+    fn merge(&mut self, action: Action) {
+        match action {
+            Action::Drop => self.drop = true,
+            Action::Delay(ms) => self.delay_ms += ms,
+            Action::CorruptCrc => self.corrupt_crc = true,
+            Action::Truncate(n) => self.truncate = Some(self.truncate.map_or(n, |t| t.min(n))),
+            Action::Version(v) => self.version = Some(v),
+            Action::UnknownType(t) => self.unknown_type = Some(t),
+            Action::Stall(ms) => self.stall_ms += ms,
+            Action::Reset => self.reset = true,
+        }
+    }
+}
+
+/// Per-connection rule evaluation
+pub struct FaultInjector {
+    rules: Vec<Rule>,
+    fired: Vec<u32>,
+    rng: Rng,
+    frames: u64,
+}
+
+impl FaultInjector {
+    /// Start a connection with the scenario's rules and seed
+    pub fn new(scenario: &Scenario) -> Self {
+        Self {
+            rules: scenario.rules.clone(),
+            fired: vec![0; scenario.rules.len()],
+            rng: Rng::new(scenario.seed),
+            frames: 0,
+        }
+    }
+
+    /// Check if any rule depends on connection age
+    pub fn has_timers(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|r| matches!(r.trigger, Trigger::After(_)))
+    }
+
+    /// Evaluate the rules for an outgoing message
+    pub fn on_reply(&mut self, msg: &Message) -> Plan {
+        self.evaluate(|trigger| match trigger {
+            Trigger::Reply => true,
+            Trigger::Handshake => matches!(
+                msg,
+                Message::Control(ControlMessage::HandshakeResponse { .. })
+            ),
+            Trigger::Pong => matches!(msg, Message::Pong { .. }),
+            Trigger::Stats => matches!(msg, Message::Control(ControlMessage::StatsResponse { .. })),
+            Trigger::Data => matches!(msg, Message::Data(_)),
+            _ => false,
+        })
+    }
+
+    /// Evaluate the rules for a frame received from the client
+    pub fn on_frame(&mut self) -> Plan {
+        self.frames += 1;
+        let frames = self.frames;
+        self.evaluate(|trigger| trigger == Trigger::Frame(frames))
+    }
+
+    /// Evaluate the rules for a read from the client
+    pub fn on_read(&mut self) -> Plan {
+        self.evaluate(|trigger| trigger == Trigger::Read)
+    }
+
+    /// Evaluate the time-based rules for a connection `elapsed_ms` old
+    pub fn on_tick(&mut self, elapsed_ms: u64) -> Plan {
+        self.evaluate(|trigger| matches!(trigger, Trigger::After(ms) if elapsed_ms >= ms))
+    }
+
+    fn evaluate(&mut self, matches: impl Fn(Trigger) -> bool) -> Plan {
+        let mut plan = Plan::default();
+        for (rule, fired) in self.rules.iter().zip(&mut self.fired) {
+            if !matches(rule.trigger) || rule.times.is_some_and(|t| *fired >= t) {
+                continue;
+            }
+            if self.rng.chance(rule.probability) {
+                *fired += 1;
+                plan.merge(rule.action);
+            }
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::protocol::{FrameBuilder, FrameError};
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = Scenario::parse(
+            "seed 0x2A\n\
+             handshake delay 2s   # slow start\n\
+             pong drop p=0.5 times=3\n\
+             after 1500ms reset\n",
+        )
+        .unwrap();
+        assert_eq!(scenario.seed, 42);
+        assert_eq!(
+            scenario.rules,
+            vec![
+                Rule {
+                    trigger: Trigger::Handshake,
+                    action: Action::Delay(2000),
+                    probability: 1.0,
+                    times: None,
+                },
+                Rule {
+                    trigger: Trigger::Pong,
+                    action: Action::Drop,
+                    probability: 0.5,
+                    times: Some(3),
+                },
+                Rule {
+                    trigger: Trigger::After(1500),
+                    action: Action::Reset,
+                    probability: 1.0,
+                    times: Some(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let line = |text: &str| match Scenario::parse(text) {
+            Err(ScenarioError::Syntax { line, .. }) => line,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(line("\nread corrupt-crc"), 2);
+        assert_eq!(line("pong version 2"), 1);
+        assert_eq!(line("pong drop p=1.5"), 1);
+        assert_eq!(line("frame x stall 10"), 1);
+        assert_eq!(line("explode"), 1);
+    }
+
+    #[test]
+    fn test_seeded_rules_are_reproducible() {
+        let scenario = Scenario::parse("seed 7\npong drop p=0.5\nframe 3 stall 100").unwrap();
+        let run = || {
+            let mut faults = FaultInjector::new(&scenario);
+            let pong = Message::Pong { timestamp: 0 };
+            let drops: Vec<bool> = (0..32).map(|_| faults.on_reply(&pong).drop).collect();
+            let stalls: Vec<u64> = (0..4).map(|_| faults.on_frame().stall_ms).collect();
+            (drops, stalls)
+        };
+
+        let (drops, stalls) = run();
+        assert_eq!(run(), (drops.clone(), stalls.clone()));
+        assert!(drops.iter().any(|d| *d) && drops.iter().any(|d| !*d));
+        assert_eq!(stalls, vec![0, 0, 100, 0]);
+    }
+
+    #[test]
+    fn test_timers_fire_once() {
+        let scenario = Scenario::parse("after 100 reset").unwrap();
+        let mut faults = FaultInjector::new(&scenario);
+        assert!(faults.has_timers());
+        assert!(!faults.on_tick(99).reset);
+        assert!(faults.on_tick(100).reset);
+        assert!(faults.on_tick(200).is_noop());
+    }
+
+    #[test]
+    fn test_frame_faults() {
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+        let len = builder
+            .build_frame(&Message::Pong { timestamp: 1 }, 0, &mut buffer)
+            .unwrap();
+        let frame = buffer[..len].to_vec();
+
+        let mut unknown = frame.clone();
+        Plan {
+            unknown_type: Some(0x42),
+            ..Plan::default()
+        }
+        .apply_to_frame(&mut unknown);
+        assert_eq!(
+            FrameBuilder::parse_raw_frame(&unknown).unwrap().msg_type,
+            0x42
+        );
+
+        let mut corrupt = frame.clone();
+        Plan {
+            corrupt_crc: true,
+            ..Plan::default()
+        }
+        .apply_to_frame(&mut corrupt);
+        assert_eq!(
+            FrameBuilder::parse_raw_frame(&corrupt),
+            Err(FrameError::CrcMismatch)
+        );
+
+        let mut msg = Message::Control(ControlMessage::HandshakeResponse {
+            version: 1,
+            features: 0,
+            session_id: 1,
+        });
+        Plan {
+            version: Some(9),
+            ..Plan::default()
+        }
+        .apply_to_message(&mut msg);
+        assert!(matches!(
+            msg,
+            Message::Control(ControlMessage::HandshakeResponse { version: 9, .. })
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+//! # Traffic Input
+//!
+//! Loads recorded traffic from capture files (see `shared::capture`) or from
+//! plain hex dumps into a list of [`Record`]s.
+//!
+//! ## Hex Dump Format
+//!
+//! ```text
+//! # comment
+//! < aa 57 49 46 00 00 05 00 ...     received bytes
+//! > aa57494601000500...             transmitted bytes
+//! 00000010: 01 02 03                offset prefixes are ignored
+//! ```
+//!
+//! Each line is one transfer. A leading `<` or `>` sets the direction for
+//! that line and the lines after it (default: received). Parsing of a line
+//! stops at the first token that is not hex, so `hexdump -C` style ASCII
+//! columns are ignored. Hex dumps carry no timing; all records get
+//! timestamp 0.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use shared::capture::{parse_capture, CaptureError, Direction, RecordKind, CAPTURE_MAGIC};
+
+/// One recorded event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    /// Transferred bytes
+    Data {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Session the transfer belongs to
+        session_id: u32,
+        /// Transfer direction
+        direction: Direction,
+        /// Bytes as captured
+        bytes: Vec<u8>,
+        /// Bytes cut off by the capture snap length
+        truncated: u32,
+    },
+    /// A session started
+    SessionStart {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// New session ID
+        session_id: u32,
+    },
+    /// A session ended
+    SessionEnd {
+        /// Capture timestamp in microseconds
+        timestamp_us: u64,
+        /// Ended session ID
+        session_id: u32,
+    },
+}
+
+impl Record {
+    /// Capture timestamp in microseconds
+    pub fn timestamp_us(&self) -> u64 {
+        match self {
+            Record::Data { timestamp_us, .. }
+            | Record::SessionStart { timestamp_us, .. }
+            | Record::SessionEnd { timestamp_us, .. } => *timestamp_us,
+        }
+    }
+}
+
+/// Errors when loading traffic
+#[derive(Debug)]
+pub enum InputError {
+    /// Reading the file failed
+    Io(io::Error),
+    /// Malformed capture file
+    Capture(CaptureError),
+    /// Malformed hex dump
+    Hex {
+        /// 1-based line number
+        line: usize,
+    },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "{e}"),
+            InputError::Capture(e) => write!(f, "invalid capture: {e:?}"),
+            InputError::Hex { line } => write!(f, "invalid hex dump at line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+impl From<CaptureError> for InputError {
+    fn from(e: CaptureError) -> Self {
+        InputError::Capture(e)
+    }
+}
+
+/// Load a capture file or hex dump, detected by content
+pub fn load(path: &Path) -> Result<Vec<Record>, InputError> {
+    let data = fs::read(path)?;
+    if data.starts_with(&CAPTURE_MAGIC) {
+        return parse_capture_file(&data);
+    }
+    let text = String::from_utf8(data).map_err(|_| InputError::Hex { line: 1 })?;
+    parse_hex_dump(&text)
+}
+
+/// Convert an in-memory capture file to records
+pub fn parse_capture_file(data: &[u8]) -> Result<Vec<Record>, InputError> {
+    let (_, records) = parse_capture(data)?;
+    records
+        .map(|record| {
+            let record = record?;
+            let h = record.header;
+            Ok(match h.kind {
+                RecordKind::Data => Record::Data {
+                    timestamp_us: h.timestamp_us,
+                    session_id: h.session_id,
+                    direction: h.direction,
+                    bytes: record.data.to_vec(),
+                    truncated: h.orig_len.saturating_sub(h.cap_len),
+                },
+                RecordKind::SessionStart => Record::SessionStart {
+                    timestamp_us: h.timestamp_us,
+                    session_id: h.session_id,
+                },
+                RecordKind::SessionEnd => Record::SessionEnd {
+                    timestamp_us: h.timestamp_us,
+                    session_id: h.session_id,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parse a hex dump (see the module docs for the format)
+pub fn parse_hex_dump(text: &str) -> Result<Vec<Record>, InputError> {
+    let mut records = Vec::new();
+    let mut direction = Direction::Rx;
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let line = if let Some(rest) = line.strip_prefix('<') {
+            direction = Direction::Rx;
+            rest
+        } else if let Some(rest) = line.strip_prefix('>') {
+            direction = Direction::Tx;
+            rest
+        } else {
+            line
+        };
+
+        let mut digits = String::new();
+        for (i, token) in line.split_whitespace().enumerate() {
+            if i == 0 && token.ends_with(':') {
+                continue;
+            }
+            if !token.chars().all(|c| c.is_ascii_hexdigit()) {
+                break;
+            }
+            digits.push_str(token);
+        }
+        if digits.is_empty() {
+            continue;
+        }
+        if !digits.len().is_multiple_of(2) {
+            return Err(InputError::Hex { line: index + 1 });
+        }
+
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| InputError::Hex { line: index + 1 })?;
+
+        records.push(Record::Data {
+            timestamp_us: 0,
+            session_id: 0,
+            direction,
+            bytes,
+            truncated: 0,
+        });
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::capture::{FileHeader, RecordHeader, CAPTURE_VERSION, CHANNEL_UNKNOWN};
+
+    #[test]
+    fn test_hex_dump() {
+        let text = "# header\n< aa57 49 46\n0010: 01 02 |..|\n> ff\n\n";
+        let records = parse_hex_dump(text).unwrap();
+        assert_eq!(records.len(), 3);
+        let bytes: Vec<_> = records
+            .iter()
+            .map(|r| match r {
+                Record::Data { direction, bytes, .. } => (*direction, bytes.clone()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(bytes[0], (Direction::Rx, vec![0xAA, 0x57, 0x49, 0x46]));
+        assert_eq!(bytes[1], (Direction::Rx, vec![0x01, 0x02]));
+        assert_eq!(bytes[2], (Direction::Tx, vec![0xFF]));
+
+        assert!(matches!(parse_hex_dump("abc"), Err(InputError::Hex { line: 1 })));
+    }
+
+    #[test]
+    fn test_capture_file() {
+        let mut file = FileHeader { version: CAPTURE_VERSION, snap_len: 4 }
+            .encode()
+            .to_vec();
+        file.extend_from_slice(
+            &RecordHeader {
+                timestamp_us: 10,
+                session_id: 5,
+                kind: RecordKind::Data,
+                direction: Direction::Tx,
+                channel: CHANNEL_UNKNOWN,
+                orig_len: 6,
+                cap_len: 4,
+            }
+            .encode(),
+        );
+        file.extend_from_slice(&[1, 2, 3, 4]);
+
+        let records = parse_capture_file(&file).unwrap();
+        assert_eq!(
+            records,
+            vec![Record::Data {
+                timestamp_us: 10,
+                session_id: 5,
+                direction: Direction::Tx,
+                bytes: vec![1, 2, 3, 4],
+                truncated: 2,
+            }]
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! # Bridge Developer Tools
+//!
+//! Host-side helpers behind the command-line tools in `src/bin`:
+//!
+//! - **Deframing**: Frame reassembly and resync over raw byte streams
+//! - **Input**: Loading capture files and hex dumps
+//! - **Analyzer**: Per-frame decoding, sequence/CRC/RTT/session tracking and
+//!   per-channel bandwidth (`aa-analyze`)
+//! - **Replay**: Re-driving a live peer from a capture and verifying its
+//!   responses (`aa-replay`)
+//! - **Simulator**: A mock dongle serving the bridge protocol on TCP 5288
+//!   (`aa-sim`)
+//! - **Fault Injection**: Scenario-driven, seeded misbehavior for the
+//!   simulator (`aa-sim --scenario`)
+//!
+//! These run on a development machine with `std`; nothing here is meant for
+//! the dongle or the phone.
+
+pub mod analyze;
+pub mod args;
+pub mod deframe;
+pub mod fault;
+pub mod input;
+pub mod replay;
+pub mod sim;
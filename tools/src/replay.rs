@@ -0,0 +1,431 @@
+//! # Capture Replay
+//!
+//! Re-drives a live peer (dongle or simulator) with one side of a recorded
+//! conversation and checks the peer's answers against the other side.
+//!
+//! ```text
+//!   capture ─► Script: [Step { at_us, send: [frame..], expect: [frame..] }, ..]
+//!                          │                    │
+//!                          ▼                    ▼
+//!                      Rewriter             Verifier ◄── live responses
+//!               (fresh sequence numbers,  (order-tolerant matching,
+//!                mapped session IDs)       session IDs normalized)
+//! ```
+//!
+//! Each step holds the frames the recording endpoint sent in one transfer and
+//! the frames it received before its next transfer, so expected responses are
+//! queued as soon as the request that caused them goes out.
+
+use std::collections::{HashMap, VecDeque};
+
+use shared::batch::parse_frames;
+use shared::capture::Direction;
+use shared::protocol::{
+    ControlMessage, FrameBuilder, FrameError, Message, MessageType, FLAG_BATCH, FRAME_OVERHEAD,
+};
+
+use crate::deframe::{Deframed, Deframer};
+use crate::input::Record;
+
+/// How far ahead in the expected queue a live message may match
+const MATCH_WINDOW: usize = 8;
+
+/// Maximum number of mismatch descriptions kept in a report
+const MAX_DETAILS: usize = 20;
+
+/// One scheduled transfer and the responses recorded after it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Step {
+    /// Offset from the first record in microseconds
+    ///
+    /// Records that go back in time (a wall-clock step) count as no gap.
+    pub at_us: u64,
+    /// Frames to send, as captured
+    pub send: Vec<Vec<u8>>,
+    /// Frames the peer answered with, as captured
+    pub expect: Vec<Vec<u8>>,
+}
+
+/// Replay schedule built from a capture
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    /// Steps in capture order
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    /// Build a script that sends the `send` direction of `records`
+    pub fn build(records: &[Record], send: Direction) -> Self {
+        let mut steps: Vec<Step> = Vec::new();
+        let mut deframers = [Deframer::new(), Deframer::new()];
+        let mut prev_us = records.first().map_or(0, Record::timestamp_us);
+        let mut elapsed_us = 0u64;
+
+        for record in records {
+            elapsed_us += record.timestamp_us().saturating_sub(prev_us);
+            prev_us = record.timestamp_us();
+            let Record::Data { direction, bytes, .. } = record else {
+                for deframer in &mut deframers {
+                    deframer.reset();
+                }
+                continue;
+            };
+
+            let deframer = &mut deframers[*direction as usize];
+            deframer.push(bytes);
+            let mut frames = Vec::new();
+            while let Some(item) = deframer.next_frame() {
+                if let Deframed::Frame { bytes, .. } = item {
+                    frames.push(bytes);
+                }
+            }
+            if frames.is_empty() {
+                continue;
+            }
+
+            if *direction == send {
+                steps.push(Step {
+                    at_us: elapsed_us,
+                    send: frames,
+                    expect: Vec::new(),
+                });
+            } else {
+                // Responses before the first send go into a leading empty step
+                if steps.is_empty() {
+                    steps.push(Step::default());
+                }
+                if let Some(step) = steps.last_mut() {
+                    step.expect.extend(frames);
+                }
+            }
+        }
+
+        Self { steps }
+    }
+
+    /// Total frames to send
+    pub fn frames_to_send(&self) -> usize {
+        self.steps.iter().map(|s| s.send.len()).sum()
+    }
+
+    /// Time of the last step in microseconds
+    pub fn duration_us(&self) -> u64 {
+        self.steps.last().map_or(0, |s| s.at_us)
+    }
+}
+
+/// Session IDs seen in the capture mapped to the live peer's
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionMap {
+    map: HashMap<u32, u32>,
+}
+
+impl SessionMap {
+    /// Record that captured session `captured` is `live` on this connection
+    pub fn learn(&mut self, captured: u32, live: u32) {
+        self.map.insert(captured, live);
+    }
+
+    /// Translate a captured session ID (unchanged if unknown)
+    pub fn map(&self, captured: u32) -> u32 {
+        self.map.get(&captured).copied().unwrap_or(captured)
+    }
+}
+
+/// Re-frames captured frames for a live connection
+#[derive(Default)]
+pub struct Rewriter {
+    builder: FrameBuilder,
+    skipped: u64,
+}
+
+impl Rewriter {
+    /// Create a rewriter whose sequence numbers start at 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frames that could not be rewritten (unknown message type)
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Re-frame `frame` with the next sequence number and mapped session IDs
+    ///
+    /// Appends the new frame to `out`. Frames of unknown type are skipped.
+    pub fn rewrite(
+        &mut self,
+        frame: &[u8],
+        sessions: &SessionMap,
+        out: &mut Vec<u8>,
+    ) -> Result<(), FrameError> {
+        let raw = FrameBuilder::parse_raw_frame(frame)?;
+        let Ok(msg_type) = MessageType::try_from(raw.msg_type) else {
+            self.skipped += 1;
+            return Ok(());
+        };
+
+        let mut remapped = [0u8; 32];
+        let mut payload = raw.payload;
+        if raw.header.flags & FLAG_BATCH == 0 {
+            if let Ok(Message::Control(ControlMessage::HandshakeResponse {
+                version,
+                features,
+                session_id,
+            })) = Message::deserialize(raw.payload)
+            {
+                let msg = Message::Control(ControlMessage::HandshakeResponse {
+                    version,
+                    features,
+                    session_id: sessions.map(session_id),
+                });
+                payload = msg
+                    .serialize(&mut remapped)
+                    .map_err(|_| FrameError::SerializationError)?;
+            }
+        }
+
+        let start = out.len();
+        out.resize(start + payload.len() + FRAME_OVERHEAD, 0);
+        let len = self.builder.build_raw_frame(
+            msg_type,
+            raw.header.channel,
+            raw.header.flags,
+            payload,
+            &mut out[start..],
+        )?;
+        out.truncate(start + len);
+        Ok(())
+    }
+}
+
+/// Outcome of verifying live responses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Responses that matched the capture
+    pub matched: u64,
+    /// Expected responses the peer never sent
+    pub missing: u64,
+    /// Live responses with no counterpart in the capture
+    pub unexpected: u64,
+    /// Live frames that did not decode
+    pub undecodable: u64,
+    /// First few problems, human-readable
+    pub details: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Check if the peer behaved exactly as recorded
+    pub fn is_clean(&self) -> bool {
+        self.missing == 0 && self.unexpected == 0 && self.undecodable == 0
+    }
+
+    fn note(&mut self, detail: String) {
+        if self.details.len() < MAX_DETAILS {
+            self.details.push(detail);
+        }
+    }
+}
+
+/// Matches live responses against the captured ones
+#[derive(Default)]
+pub struct Verifier {
+    expected: VecDeque<(u8, Message)>,
+    sessions: SessionMap,
+    report: VerifyReport,
+}
+
+impl Verifier {
+    /// Create a verifier with nothing expected
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Session IDs learned so far
+    pub fn sessions(&self) -> &SessionMap {
+        &self.sessions
+    }
+
+    /// Number of expected messages not yet seen
+    pub fn pending(&self) -> usize {
+        self.expected.len()
+    }
+
+    /// Queue the messages of a captured response frame
+    pub fn expect(&mut self, frame: &[u8]) {
+        for (header, msg) in parse_frames(frame).into_iter().flatten().flatten() {
+            self.expected.push_back((header.channel, msg));
+        }
+    }
+
+    /// Check the messages of a live frame
+    pub fn observe(&mut self, frame: &[u8]) {
+        let Ok(items) = parse_frames(frame) else {
+            self.report.undecodable += 1;
+            return;
+        };
+        for item in items {
+            match item {
+                Ok((header, msg)) => self.observe_message(header.channel, msg),
+                Err(_) => self.report.undecodable += 1,
+            }
+        }
+    }
+
+    /// Count everything still expected as missing and return the report
+    pub fn finish(mut self) -> VerifyReport {
+        while let Some((channel, msg)) = self.expected.pop_front() {
+            self.report.missing += 1;
+            self.report.note(format!("missing on ch={channel}: {msg:?}"));
+        }
+        self.report
+    }
+
+    fn observe_message(&mut self, channel: u8, msg: Message) {
+        let window = self.expected.len().min(MATCH_WINDOW);
+        let found = (0..window).find(|&i| {
+            let (ch, expected) = &self.expected[i];
+            *ch == channel && Self::matches(expected, &msg)
+        });
+
+        let Some(index) = found else {
+            self.report.unexpected += 1;
+            let detail = match self.expected.front() {
+                Some((ch, expected)) => {
+                    format!("ch={channel}: got {msg:?}, expected ch={ch}: {expected:?}")
+                }
+                None => format!("ch={channel}: unexpected {msg:?}"),
+            };
+            self.report.note(detail);
+            return;
+        };
+
+        // Everything the peer skipped over is missing
+        for _ in 0..index {
+            if let Some((ch, skipped)) = self.expected.pop_front() {
+                self.report.missing += 1;
+                self.report.note(format!("missing on ch={ch}: {skipped:?}"));
+            }
+        }
+        if let Some((_, expected)) = self.expected.pop_front() {
+            if let (
+                Message::Control(ControlMessage::HandshakeResponse { session_id: captured, .. }),
+                Message::Control(ControlMessage::HandshakeResponse { session_id: live, .. }),
+            ) = (&expected, &msg)
+            {
+                self.sessions.learn(*captured, *live);
+            }
+        }
+        self.report.matched += 1;
+    }
+
+    /// Compare ignoring values that legitimately differ between runs
+    fn matches(expected: &Message, live: &Message) -> bool {
+        use ControlMessage::*;
+        match (expected, live) {
+            (
+                Message::Control(HandshakeResponse { version: v1, features: f1, .. }),
+                Message::Control(HandshakeResponse { version: v2, features: f2, .. }),
+            ) => v1 == v2 && f1 == f2,
+            (Message::Control(StatsResponse { .. }), Message::Control(StatsResponse { .. })) => true,
+            _ => expected == live,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(builder: &mut FrameBuilder, msg: &Message) -> Vec<u8> {
+        let mut buffer = [0u8; 64];
+        let len = builder.build_frame(msg, 0, &mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    fn data(timestamp_us: u64, direction: Direction, bytes: Vec<u8>) -> Record {
+        Record::Data { timestamp_us, session_id: 1, direction, bytes, truncated: 0 }
+    }
+
+    fn handshake(session_id: u32) -> Message {
+        Message::Control(ControlMessage::HandshakeResponse { version: 1, features: 0, session_id })
+    }
+
+    #[test]
+    fn test_script_pairs_requests_with_responses() {
+        let mut phone = FrameBuilder::new();
+        let mut dongle = FrameBuilder::new();
+        let records = [
+            data(1_000, Direction::Tx, frame(&mut phone, &Message::Ping { timestamp: 1 })),
+            data(1_400, Direction::Rx, frame(&mut dongle, &Message::Pong { timestamp: 1 })),
+            data(5_000, Direction::Tx, frame(&mut phone, &Message::Ping { timestamp: 2 })),
+        ];
+
+        let script = Script::build(&records, Direction::Tx);
+        assert_eq!(script.steps.len(), 2);
+        assert_eq!(script.steps[0].at_us, 0);
+        assert_eq!(script.steps[0].expect.len(), 1);
+        assert_eq!(script.steps[1].at_us, 4_000);
+        assert!(script.steps[1].expect.is_empty());
+        assert_eq!(script.frames_to_send(), 2);
+    }
+
+    #[test]
+    fn test_script_tolerates_clock_going_back() {
+        let mut phone = FrameBuilder::new();
+        let ping = |builder: &mut FrameBuilder, timestamp| {
+            frame(builder, &Message::Ping { timestamp })
+        };
+        let records = [
+            data(5_000, Direction::Tx, ping(&mut phone, 1)),
+            // Wall clock stepped back 4ms
+            data(1_000, Direction::Tx, ping(&mut phone, 2)),
+            data(3_000, Direction::Tx, ping(&mut phone, 3)),
+        ];
+
+        let script = Script::build(&records, Direction::Tx);
+        let at: Vec<u64> = script.steps.iter().map(|s| s.at_us).collect();
+        assert_eq!(at, [0, 0, 2_000]);
+    }
+
+    #[test]
+    fn test_rewriter_renumbers_and_maps_sessions() {
+        let mut captured = FrameBuilder::new();
+        for _ in 0..100 {
+            captured.next_sequence();
+        }
+        let original = frame(&mut captured, &handshake(7));
+
+        let mut sessions = SessionMap::default();
+        sessions.learn(7, 42);
+        let mut rewriter = Rewriter::new();
+        let mut out = Vec::new();
+        rewriter.rewrite(&original, &sessions, &mut out).unwrap();
+
+        let (header, msg) = FrameBuilder::parse_frame(&out).unwrap();
+        assert_eq!(header.sequence, 0);
+        assert_eq!(msg, handshake(42));
+    }
+
+    #[test]
+    fn test_verifier_matching() {
+        let mut builder = FrameBuilder::new();
+        let mut verifier = Verifier::new();
+        verifier.expect(&frame(&mut builder, &handshake(7)));
+        verifier.expect(&frame(&mut builder, &Message::Pong { timestamp: 1 }));
+        verifier.expect(&frame(&mut builder, &Message::Pong { timestamp: 2 }));
+
+        // Different session ID still matches and is learned
+        verifier.observe(&frame(&mut builder, &handshake(99)));
+        assert_eq!(verifier.sessions().map(7), 99);
+
+        // Pong 1 never arrives; pong 2 matches past it; pong 3 is unexpected
+        verifier.observe(&frame(&mut builder, &Message::Pong { timestamp: 2 }));
+        verifier.observe(&frame(&mut builder, &Message::Pong { timestamp: 3 }));
+
+        let report = verifier.finish();
+        assert_eq!((report.matched, report.missing, report.unexpected), (2, 1, 1));
+        assert!(!report.is_clean());
+        assert_eq!(report.details.len(), 2);
+    }
+}
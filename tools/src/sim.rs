@@ -0,0 +1,716 @@
+//! # Dongle Simulator
+//!
+//! Plays the ESP32 dongle's side of the bridge protocol over TCP so the
+//! Android side can be developed and tested without hardware.
+//!
+//! ```text
+//!   phone ──TCP 5288──► Simulator ─► DongleSession::handle ─► replies
+//!                          │
+//!                          └─ second client while one is active: closed at once
+//! ```
+//!
+//! - `HandshakeRequest` is answered with `HandshakeResponse` and a fresh
+//!   session ID. If both sides offer `FEATURE_FLOW_CONTROL`, a
+//!   `WindowUpdate` opening every channel follows.
+//! - `Ping` is answered with `Pong`, `StatsRequest` with `StatsResponse`.
+//! - `Data` is echoed back on its channel or sunk, and credits are handed
+//!   back as it drains, exactly like a receiver using [`CreditGrantor`].
+//! - `Disconnect` closes the connection.
+//! - Anything but a handshake or ping before the handshake gets a `Nack`.
+//!
+//! Like the firmware, only one client is served at a time; additional
+//! connections are accepted and closed immediately.
+//!
+//! A [`Scenario`] makes the simulator misbehave on purpose (see
+//! [`crate::fault`]); without one it behaves like a healthy dongle.
+//! Progress lines go to the [`Log`] given to [`Simulator::with_log`] and
+//! are discarded otherwise.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use shared::batch::parse_frames;
+use shared::flow::{CreditGrantor, FlowConfig, FlowError, FEATURE_FLOW_CONTROL, MAX_CHANNELS};
+use shared::protocol::{
+    ControlMessage, FrameBuilder, Header, Message, FRAME_OVERHEAD, MAX_PAYLOAD_SIZE,
+};
+
+use crate::deframe::{Deframed, Deframer};
+use crate::fault::{FaultInjector, Plan, Scenario};
+
+/// Default TCP port, as served by the firmware
+pub const DEFAULT_PORT: u16 = 5288;
+
+/// Highest protocol version the simulator speaks
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// `Nack` error: message not allowed before the handshake
+pub const NACK_NOT_READY: u8 = 0x01;
+
+/// `Nack` error: `Data` exceeded the granted window
+pub const NACK_WINDOW_EXCEEDED: u8 = 0x02;
+
+/// `Nack` error: `Data` on a channel the flow controller does not track
+pub const NACK_UNKNOWN_CHANNEL: u8 = 0x03;
+
+/// Simulator behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimConfig {
+    /// Feature bits offered in `HandshakeResponse` (masked with the request)
+    pub features: u32,
+    /// Echo `Data` back instead of sinking it
+    pub echo: bool,
+    /// Credit windows granted to the client
+    pub flow: FlowConfig,
+    /// Log every frame
+    pub verbose: bool,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            features: 0xFF,
+            echo: true,
+            flow: FlowConfig::default(),
+            verbose: false,
+        }
+    }
+}
+
+/// Per-connection counters, reported in `StatsResponse`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SimStats {
+    /// Bytes received from the client
+    pub bytes_rx: u64,
+    /// Bytes sent to the client
+    pub bytes_tx: u64,
+    /// Frames that failed the CRC check or did not decode
+    pub packets_dropped: u32,
+}
+
+/// Something the connection loop must do in response to a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// Send a message on a channel
+    Send {
+        /// Frame channel
+        channel: u8,
+        /// Message to send
+        msg: Box<Message>,
+    },
+    /// Close the connection
+    Close,
+}
+
+impl Reply {
+    /// Send `msg` on `channel`
+    pub fn send(channel: u8, msg: Message) -> Self {
+        Reply::Send {
+            channel,
+            msg: Box::new(msg),
+        }
+    }
+}
+
+/// Protocol state of one client connection
+pub struct DongleSession {
+    config: SimConfig,
+    session_id: u32,
+    established: bool,
+    flow_control: bool,
+    grantor: CreditGrantor,
+    stats: SimStats,
+}
+
+impl DongleSession {
+    /// Create the state for a new connection
+    pub fn new(config: SimConfig, session_id: u32) -> Self {
+        Self {
+            config,
+            session_id,
+            established: false,
+            flow_control: false,
+            grantor: CreditGrantor::new(config.flow),
+            stats: SimStats::default(),
+        }
+    }
+
+    /// Session ID handed out in the handshake
+    pub fn session_id(&self) -> u32 {
+        self.session_id
+    }
+
+    /// Check if the handshake has completed
+    pub fn is_established(&self) -> bool {
+        self.established
+    }
+
+    /// Get the connection counters
+    pub fn stats(&self) -> &SimStats {
+        &self.stats
+    }
+
+    /// Account for bytes read from the client
+    pub fn on_received(&mut self, len: usize) {
+        self.stats.bytes_rx += len as u64;
+    }
+
+    /// Account for bytes written to the client
+    pub fn on_sent(&mut self, len: usize) {
+        self.stats.bytes_tx += len as u64;
+    }
+
+    /// Account for a frame that could not be used
+    pub fn on_dropped(&mut self) {
+        self.stats.packets_dropped += 1;
+    }
+
+    /// Handle one message from the client
+    pub fn handle(&mut self, header: Header, msg: Message, replies: &mut Vec<Reply>) {
+        let channel = header.channel;
+        match msg {
+            Message::Control(ControlMessage::HandshakeRequest { version, features }) => {
+                let features = features & self.config.features;
+                self.established = true;
+                self.flow_control = features & FEATURE_FLOW_CONTROL != 0;
+                replies.push(Reply::send(
+                    channel,
+                    Message::Control(ControlMessage::HandshakeResponse {
+                        version: version.min(PROTOCOL_VERSION),
+                        features,
+                        session_id: self.session_id,
+                    }),
+                ));
+                for ch in 0..MAX_CHANNELS as u8 {
+                    self.open(ch, replies);
+                }
+            }
+            Message::Ping { timestamp } => {
+                replies.push(Reply::send(channel, Message::Pong { timestamp }))
+            }
+            _ if !self.established => replies.push(Reply::send(
+                channel,
+                Message::Nack {
+                    sequence: header.sequence,
+                    error: NACK_NOT_READY,
+                },
+            )),
+            Message::Control(ControlMessage::StartStream {
+                video_channel,
+                audio_channel,
+                input_channel,
+            }) => {
+                for ch in [video_channel, audio_channel, input_channel] {
+                    self.open(ch, replies);
+                }
+            }
+            Message::Control(ControlMessage::StatsRequest) => replies.push(Reply::send(
+                channel,
+                Message::Control(ControlMessage::StatsResponse {
+                    bytes_rx: self.stats.bytes_rx,
+                    bytes_tx: self.stats.bytes_tx,
+                    packets_dropped: self.stats.packets_dropped,
+                }),
+            )),
+            Message::Control(ControlMessage::Disconnect { .. }) => replies.push(Reply::Close),
+            Message::Data(payload) => {
+                let len = payload.len();
+                let accepted = if self.flow_control {
+                    self.grantor.on_received(channel, len)
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = accepted {
+                    self.stats.packets_dropped += 1;
+                    let error = match e {
+                        FlowError::UnknownChannel => NACK_UNKNOWN_CHANNEL,
+                        _ => NACK_WINDOW_EXCEEDED,
+                    };
+                    replies.push(Reply::send(
+                        channel,
+                        Message::Nack {
+                            sequence: header.sequence,
+                            error,
+                        },
+                    ));
+                    return;
+                }
+                if self.config.echo {
+                    replies.push(Reply::send(channel, Message::Data(payload)));
+                }
+                // Echoed or sunk, the data has left the simulator's buffer
+                if !self.flow_control {
+                    return;
+                }
+                if let Some(update) = self.grantor.on_drained(channel, len) {
+                    replies.push(Reply::send(0, Message::Control(update)));
+                }
+            }
+            // StopStream, window updates from the client, acks, ...
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, channel: u8, replies: &mut Vec<Reply>) {
+        if !self.flow_control {
+            return;
+        }
+        if let Ok(update) = self.grantor.open(channel) {
+            replies.push(Reply::send(0, Message::Control(update)));
+        }
+    }
+}
+
+/// Read poll interval while time-based faults are pending
+const TIMER_TICK: Duration = Duration::from_millis(50);
+
+/// Destination for the simulator's progress lines; discards them by default
+#[derive(Clone, Default)]
+pub struct Log(Option<Arc<Mutex<dyn Write + Send>>>);
+
+impl Log {
+    /// Write every line to `writer`
+    pub fn to(writer: impl Write + Send + 'static) -> Self {
+        Self(Some(Arc::new(Mutex::new(writer))))
+    }
+
+    /// Write one line; a failing writer is ignored
+    pub fn line(&self, args: fmt::Arguments) {
+        if let Some(writer) = &self.0 {
+            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(writer, "{args}");
+        }
+    }
+}
+
+/// Abort the connection with a TCP reset instead of an orderly close
+fn reset(stream: TcpStream, log: &Log) -> io::Result<()> {
+    log.line(format_args!("[FAULT] Resetting connection"));
+    socket2::SockRef::from(&stream).set_linger(Some(Duration::ZERO))?;
+    drop(stream);
+    Ok(())
+}
+
+/// Apply the connection-level part of a plan; `false` once the connection is gone
+fn apply_connection_faults(plan: &Plan, log: &Log) -> bool {
+    if plan.stall_ms > 0 {
+        log.line(format_args!(
+            "[FAULT] Stalling reads for {} ms",
+            plan.stall_ms
+        ));
+        thread::sleep(Duration::from_millis(plan.stall_ms));
+    }
+    !plan.reset
+}
+
+/// Serve one client until it disconnects, misbehaving as `faults` dictates
+pub fn serve_connection(
+    mut stream: TcpStream,
+    session: &mut DongleSession,
+    faults: &mut FaultInjector,
+    log: &Log,
+) -> io::Result<()> {
+    let verbose = session.config.verbose;
+    let mut builder = FrameBuilder::new();
+    let mut deframer = Deframer::new();
+    let mut replies = Vec::new();
+    let mut buf = vec![0u8; 4096];
+    let mut out = vec![0u8; MAX_PAYLOAD_SIZE + FRAME_OVERHEAD + 16];
+    let mut frame_out = Vec::new();
+    let started = Instant::now();
+    if faults.has_timers() {
+        stream.set_read_timeout(Some(TIMER_TICK))?;
+    }
+
+    loop {
+        let plan = faults.on_tick(started.elapsed().as_millis() as u64);
+        if !apply_connection_faults(&plan, log) {
+            return reset(stream, log);
+        }
+
+        let n = match stream.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+        session.on_received(n);
+        deframer.push(&buf[..n]);
+        if !apply_connection_faults(&faults.on_read(), log) {
+            return reset(stream, log);
+        }
+
+        while let Some(item) = deframer.next_frame() {
+            let frame = match item {
+                Deframed::Frame { bytes, .. } => bytes,
+                Deframed::CrcError { .. } => {
+                    log.line(format_args!("[WARN] CRC error, frame dropped"));
+                    session.on_dropped();
+                    continue;
+                }
+                Deframed::Skipped { len, .. } => {
+                    log.line(format_args!("[WARN] Skipped {len} bytes of garbage"));
+                    continue;
+                }
+            };
+
+            let plan = faults.on_frame();
+            if !apply_connection_faults(&plan, log) {
+                return reset(stream, log);
+            }
+            if plan.drop {
+                log.line(format_args!("[FAULT] Ignoring received frame"));
+                continue;
+            }
+
+            let Ok(items) = parse_frames(&frame) else {
+                session.on_dropped();
+                continue;
+            };
+            for item in items {
+                match item {
+                    Ok((header, msg)) => {
+                        if verbose {
+                            log.line(format_args!(
+                                "[RX] seq={} ch={} {:?}",
+                                header.sequence,
+                                header.channel,
+                                msg.message_type()
+                            ));
+                        }
+                        session.handle(header, msg, &mut replies);
+                    }
+                    Err(_) => session.on_dropped(),
+                }
+            }
+        }
+
+        for reply in replies.drain(..) {
+            match reply {
+                Reply::Send { channel, mut msg } => {
+                    let plan = faults.on_reply(&msg);
+                    if !plan.is_noop() {
+                        log.line(format_args!("[FAULT] {:?}: {plan:?}", msg.message_type()));
+                    }
+                    if !apply_connection_faults(&plan, log) {
+                        return reset(stream, log);
+                    }
+                    if plan.drop {
+                        continue;
+                    }
+                    if plan.delay_ms > 0 {
+                        thread::sleep(Duration::from_millis(plan.delay_ms));
+                    }
+
+                    plan.apply_to_message(&mut msg);
+                    let len = builder.build_frame(&msg, channel, &mut out).map_err(|e| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}"))
+                    })?;
+                    frame_out.clear();
+                    frame_out.extend_from_slice(&out[..len]);
+                    plan.apply_to_frame(&mut frame_out);
+
+                    if verbose {
+                        log.line(format_args!("[TX] ch={} {:?}", channel, msg.message_type()));
+                    }
+                    stream.write_all(&frame_out)?;
+                    session.on_sent(frame_out.len());
+                }
+                Reply::Close => {
+                    log.line(format_args!("[INFO] Client requested disconnect"));
+                    return stream.shutdown(Shutdown::Both);
+                }
+            }
+        }
+    }
+}
+
+/// Accepts clients and serves them one at a time
+pub struct Simulator {
+    config: SimConfig,
+    scenario: Arc<Scenario>,
+    active: Arc<AtomicBool>,
+    next_session: Arc<AtomicU32>,
+    log: Log,
+}
+
+impl Simulator {
+    /// Create a simulator; session IDs start at `first_session`
+    pub fn new(config: SimConfig, first_session: u32) -> Self {
+        Self {
+            config,
+            scenario: Arc::new(Scenario::default()),
+            active: Arc::new(AtomicBool::new(false)),
+            next_session: Arc::new(AtomicU32::new(first_session)),
+            log: Log::default(),
+        }
+    }
+
+    /// Inject the scenario's faults into every connection
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = Arc::new(scenario);
+        self
+    }
+
+    /// Report connections, faults and (if verbose) traffic to `log`
+    pub fn with_log(mut self, log: Log) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Accept connections forever
+    pub fn run(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let peer = stream.peer_addr()?;
+
+            if self.active.swap(true, Ordering::AcqRel) {
+                // Already have a client, reject new one
+                let _ = stream.shutdown(Shutdown::Both);
+                self.log.line(format_args!(
+                    "[WARN] Rejected additional client connection from {peer}"
+                ));
+                continue;
+            }
+
+            self.log
+                .line(format_args!("[INFO] Client connected: {peer}"));
+            let _ = stream.set_nodelay(true);
+            let active = Arc::clone(&self.active);
+            let scenario = Arc::clone(&self.scenario);
+            let session_id = self.next_session.fetch_add(1, Ordering::Relaxed);
+            let config = self.config;
+            let log = self.log.clone();
+            thread::spawn(move || {
+                let mut session = DongleSession::new(config, session_id);
+                // Re-seeded per connection so every client sees the same failures
+                let mut faults = FaultInjector::new(&scenario);
+                if let Err(e) = serve_connection(stream, &mut session, &mut faults, &log) {
+                    log.line(format_args!("[WARN] Connection error: {e}"));
+                }
+                let stats = session.stats();
+                log.line(format_args!(
+                    "[INFO] Client disconnected (rx {} bytes, tx {} bytes, dropped {})",
+                    stats.bytes_rx, stats.bytes_tx, stats.packets_dropped
+                ));
+                active.store(false, Ordering::Release);
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(session: &mut DongleSession) -> Vec<Reply> {
+        let mut replies = Vec::new();
+        let msg = Message::Control(ControlMessage::HandshakeRequest {
+            version: 3,
+            features: 0x0F,
+        });
+        session.handle(Header::new(0, 0, 0), msg, &mut replies);
+        replies
+    }
+
+    #[test]
+    fn test_handshake_opens_windows() {
+        let mut session = DongleSession::new(SimConfig::default(), 77);
+        let replies = handshake(&mut session);
+
+        assert!(session.is_established());
+        assert_eq!(
+            replies[0],
+            Reply::send(
+                0,
+                Message::Control(ControlMessage::HandshakeResponse {
+                    version: PROTOCOL_VERSION,
+                    features: 0x0F,
+                    session_id: 77,
+                })
+            )
+        );
+        assert_eq!(replies.len(), 1 + MAX_CHANNELS);
+
+        // Without flow control no windows are opened or enforced
+        let mut session = DongleSession::new(SimConfig::default(), 78);
+        let mut replies = Vec::new();
+        let msg = Message::Control(ControlMessage::HandshakeRequest {
+            version: 1,
+            features: 0,
+        });
+        session.handle(Header::new(0, 0, 0), msg, &mut replies);
+        assert_eq!(replies.len(), 1);
+        let data = Message::Data(shared::DataPayload::new(&[0; 100]).unwrap());
+        session.handle(Header::new(1, 0, 2), data, &mut replies);
+        assert_eq!(session.stats().packets_dropped, 0);
+    }
+
+    #[test]
+    fn test_nack_before_handshake() {
+        let mut session = DongleSession::new(SimConfig::default(), 1);
+        let mut replies = Vec::new();
+        session.handle(
+            Header::new(5, 0, 0),
+            Message::Control(ControlMessage::StatsRequest),
+            &mut replies,
+        );
+        session.handle(
+            Header::new(6, 0, 0),
+            Message::Ping { timestamp: 9 },
+            &mut replies,
+        );
+
+        assert_eq!(
+            replies,
+            vec![
+                Reply::send(
+                    0,
+                    Message::Nack {
+                        sequence: 5,
+                        error: NACK_NOT_READY
+                    }
+                ),
+                Reply::send(0, Message::Pong { timestamp: 9 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_echo_and_credits() {
+        let config = SimConfig {
+            flow: FlowConfig {
+                initial_window: 8,
+                update_threshold: 4,
+            },
+            ..SimConfig::default()
+        };
+        let mut session = DongleSession::new(config, 1);
+        handshake(&mut session);
+
+        let data = |bytes: &[u8]| Message::Data(shared::DataPayload::new(bytes).unwrap());
+        let mut replies = Vec::new();
+        session.handle(Header::new(1, 0, 2), data(b"abcde"), &mut replies);
+        assert_eq!(replies[0], Reply::send(2, data(b"abcde")));
+        assert_eq!(
+            replies[1],
+            Reply::send(
+                0,
+                Message::Control(ControlMessage::WindowUpdate {
+                    channel: 2,
+                    credits: 5
+                })
+            )
+        );
+
+        // 5 of 8 + 5 returned = 8 outstanding; 9 bytes exceeds it
+        replies.clear();
+        session.handle(Header::new(2, 0, 2), data(b"123456789"), &mut replies);
+        assert_eq!(
+            replies[0],
+            Reply::send(
+                2,
+                Message::Nack {
+                    sequence: 2,
+                    error: NACK_WINDOW_EXCEEDED
+                }
+            )
+        );
+
+        // No window on an untracked channel: not an overrun
+        replies.clear();
+        let channel = MAX_CHANNELS as u8;
+        session.handle(Header::new(3, 0, channel), data(b"x"), &mut replies);
+        assert_eq!(
+            replies,
+            [Reply::send(
+                channel,
+                Message::Nack {
+                    sequence: 3,
+                    error: NACK_UNKNOWN_CHANNEL
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_rejects_second_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || Simulator::new(SimConfig::default(), 1).run(listener));
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+        let len = builder
+            .build_frame(&Message::Ping { timestamp: 4 }, 0, &mut buffer)
+            .unwrap();
+        first.write_all(&buffer[..len]).unwrap();
+        let n = first.read(&mut buffer).unwrap();
+        assert_eq!(
+            FrameBuilder::parse_frame(&buffer[..n]).unwrap().1,
+            Message::Pong { timestamp: 4 }
+        );
+
+        let mut second = TcpStream::connect(addr).unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        // Rejected with an orderly close, not an error
+        assert_eq!(second.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_scenario_faults() {
+        let scenario = Scenario::parse("pong unknown-type 0x42 times=1\nframe 2 reset").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            Simulator::new(SimConfig::default(), 1)
+                .with_scenario(scenario)
+                .run(listener)
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut builder = FrameBuilder::new();
+        let mut buffer = [0u8; 64];
+        let len = builder
+            .build_frame(&Message::Ping { timestamp: 4 }, 0, &mut buffer)
+            .unwrap();
+        client.write_all(&buffer[..len]).unwrap();
+        let n = client.read(&mut buffer).unwrap();
+        assert_eq!(
+            FrameBuilder::parse_raw_frame(&buffer[..n])
+                .unwrap()
+                .msg_type,
+            0x42
+        );
+
+        // Second frame triggers the reset
+        let len = builder
+            .build_frame(&Message::Ping { timestamp: 5 }, 0, &mut buffer)
+            .unwrap();
+        client.write_all(&buffer[..len]).unwrap();
+        let err = client.read(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+}